@@ -0,0 +1,249 @@
+use anyhow::Context;
+use aws_sdk_s3::{primitives::{ByteStream, SdkBody}, Client};
+use bytes::Bytes;
+use colorize::AnsiColor;
+use futures::future::join_all;
+use rand::{rngs::SmallRng, SeedableRng};
+use serde::Serialize;
+use std::{sync::{atomic::{AtomicU64, Ordering}, Arc}, time::{Duration, Instant}};
+use tokio_util::sync::CancellationToken;
+
+use crate::cli::AutoTuneArgs;
+use crate::interrupt;
+use crate::output::{self, status};
+use crate::stats::RunStats;
+use crate::{format_duration, generate_payload, new_duration_progress_bar, parse_count, parse_duration, parse_size, retry, track_progress, validate_duration, validate_number, validate_optional_number, validate_size, PayloadMode};
+
+// The first level tried, doubled after each burst
+const START_CONCURRENCY: u32 = 4;
+
+// A level has to beat the best throughput so far by this much to count as an improvement
+const PLATEAU_GAIN: f64 = 0.05;
+
+// Consecutive levels without an improvement before the ramp stops
+const PLATEAU_LEVELS: u32 = 2;
+
+// Results of one burst at a fixed worker count
+#[derive(Clone, Serialize)]
+struct LevelResult {
+    concurrency: u32,
+    objects_per_second: f64,
+    megabytes_per_second: f64,
+    p99_ms: f64,
+    failed: u64,
+}
+
+#[derive(Serialize)]
+struct AutoTuneReport {
+    bucket: String,
+    object_size: u64,
+    burst_seconds: u64,
+    levels: Vec<LevelResult>,
+    stop_reason: String,
+    // Level with the highest throughput that stayed within the p99 limit
+    peak: Option<LevelResult>,
+}
+
+// Settings and shared counters for every worker taking part in one burst
+struct BurstContext {
+    bucket_name: String,
+    prefix: String,
+    object_size: u64,
+    payload: Bytes,
+    deadline: Instant,
+    cancel: CancellationToken,
+    completed_count: Arc<AtomicU64>,
+    run_stats: RunStats,
+}
+
+#[tracing::instrument(skip_all, fields(bucket = %bucket_name))]
+pub async fn operation_auto_tune(s3_client: Client, bucket_name: String, args: AutoTuneArgs) -> anyhow::Result<()> {
+    let object_size = match args.size {
+        Some(size) => size,
+        None => {
+            let object_size = inquire::Text::new("What size should each object be? (eg. 4KB, 1MB, 10MB)")
+                .with_default("1KB")
+                .with_validator(validate_size)
+                .prompt()?;
+            parse_size(&object_size).context("Invalid object size")?
+        }
+    };
+
+    let burst = match args.burst {
+        Some(burst) => burst,
+        None => {
+            let burst = inquire::Text::new("How long should each concurrency level run? (eg. 10s, 1m)")
+                .with_default("10s")
+                .with_validator(validate_duration)
+                .prompt()?;
+            parse_duration(&burst).context("Invalid duration")?
+        }
+    };
+
+    let max_concurrency = match args.max_concurrency {
+        Some(max_concurrency) => max_concurrency,
+        None => {
+            let max_concurrency = inquire::Text::new("Stop ramping at how many workers?")
+                .with_default("512")
+                .with_validator(validate_number)
+                .prompt()?;
+            parse_count(&max_concurrency).context("Invalid worker count")?.max(1)
+        }
+    };
+
+    // Milliseconds, where 0 means there's no latency limit
+    let max_p99_ms = match args.max_p99_ms {
+        Some(max_p99_ms) => max_p99_ms,
+        None => {
+            let max_p99_ms = inquire::Text::new("Stop once p99 latency exceeds how many milliseconds? (leave blank for no limit)")
+                .with_validator(validate_optional_number)
+                .prompt()?;
+            parse_count(&max_p99_ms).unwrap_or_default()
+        }
+    };
+
+    let prefix = match args.prefix {
+        Some(prefix) => prefix,
+        None => inquire::Text::new("Key prefix for the objects written while tuning")
+            .with_default("autotune/")
+            .prompt()?,
+    };
+
+    let operation = interrupt::start_operation();
+    let cancel = operation.token();
+    let payload = generate_payload(&mut SmallRng::from_entropy(), PayloadMode::Random, object_size);
+
+    let mut report = AutoTuneReport {
+        bucket: bucket_name,
+        object_size,
+        burst_seconds: burst.as_secs(),
+        levels: vec![],
+        stop_reason: String::new(),
+        peak: None,
+    };
+    let mut stalled_levels = 0;
+    let mut concurrency = START_CONCURRENCY.min(max_concurrency);
+
+    loop {
+        status!("Running {0} workers for {1}", concurrency, format_duration(burst));
+        let context = Arc::new(BurstContext {
+            bucket_name: report.bucket.clone(),
+            prefix: prefix.clone(),
+            object_size,
+            payload: payload.clone(),
+            deadline: Instant::now() + burst,
+            cancel: cancel.clone(),
+            completed_count: Arc::new(AtomicU64::new(0)),
+            run_stats: RunStats::default(),
+        });
+        let level = run_burst(&s3_client, context, concurrency, burst).await;
+        if cancel.is_cancelled() {
+            report.stop_reason = "interrupted".to_string();
+            break;
+        }
+        print_level(&level);
+        report.levels.push(level.clone());
+
+        if max_p99_ms > 0 && level.p99_ms > max_p99_ms as f64 {
+            report.stop_reason = format!("p99 latency exceeded {0}ms at {1} workers", max_p99_ms, concurrency);
+            break;
+        }
+
+        let improved = report.peak.as_ref()
+            .is_none_or(|peak| level.objects_per_second > peak.objects_per_second * (1.0 + PLATEAU_GAIN));
+        if report.peak.as_ref().is_none_or(|peak| level.objects_per_second > peak.objects_per_second) {
+            report.peak = Some(level);
+        }
+        stalled_levels = if improved { 0 } else { stalled_levels + 1 };
+        if stalled_levels >= PLATEAU_LEVELS {
+            report.stop_reason = format!("throughput stopped improving after {0} workers", concurrency);
+            break;
+        }
+
+        if concurrency >= max_concurrency {
+            report.stop_reason = format!("reached the limit of {0} workers", max_concurrency);
+            break;
+        }
+        concurrency = concurrency.saturating_mul(2).min(max_concurrency);
+    }
+    interrupt::report_if_interrupted(&cancel);
+
+    print_report(&report, &prefix);
+    Ok(())
+}
+
+async fn run_burst(s3_client: &Client, context: Arc<BurstContext>, concurrency: u32, burst: Duration) -> LevelResult {
+    let progress = new_duration_progress_bar(burst);
+    let progress_task = tokio::spawn(track_progress(progress.clone(), context.completed_count.clone(), vec![context.run_stats.byte_counter()]));
+
+    let start_time = Instant::now();
+    let mut join_handle_list = vec![];
+    for _ in 0..concurrency {
+        join_handle_list.push(tokio::spawn(burst_worker(s3_client.clone(), context.clone())));
+    }
+    join_all(join_handle_list).await;
+
+    progress_task.abort();
+    progress.finish_and_clear();
+
+    let summary = context.run_stats.summarize(start_time.elapsed());
+    LevelResult {
+        concurrency,
+        objects_per_second: summary.objects_per_second(),
+        megabytes_per_second: summary.megabytes_per_second(),
+        p99_ms: summary.p99.as_secs_f64() * 1000.0,
+        failed: summary.failed_count,
+    }
+}
+
+async fn burst_worker(s3_client: Client, context: Arc<BurstContext>) {
+    while Instant::now() < context.deadline && !context.cancel.is_cancelled() {
+        let key = format!("{0}{1}", context.prefix, uuid::Uuid::new_v4());
+        let request_start = Instant::now();
+        let put_result = s3_client.put_object()
+            .bucket(&context.bucket_name)
+            .key(&key)
+            .body(ByteStream::new(SdkBody::from(context.payload.clone())))
+            .send().await;
+
+        match put_result {
+            Ok(_) => context.run_stats.record(request_start.elapsed(), context.object_size),
+            Err(err) => context.run_stats.record_failure(retry::error_reason(&err)),
+        }
+        context.completed_count.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+fn print_level(level: &LevelResult) {
+    // Padding is applied before coloring, since the escape codes would otherwise count towards the width
+    status!("  {0:>6} workers  {1} ops/s  {2} MB/s  p99 {3}  {4} failed",
+        level.concurrency,
+        format!("{0:>10.2}", level.objects_per_second).green(),
+        format!("{0:>8.2}", level.megabytes_per_second).green(),
+        format!("{0:>9.1}ms", level.p99_ms).yellow(),
+        level.failed.to_string().red());
+}
+
+fn print_report(report: &AutoTuneReport, prefix: &str) {
+    if output::is_json() {
+        println!("{0}", serde_json::to_string(report).unwrap());
+        return;
+    }
+
+    println!("{0}", format!("Auto-tune summary for {0}", report.bucket).bold());
+    println!("  {0:<14} {1}", "Stopped", report.stop_reason);
+    match &report.peak {
+        Some(peak) => {
+            println!("  {0:<14} {1}", "Best workers", peak.concurrency.to_string().green());
+            println!("  {0:<14} {1}", "Objects/sec", format!("{0:.2}", peak.objects_per_second).green());
+            println!("  {0:<14} {1}", "MB/sec", format!("{0:.2}", peak.megabytes_per_second).green());
+            println!("  {0:<14} {1}", "p99", format!("{0:.1}ms", peak.p99_ms).yellow());
+        }
+        None => println!("{0}", "No concurrency level completed within the limits".yellow()),
+    }
+    let cleanup_hint = match prefix.is_empty() {
+        true => "Objects were written to the root of the bucket, use cleanup to remove them".to_string(),
+        false => format!("Objects were written under {0}, use cleanup with --prefix {0} to remove them", prefix),
+    };
+    println!("{0}", cleanup_hint.blue());
+}
@@ -0,0 +1,180 @@
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::{collections::{BTreeMap, HashMap}, path::Path, sync::Mutex};
+
+// Cleanup progress is saved to this file in the working directory, as a JSON object keyed by bucket name
+const STATE_FILE: &str = "s3-stress-cleanup.json";
+
+// Where a listing should carry on from. Unversioned buckets are listed with a continuation token,
+// versioned buckets with a key and version ID marker.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ListPosition {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub continuation_token: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_marker: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version_id_marker: Option<String>,
+}
+
+// Saved state of an unfinished cleanup. Every key listed before the position has already been processed,
+// whether it was deleted or failed for good.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CleanupCheckpoint {
+    pub prefix: Option<String>,
+    pub position: ListPosition,
+    pub processed_count: u64,
+}
+
+type StateFile = BTreeMap<String, CleanupCheckpoint>;
+
+fn read_state_file() -> anyhow::Result<StateFile> {
+    if !Path::new(STATE_FILE).exists() {
+        return Ok(StateFile::new());
+    }
+    let contents = std::fs::read_to_string(STATE_FILE)
+        .with_context(|| format!("Unable to read cleanup state file {0}", STATE_FILE))?;
+    serde_json::from_str(&contents).with_context(|| format!("Invalid cleanup state file {0}", STATE_FILE))
+}
+
+// Replaces the checkpoint saved for a bucket, or removes it when the cleanup finished
+fn write_checkpoint(bucket_name: &str, checkpoint: Option<&CleanupCheckpoint>) -> anyhow::Result<()> {
+    let mut state = read_state_file()?;
+    match checkpoint {
+        Some(checkpoint) => state.insert(bucket_name.to_string(), checkpoint.clone()),
+        None => state.remove(bucket_name),
+    };
+    let contents = serde_json::to_string_pretty(&state)?;
+    std::fs::write(STATE_FILE, contents).with_context(|| format!("Unable to write cleanup state file {0}", STATE_FILE))
+}
+
+pub fn load(bucket_name: &str) -> anyhow::Result<Option<CleanupCheckpoint>> {
+    Ok(read_state_file()?.remove(bucket_name))
+}
+
+pub fn clear(bucket_name: &str) -> anyhow::Result<()> {
+    write_checkpoint(bucket_name, None)
+}
+
+// A listed page whose keys haven't all been processed yet
+struct PendingPage {
+    // Where the listing continues after this page
+    next_position: ListPosition,
+    remaining: u64,
+    key_count: u64,
+}
+
+struct TrackerState {
+    next_page: u64,
+    pages: BTreeMap<u64, PendingPage>,
+    checkpoint: CleanupCheckpoint,
+}
+
+// Tracks which listed pages have been fully processed. Delete workers finish batches out of order,
+// so the saved position only moves past a page once it and every page before it are done.
+pub struct CheckpointTracker {
+    bucket_name: String,
+    state: Mutex<TrackerState>,
+}
+
+impl CheckpointTracker {
+    pub fn new(bucket_name: &str, checkpoint: CleanupCheckpoint) -> CheckpointTracker {
+        CheckpointTracker {
+            bucket_name: bucket_name.to_string(),
+            state: Mutex::new(TrackerState { next_page: 0, pages: BTreeMap::new(), checkpoint }),
+        }
+    }
+
+    // Registers a newly listed page, returning the number its keys are tagged with
+    pub fn start_page(&self, next_position: ListPosition, key_count: u64) -> anyhow::Result<u64> {
+        let page = {
+            let mut state = self.state.lock().unwrap();
+            let page = state.next_page;
+            state.next_page += 1;
+            state.pages.insert(page, PendingPage { next_position, remaining: key_count, key_count });
+            page
+        };
+        // A page with nothing on it is done as soon as it's listed
+        match key_count {
+            0 => self.complete(&HashMap::from([(page, 0)])).map(|_| page),
+            _ => Ok(page),
+        }
+    }
+
+    // Marks keys as processed, given as a count per page, and saves the checkpoint if that finishes
+    // the oldest outstanding pages
+    pub fn complete(&self, page_counts: &HashMap<u64, u64>) -> anyhow::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        for (page, count) in page_counts {
+            if let Some(pending) = state.pages.get_mut(page) {
+                pending.remaining = pending.remaining.saturating_sub(*count);
+            }
+        }
+
+        let mut advanced = false;
+        while let Some(entry) = state.pages.first_entry() {
+            if entry.get().remaining > 0 {
+                break;
+            }
+            let finished = entry.remove();
+            state.checkpoint.position = finished.next_position;
+            state.checkpoint.processed_count += finished.key_count;
+            advanced = true;
+        }
+
+        // The file is written while still holding the lock, so an older checkpoint can never overwrite a newer one
+        match advanced {
+            true => write_checkpoint(&self.bucket_name, Some(&state.checkpoint)),
+            false => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(token: &str) -> ListPosition {
+        ListPosition { continuation_token: Some(token.to_string()), ..Default::default() }
+    }
+
+    fn saved_token(bucket_name: &str) -> Option<String> {
+        load(bucket_name).unwrap().and_then(|checkpoint| checkpoint.position.continuation_token)
+    }
+
+    #[test]
+    fn tracker_only_saves_past_contiguous_completed_pages() {
+        let bucket_name = "s3-stress-checkpoint-test-out-of-order";
+        let tracker = CheckpointTracker::new(bucket_name, CleanupCheckpoint::default());
+        let first = tracker.start_page(position("after-0"), 2).unwrap();
+        let second = tracker.start_page(position("after-1"), 3).unwrap();
+        let third = tracker.start_page(position("after-2"), 1).unwrap();
+
+        // The later pages finishing first leaves the saved position where it was
+        tracker.complete(&HashMap::from([(third, 1), (second, 3)])).unwrap();
+        assert_eq!(saved_token(bucket_name), None);
+
+        // Part of the first page isn't enough either
+        tracker.complete(&HashMap::from([(first, 1)])).unwrap();
+        assert_eq!(saved_token(bucket_name), None);
+
+        // Finishing the first page moves the position past all three at once
+        tracker.complete(&HashMap::from([(first, 1)])).unwrap();
+        let saved = load(bucket_name).unwrap().unwrap();
+        assert_eq!(saved.position.continuation_token.as_deref(), Some("after-2"));
+        assert_eq!(saved.processed_count, 6);
+
+        // A page finished while an earlier one is outstanding waits for it
+        let fourth = tracker.start_page(position("after-3"), 1).unwrap();
+        let fifth = tracker.start_page(position("after-4"), 1).unwrap();
+        tracker.complete(&HashMap::from([(fifth, 1)])).unwrap();
+        assert_eq!(saved_token(bucket_name).as_deref(), Some("after-2"));
+        tracker.complete(&HashMap::from([(fourth, 1)])).unwrap();
+        assert_eq!(saved_token(bucket_name).as_deref(), Some("after-4"));
+
+        clear(bucket_name).unwrap();
+        if read_state_file().unwrap().is_empty() {
+            std::fs::remove_file(STATE_FILE).unwrap();
+        }
+    }
+}
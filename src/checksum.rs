@@ -0,0 +1,83 @@
+use aws_sdk_s3::types::ChecksumAlgorithm;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use sha2::{Digest, Sha256};
+
+// Content checksum computed over each object body and sent with the upload, so the backend
+// rejects anything that arrives corrupted
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Checksum {
+    None,
+    Crc32c,
+    Sha256,
+}
+
+impl Checksum {
+    pub fn all() -> Vec<Checksum> {
+        vec![Checksum::None, Checksum::Crc32c, Checksum::Sha256]
+    }
+
+    pub fn algorithm(self) -> Option<ChecksumAlgorithm> {
+        match self {
+            Checksum::None => None,
+            Checksum::Crc32c => Some(ChecksumAlgorithm::Crc32C),
+            Checksum::Sha256 => Some(ChecksumAlgorithm::Sha256),
+        }
+    }
+
+    // Raw checksum bytes of a body, before the base64 encoding S3 expects in headers
+    fn digest(self, body: &[u8]) -> Vec<u8> {
+        match self {
+            Checksum::None => vec![],
+            Checksum::Crc32c => crc32c::crc32c(body).to_be_bytes().to_vec(),
+            Checksum::Sha256 => Sha256::digest(body).to_vec(),
+        }
+    }
+
+    // Base64 checksum of a single body, as sent with a PUT or an individual part
+    pub fn compute(self, body: &[u8]) -> Option<String> {
+        match self {
+            Checksum::None => None,
+            _ => Some(STANDARD.encode(self.digest(body))),
+        }
+    }
+
+    // Checksum S3 reports for a multipart object: the checksum of the concatenated part checksums,
+    // followed by the part count
+    pub fn compute_composite(self, part_checksums: &[String]) -> Option<String> {
+        let mut joined_digests = vec![];
+        for part_checksum in part_checksums {
+            joined_digests.extend(STANDARD.decode(part_checksum).ok()?);
+        }
+        let composite = self.compute(&joined_digests)?;
+        Some(format!("{0}-{1}", composite, part_checksums.len()))
+    }
+
+    // Splits a checksum into the (CRC32C, SHA256) pair of optional header values the SDK builders take
+    pub fn header_values(self, value: Option<String>) -> (Option<String>, Option<String>) {
+        match self {
+            Checksum::None => (None, None),
+            Checksum::Crc32c => (value, None),
+            Checksum::Sha256 => (None, value),
+        }
+    }
+
+    // Picks the returned checksum matching this algorithm out of a response's (CRC32C, SHA256) values
+    pub fn returned_value(self, crc32c: Option<String>, sha256: Option<String>) -> Option<String> {
+        match self {
+            Checksum::None => None,
+            Checksum::Crc32c => crc32c,
+            Checksum::Sha256 => sha256,
+        }
+    }
+}
+
+impl std::fmt::Display for Checksum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Checksum::None => "None",
+            Checksum::Crc32c => "CRC32C",
+            Checksum::Sha256 => "SHA256",
+        };
+        write!(f, "{0}", name)
+    }
+}
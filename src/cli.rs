@@ -0,0 +1,763 @@
+use aws_sdk_s3::types::{BucketCannedAcl, ObjectCannedAcl, StorageClass};
+use clap::{Args, Parser, Subcommand};
+use std::{path::PathBuf, time::Duration};
+
+use crate::checksum::Checksum;
+use crate::connection::ConnectionStrategy;
+use crate::logging::LogLevel;
+use crate::output::OutputFormat;
+use crate::presign::PresignMethod;
+use crate::report::ReportFormat;
+use crate::read::ReadOrder;
+use crate::sizes::SizeDistribution;
+
+use crate::{Encryption, KeyNaming, Keyspace, PayloadMode, RunLength, MAX_SPREAD_PREFIXES};
+
+// Kept in step with failure::ErrorKind::exit_code
+const EXIT_CODES: &str = "Exit codes: 0 success, 1 other error, 2 invalid arguments, 3 authentication, 4 permission denied, \
+5 not found, 6 throttled, 7 network, 130 cancelled";
+
+/// An interactive CLI tool that lets you perform various operations against the Amazon S3 service APIs.
+///
+/// Run without a subcommand to use the interactive menu. Any option that isn't supplied on the
+/// command line is prompted for, so partial invocations still work.
+#[derive(Parser, Debug)]
+#[command(version, about, after_help = EXIT_CODES)]
+pub struct Cli {
+    #[command(flatten)]
+    pub global: GlobalArgs,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Args, Clone, Debug, Default)]
+pub struct GlobalArgs {
+    /// TOML file of run parameters. Command line flags take precedence over the file, and
+    /// anything set in neither place is prompted for
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+
+    /// JSON file listing operations to run one after another, each with its own options, followed by
+    /// a combined summary. Steps fail the scenario unless they set continue_on_error
+    #[arg(long)]
+    pub scenario: Option<PathBuf>,
+
+    /// Named AWS profile to authenticate with, skipping the authentication prompt
+    #[arg(long, global = true)]
+    pub profile: Option<String>,
+
+    /// AWS credentials file to read profiles from, instead of ~/.aws/credentials
+    #[arg(long, global = true)]
+    pub credentials_file: Option<PathBuf>,
+
+    /// AWS shared config file to read profiles and SSO sessions from, instead of ~/.aws/config
+    #[arg(long, global = true)]
+    pub config_file: Option<PathBuf>,
+
+    /// AWS region to use, and the location for new buckets. Skips looking up each bucket's region
+    #[arg(long, global = true)]
+    pub region: Option<String>,
+
+    /// Automatically confirm destructive operations, such as deleting objects or buckets
+    #[arg(short, long, global = true)]
+    pub yes: bool,
+
+    /// Idle HTTP connections kept open for reuse. Unlimited by default, so the pool grows with the worker count
+    #[arg(long, global = true, value_parser = clap::value_parser!(u64).range(1..))]
+    pub max_connections: Option<u64>,
+
+    /// Seconds allowed to establish a connection, eg. 0.5. The SDK default is 3.1
+    #[arg(long, global = true, value_parser = parse_seconds)]
+    pub connect_timeout: Option<Duration>,
+
+    /// Seconds allowed for each request including retries. Unlimited by default
+    #[arg(long, global = true, value_parser = parse_seconds)]
+    pub operation_timeout: Option<Duration>,
+
+    /// Report what create objects or create bucket would add, what cleanup or delete bucket would remove, or which
+    /// lifecycle rule would be applied, without changing anything
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+
+    /// Accept the charges for requests to requester pays buckets, which otherwise refuse access with a 403
+    #[arg(long, global = true)]
+    pub requester_pays: bool,
+
+    /// Send requests through the bucket's S3 Transfer Acceleration endpoint. Buckets that don't have
+    /// acceleration enabled fall back to the regular endpoint with a warning
+    #[arg(long, global = true)]
+    pub accelerate: bool,
+
+    /// Only compute and validate checksums when an operation requires them, instead of the SDK's default CRC32
+    /// on every upload and download. Saves client CPU when benchmarking raw throughput, at the cost of not
+    /// detecting corrupted transfers unless --checksum or --verify is used
+    #[arg(long, global = true)]
+    pub no_sdk_checksums: bool,
+
+    /// Use the --bucket name as given, without first checking that the bucket exists. Useful when
+    /// HeadBucket isn't allowed, such as some cross-account access or S3-compatible endpoints
+    #[arg(long, global = true)]
+    pub skip_bucket_check: bool,
+
+    /// Output format for run results. JSON mode prints a single report and suppresses decorative output
+    #[arg(long, value_enum, global = true, default_value_t)]
+    pub output: OutputFormat,
+
+    /// Diagnostic logging written to stderr. Debug logs every request and retry, trace adds the SDK's internals
+    #[arg(long, value_enum, global = true, default_value_t)]
+    pub log_level: LogLevel,
+
+    /// Shorthand for --log-level, -v for debug and -vv for trace
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    pub verbose: u8,
+
+    /// Write every create and read request to this CSV file, with its start time, key, size, latency and outcome
+    #[arg(long, global = true)]
+    pub latency_out: Option<PathBuf>,
+
+    /// Append a structured report of every run's results to this file, alongside the normal output
+    #[arg(long, global = true)]
+    pub report_out: Option<PathBuf>,
+
+    /// Format of the --report-out file, one JSON object per line or CSV rows with a header
+    #[arg(long, value_enum, global = true, default_value_t, requires = "report_out")]
+    pub report_format: ReportFormat,
+
+    /// Storage price in USD per GB-month for the cost estimate, instead of the built-in us-east-1 price
+    #[arg(long, global = true, value_parser = parse_price)]
+    pub price_per_gb_month: Option<f64>,
+
+    /// Price in USD per 1,000 PUT or LIST requests for the cost estimate, instead of the built-in us-east-1 price
+    #[arg(long = "price-per-1000-requests", global = true, value_parser = parse_price)]
+    pub price_per_thousand_requests: Option<f64>,
+
+    /// Don't save the buckets and values chosen at the prompts, which are otherwise offered as defaults next run
+    #[arg(long, global = true)]
+    pub no_save: bool,
+}
+
+// Only one command is parsed per run, so the size of the largest variant doesn't matter
+#[allow(clippy::large_enum_variant)]
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Create objects in a bucket
+    CreateObjects(CreateObjectsArgs),
+    /// Ramp up the worker count in short bursts of writes to find the concurrency with the highest throughput
+    AutoTune(AutoTuneArgs),
+    /// Report a bucket's object count, total size and storage class distribution
+    BucketInfo(BucketInfoArgs),
+    /// Delete every object in a bucket, or only those under a prefix, and abort incomplete multipart uploads
+    Cleanup(CleanupArgs),
+    /// Write objects and immediately read them back to check read-after-write consistency
+    ConsistencyTest(ConsistencyTestArgs),
+    /// Copy objects within a bucket using server-side copy
+    CopyObjects(CopyObjectsArgs),
+    /// Copy every object in a bucket, or only those under a prefix, to the same keys in another bucket
+    MirrorBucket(MirrorBucketArgs),
+    /// Page through a listing repeatedly at several page sizes and report pages/sec, keys/sec and page latency
+    ListBenchmark(ListBenchmarkArgs),
+    /// Create a new bucket
+    CreateBucket(CreateBucketArgs),
+    /// Delete a bucket, which must be empty unless --force is given
+    DeleteBucket(DeleteBucketArgs),
+    /// Download objects from a bucket
+    ReadObjects(ReadObjectsArgs),
+    /// Download objects to a local directory, mirroring the key structure as subdirectories
+    DownloadDirectory(DownloadDirectoryArgs),
+    /// Send HEAD requests for objects in a bucket
+    HeadObjects(HeadObjectsArgs),
+    /// Apply a lifecycle rule that expires objects or aborts incomplete multipart uploads
+    Lifecycle(LifecycleArgs),
+    /// Run a blend of reads and writes against a bucket
+    MixedWorkload(MixedWorkloadArgs),
+    /// Sign PUT or GET URLs for a set of keys, then send load to them with a plain HTTP client
+    Presigned(PresignArgs),
+    /// Upload the files in a local directory, keyed by their relative paths
+    UploadDirectory(UploadDirectoryArgs),
+}
+
+#[derive(Args, Debug, Default)]
+pub struct BucketInfoArgs {
+    /// Name of the bucket to report on
+    #[arg(long)]
+    pub bucket: Option<String>,
+
+    /// Only count objects whose keys start with this prefix
+    #[arg(long)]
+    pub prefix: Option<String>,
+
+    /// Stop scanning after this many objects, 0 for no limit. The totals are then a lower bound
+    #[arg(long)]
+    pub max_objects: Option<u64>,
+}
+
+#[derive(Args, Debug, Default)]
+pub struct CreateBucketArgs {
+    /// Name of the bucket to create
+    #[arg(long)]
+    pub bucket: Option<String>,
+
+    /// Enable versioning on the new bucket
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    pub versioning: Option<bool>,
+
+    /// Canned ACL for the new bucket, eg. private, public-read
+    #[arg(long, value_parser = parse_bucket_acl)]
+    pub acl: Option<BucketCannedAcl>,
+
+    /// Create a bucket in each of these regions, eg. us-east-1,eu-west-1. Each bucket name is suffixed with its region
+    #[arg(long, value_delimiter = ',')]
+    pub regions: Vec<String>,
+}
+
+#[derive(Args, Debug, Default)]
+pub struct DeleteBucketArgs {
+    /// Name of the bucket to delete
+    #[arg(long)]
+    pub bucket: Option<String>,
+
+    /// Delete every object, version and delete marker in the bucket first
+    #[arg(long)]
+    pub force: bool,
+
+    /// Cleanup passes to run with --force before giving up, when listing again still finds objects left behind
+    #[arg(long, requires = "force", value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_passes: Option<u32>,
+
+    /// Maximum retries for each throttled or failed DeleteObjects batch with --force, from 0 to 20. Defaults to 3
+    #[arg(long, requires = "force", value_parser = clap::value_parser!(u32).range(0..=crate::retry::MAX_RETRIES as i64))]
+    pub retries: Option<u32>,
+}
+
+#[derive(Args, Debug, Default)]
+pub struct CleanupArgs {
+    /// Name of the bucket to clean up
+    #[arg(long)]
+    pub bucket: Option<String>,
+
+    /// Only delete objects whose keys start with this prefix, eg. stress/2024/
+    #[arg(long)]
+    pub prefix: Option<String>,
+
+    /// Number of concurrent delete workers, and the most DeleteObjects requests in flight. Lowered
+    /// automatically while S3 is throttling with SlowDown, then raised again as requests succeed
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub concurrency: Option<u32>,
+
+    /// Keys listed per page, up to 1000. Smaller pages are only useful for exercising pagination
+    #[arg(long, value_parser = clap::value_parser!(i32).range(1..=1000))]
+    pub page_size: Option<i32>,
+
+    /// Only delete objects last modified longer ago than this, eg. 12h, 7d. Incomplete multipart uploads are left alone
+    #[arg(long, value_parser = parse_run_duration)]
+    pub older_than: Option<Duration>,
+
+    /// With --dry-run, print every key that would be deleted
+    #[arg(long)]
+    pub show_keys: bool,
+
+    /// Save progress to s3-stress-cleanup.json as pages are deleted, so an interrupted run can be resumed
+    #[arg(long)]
+    pub checkpoint: bool,
+
+    /// Carry on from the checkpoint saved by an earlier interrupted run instead of starting over
+    #[arg(long)]
+    pub resume: bool,
+
+    /// Delete exactly the keys in this file, one per line, instead of listing the bucket
+    #[arg(long, conflicts_with_all = ["prefix", "older_than", "checkpoint", "resume", "max_passes"])]
+    pub keys_file: Option<PathBuf>,
+
+    /// Target DeleteObjects requests per second across all workers, each removing up to 1000 keys. Unlimited when omitted
+    #[arg(long)]
+    pub rate: Option<u32>,
+
+    /// Cleanup passes to run before giving up, when listing again still finds objects left behind
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_passes: Option<u32>,
+
+    /// Maximum retries for each throttled or failed DeleteObjects batch, from 0 to 20. Defaults to 3
+    #[arg(long, value_parser = clap::value_parser!(u32).range(0..=crate::retry::MAX_RETRIES as i64))]
+    pub retries: Option<u32>,
+}
+
+#[derive(Args, Debug, Default)]
+pub struct ConsistencyTestArgs {
+    /// Name of the bucket to test
+    #[arg(long)]
+    pub bucket: Option<String>,
+
+    /// Total number of write-then-read cycles to run
+    #[arg(long, value_parser = parse_object_count)]
+    pub count: Option<u32>,
+
+    /// Size of each test object, eg. 4KB, 1MB, 10MB
+    #[arg(long, value_parser = parse_object_size)]
+    pub size: Option<u64>,
+
+    /// Number of concurrent workers
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub concurrency: Option<u32>,
+}
+
+#[derive(Args, Debug, Default)]
+pub struct DownloadDirectoryArgs {
+    /// Name of the bucket to download from
+    #[arg(long)]
+    pub bucket: Option<String>,
+
+    /// Only download objects whose keys start with this prefix, which is left out of the local paths
+    #[arg(long)]
+    pub prefix: Option<String>,
+
+    /// Local directory the objects are written to, created if it doesn't exist
+    #[arg(long)]
+    pub directory: Option<PathBuf>,
+
+    /// Number of concurrent workers
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub concurrency: Option<u32>,
+
+    /// Replace local files that already exist instead of skipping them
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    pub overwrite: Option<bool>,
+}
+
+#[derive(Args, Debug, Default)]
+pub struct UploadDirectoryArgs {
+    /// Name of the bucket to upload to
+    #[arg(long)]
+    pub bucket: Option<String>,
+
+    /// Local directory whose files are uploaded, including those in subdirectories
+    #[arg(long)]
+    pub directory: Option<PathBuf>,
+
+    /// Prefix prepended to every object key, eg. uploads/
+    #[arg(long)]
+    pub prefix: Option<String>,
+
+    /// Number of concurrent workers
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub concurrency: Option<u32>,
+
+    /// Files larger than this are uploaded in parts, eg. 100MB
+    #[arg(long, value_parser = parse_object_size)]
+    pub multipart_threshold: Option<u64>,
+}
+
+#[derive(Args, Debug, Default)]
+pub struct CopyObjectsArgs {
+    /// Name of the bucket to copy objects within
+    #[arg(long)]
+    pub bucket: Option<String>,
+
+    /// Only copy objects whose keys start with this prefix
+    #[arg(long)]
+    pub source_prefix: Option<String>,
+
+    /// Prefix the copies are written under, defaults to copies/
+    #[arg(long)]
+    pub destination_prefix: Option<String>,
+
+    /// Total number of copies to make
+    #[arg(long, value_parser = parse_object_count)]
+    pub count: Option<u32>,
+
+    /// Number of concurrent workers
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub concurrency: Option<u32>,
+}
+
+#[derive(Args, Debug, Default)]
+pub struct MirrorBucketArgs {
+    /// Name of the bucket to copy objects from
+    #[arg(long)]
+    pub bucket: Option<String>,
+
+    /// Name of the bucket to copy objects to, which may be in another region
+    #[arg(long)]
+    pub destination_bucket: Option<String>,
+
+    /// Only mirror objects whose keys start with this prefix
+    #[arg(long)]
+    pub prefix: Option<String>,
+
+    /// Write the copies under this prefix in place of --prefix, which lets the destination be the source bucket
+    #[arg(long)]
+    pub destination_prefix: Option<String>,
+
+    /// Number of concurrent workers
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub concurrency: Option<u32>,
+}
+
+#[derive(Args, Debug, Default)]
+pub struct CreateObjectsArgs {
+    /// Name of the bucket to create objects in
+    #[arg(long)]
+    pub bucket: Option<String>,
+
+    /// Total number of objects to create
+    #[arg(long, value_parser = parse_object_count)]
+    pub count: Option<u32>,
+
+    /// Keep creating objects for this long instead of a fixed count, eg. 30s, 5m, 1h
+    #[arg(long, value_parser = parse_run_duration, conflicts_with = "count")]
+    pub duration: Option<Duration>,
+
+    /// Create one object for each key in this file, one per line, instead of generating keys
+    #[arg(long, conflicts_with_all = ["count", "duration", "prefix", "naming", "keyspace"])]
+    pub keys_file: Option<PathBuf>,
+
+    /// Size of each object, eg. 4KB, 1MB, 10MB. Mixed sizes are given as a uniform range such as 4KB-1MB,
+    /// or as weighted sizes such as 4KB:70,1MB:25,100MB:5
+    #[arg(long, value_parser = SizeDistribution::parse)]
+    pub size: Option<SizeDistribution>,
+
+    /// How the body of each object is filled
+    #[arg(long, value_enum)]
+    pub payload: Option<PayloadMode>,
+
+    /// Storage class for created objects, eg. STANDARD, STANDARD_IA, INTELLIGENT_TIERING
+    #[arg(long, value_parser = parse_storage_class)]
+    pub storage_class: Option<StorageClass>,
+
+    /// Canned ACL for created objects, eg. private, public-read, bucket-owner-full-control
+    #[arg(long, value_parser = parse_object_acl)]
+    pub acl: Option<ObjectCannedAcl>,
+
+    /// Server-side encryption for created objects
+    #[arg(long, value_enum)]
+    pub encryption: Option<Encryption>,
+
+    /// KMS key ID for SSE-KMS, defaults to the AWS managed key. Only valid with --encryption sse-kms
+    #[arg(long, requires = "encryption")]
+    pub kms_key_id: Option<String>,
+
+    /// Checksum computed over each object body and sent with the upload
+    #[arg(long, value_enum)]
+    pub checksum: Option<Checksum>,
+
+    /// Read each object's checksum back after upload and report any that don't match
+    #[arg(long, num_args = 0..=1, default_missing_value = "true", requires = "checksum")]
+    pub verify: Option<bool>,
+
+    /// Percentage of uploaded objects to download again and compare byte for byte with what was sent
+    #[arg(long, value_parser = clap::value_parser!(u32).range(0..=100))]
+    pub verify_sample: Option<u32>,
+
+    /// Number of x-amz-meta-* headers to attach to each object
+    #[arg(long, value_parser = clap::value_parser!(u32).range(0..=crate::MAX_METADATA_COUNT as i64))]
+    pub metadata_count: Option<u32>,
+
+    /// User metadata header to attach to each object as key=value, repeated for several. Sent alongside any
+    /// headers added by --metadata-count, within S3's 2KB limit for all user metadata
+    #[arg(long = "metadata", value_parser = parse_metadata_pair)]
+    pub metadata: Vec<(String, String)>,
+
+    /// Content-Type of each object, eg. application/json. Objects are stored as binary/octet-stream when not given
+    #[arg(long)]
+    pub content_type: Option<String>,
+
+    /// Tag set to attach to each object, eg. team=storage&env=test
+    #[arg(long, value_parser = parse_tags)]
+    pub tags: Option<String>,
+
+    /// Prefix prepended to every object key, eg. stress/2024/
+    #[arg(long)]
+    pub prefix: Option<String>,
+
+    /// How object keys are named
+    #[arg(long, value_enum)]
+    pub naming: Option<KeyNaming>,
+
+    /// Whether every key shares the one prefix, or keys are spread across several, to compare S3's
+    /// per-prefix throttling
+    #[arg(long, value_enum)]
+    pub keyspace: Option<Keyspace>,
+
+    /// Number of prefixes keys are spread across with --keyspace spread
+    #[arg(long, value_parser = clap::value_parser!(u32).range(2..=MAX_SPREAD_PREFIXES as i64))]
+    pub spread_prefixes: Option<u32>,
+
+    /// Number of concurrent workers
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub concurrency: Option<u32>,
+
+    /// Target requests per second across all workers, 0 for unlimited
+    #[arg(long)]
+    pub rate: Option<u32>,
+
+    /// Maximum retries for throttled or failed requests, from 0 to 20
+    #[arg(long, value_parser = clap::value_parser!(u32).range(0..=crate::retry::MAX_RETRIES as i64))]
+    pub retries: Option<u32>,
+
+    /// Objects larger than this are uploaded in parts, eg. 100MB
+    #[arg(long, value_parser = parse_object_size)]
+    pub multipart_threshold: Option<u64>,
+
+    /// Size of each part of a multipart upload, at least 5MB. Grown when needed to stay within 10,000 parts
+    #[arg(long, value_parser = parse_object_size)]
+    pub part_size: Option<u64>,
+
+    /// Untimed requests sent before the run to open connections, excluded from the results. Takes a request
+    /// count such as 100, or a length of time such as 30s
+    #[arg(long, value_parser = parse_warmup)]
+    pub warmup: Option<RunLength>,
+
+    /// Whether workers share one connection pool, get one each, or open a new connection for every request.
+    /// Anything but shared adds connection setup to the measurements, which helps tell whether a bottleneck
+    /// is in connection reuse
+    #[arg(long, value_enum)]
+    pub connection_strategy: Option<ConnectionStrategy>,
+}
+
+#[derive(Args, Debug, Default)]
+pub struct ReadObjectsArgs {
+    /// Name of the bucket to read objects from
+    #[arg(long)]
+    pub bucket: Option<String>,
+
+    /// Total number of objects to read
+    #[arg(long, value_parser = parse_object_count)]
+    pub count: Option<u32>,
+
+    /// Number of concurrent workers
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub concurrency: Option<u32>,
+
+    /// Order in which keys are read
+    #[arg(long, value_enum)]
+    pub order: Option<ReadOrder>,
+
+    /// Target requests per second across all workers, 0 for unlimited
+    #[arg(long)]
+    pub rate: Option<u32>,
+
+    /// Read exactly the keys in this file, one per line, instead of listing the bucket
+    #[arg(long)]
+    pub keys_file: Option<PathBuf>,
+}
+
+#[derive(Args, Debug, Default)]
+pub struct HeadObjectsArgs {
+    /// Name of the bucket to send HEAD requests to
+    #[arg(long)]
+    pub bucket: Option<String>,
+
+    /// Total number of HEAD requests to send
+    #[arg(long, value_parser = parse_object_count)]
+    pub count: Option<u32>,
+
+    /// Number of concurrent workers
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub concurrency: Option<u32>,
+
+    /// Percentage of requests for keys that don't exist, to exercise the 404 path
+    #[arg(long, value_parser = clap::value_parser!(u32).range(0..=100))]
+    pub miss_percent: Option<u32>,
+}
+
+#[derive(Args, Debug, Default)]
+pub struct LifecycleArgs {
+    /// Name of the bucket to apply the lifecycle rule to
+    #[arg(long)]
+    pub bucket: Option<String>,
+
+    /// Only apply the rule to objects whose keys start with this prefix
+    #[arg(long)]
+    pub prefix: Option<String>,
+
+    /// Days after creation that objects expire, 0 to never expire them
+    #[arg(long)]
+    pub expire_days: Option<u32>,
+
+    /// Days after initiation that incomplete multipart uploads are aborted, 0 to leave them
+    #[arg(long)]
+    pub abort_multipart_days: Option<u32>,
+}
+
+#[derive(Args, Debug, Default)]
+pub struct MixedWorkloadArgs {
+    /// Name of the bucket to run the workload against
+    #[arg(long)]
+    pub bucket: Option<String>,
+
+    /// Read:write ratio, eg. 70:30
+    #[arg(long, value_parser = parse_read_ratio)]
+    pub ratio: Option<u32>,
+
+    /// Total number of operations to run
+    #[arg(long, value_parser = parse_object_count)]
+    pub count: Option<u32>,
+
+    /// Keep running operations for this long instead of a fixed count, eg. 30s, 5m, 1h
+    #[arg(long, value_parser = parse_run_duration, conflicts_with = "count")]
+    pub duration: Option<Duration>,
+
+    /// Size of each written object, eg. 4KB, 1MB, 10MB
+    #[arg(long, value_parser = parse_object_size)]
+    pub size: Option<u64>,
+
+    /// Number of concurrent workers
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub concurrency: Option<u32>,
+}
+
+#[derive(Args, Debug, Default)]
+pub struct AutoTuneArgs {
+    /// Name of the bucket to write to while tuning
+    #[arg(long)]
+    pub bucket: Option<String>,
+
+    /// Size of each written object, eg. 4KB, 1MB, 10MB
+    #[arg(long, value_parser = parse_object_size)]
+    pub size: Option<u64>,
+
+    /// How long each concurrency level runs, eg. 10s, 1m
+    #[arg(long, value_parser = parse_run_duration)]
+    pub burst: Option<Duration>,
+
+    /// Highest worker count to try. Levels start at 4 and double each burst
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_concurrency: Option<u32>,
+
+    /// Stop ramping once p99 latency exceeds this many milliseconds. 0 means no limit
+    #[arg(long)]
+    pub max_p99_ms: Option<u32>,
+
+    /// Key prefix for the objects written while tuning
+    #[arg(long)]
+    pub prefix: Option<String>,
+}
+
+#[derive(Args, Debug, Default)]
+pub struct PresignArgs {
+    /// Name of the bucket to sign URLs for
+    #[arg(long)]
+    pub bucket: Option<String>,
+
+    /// Request sent to each URL. PUT uploads new objects, GET downloads existing ones
+    #[arg(long, value_enum)]
+    pub method: Option<PresignMethod>,
+
+    /// Total number of requests to send
+    #[arg(long, value_parser = parse_object_count)]
+    pub count: Option<u32>,
+
+    /// Size of each uploaded object with PUT, eg. 4KB, 1MB, 10MB
+    #[arg(long, value_parser = parse_object_size)]
+    pub size: Option<u64>,
+
+    /// How long the URLs stay valid, up to 7 days, eg. 15m, 1h
+    #[arg(long, value_parser = parse_run_duration)]
+    pub expires_in: Option<Duration>,
+
+    /// Number of concurrent workers
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub concurrency: Option<u32>,
+}
+
+#[derive(Args, Debug, Default)]
+pub struct ListBenchmarkArgs {
+    /// Name of the bucket to list
+    #[arg(long)]
+    pub bucket: Option<String>,
+
+    /// Only list keys starting with this prefix
+    #[arg(long)]
+    pub prefix: Option<String>,
+
+    /// Group keys into common prefixes at this delimiter, eg. / to list one "directory" level
+    #[arg(long)]
+    pub delimiter: Option<String>,
+
+    /// Keys requested per page, separated by commas to compare several, eg. 100,1000
+    #[arg(long, value_delimiter = ',', value_parser = parse_page_size)]
+    pub page_sizes: Vec<i32>,
+
+    /// Number of complete listings to run at each page size
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub passes: Option<u32>,
+}
+
+fn parse_seconds(input: &str) -> Result<Duration, String> {
+    match input.parse::<f64>() {
+        Ok(seconds) if seconds > 0.0 => Duration::try_from_secs_f64(seconds).map_err(|err| err.to_string()),
+        _ => Err("expected a positive number of seconds, eg. 0.5, 30".to_string()),
+    }
+}
+
+fn parse_price(input: &str) -> Result<f64, String> {
+    match input.trim().trim_start_matches('$').parse::<f64>() {
+        Ok(price) if price >= 0.0 && price.is_finite() => Ok(price),
+        _ => Err("expected a price in USD, eg. 0.023".to_string()),
+    }
+}
+
+fn parse_run_duration(input: &str) -> Result<Duration, String> {
+    crate::parse_duration(input).ok_or("expected a duration with an optional s, m, h or d suffix, eg. 30s, 5m, 1h, 7d".to_string())
+}
+
+fn parse_warmup(input: &str) -> Result<RunLength, String> {
+    crate::parse_warmup(input).ok_or("expected a request count such as 100, or a duration such as 30s or 2m".to_string())
+}
+
+fn parse_page_size(input: &str) -> Result<i32, String> {
+    match input.trim().parse::<i32>() {
+        Ok(page_size) if (1..=crate::listing::MAX_PAGE_SIZE).contains(&page_size) => Ok(page_size),
+        _ => Err(format!("expected a page size from 1 - {0}", crate::listing::MAX_PAGE_SIZE)),
+    }
+}
+
+fn parse_read_ratio(input: &str) -> Result<u32, String> {
+    crate::mixed::parse_ratio(input).ok_or("expected reads:writes, eg. 70:30".to_string())
+}
+
+fn parse_object_count(input: &str) -> Result<u32, String> {
+    crate::parse_count(input)
+        .ok_or(format!("expected a whole number up to {0} with an optional k or m suffix, eg. 500k", crate::MAX_COUNT))
+}
+
+fn parse_object_size(input: &str) -> Result<u64, String> {
+    match crate::parse_size(input) {
+        Some(size) if size > crate::MAX_OBJECT_SIZE => Err("object size cannot exceed 5TB, the S3 object size limit".to_string()),
+        Some(size) => Ok(size),
+        None => Err("expected a number with an optional suffix, eg. 4KB, 1MB, 10MB".to_string()),
+    }
+}
+
+fn parse_storage_class(input: &str) -> Result<StorageClass, String> {
+    crate::parse_storage_class(input)
+        .ok_or(format!("unknown storage class, expected one of {0}", StorageClass::values().join(", ")))
+}
+
+fn parse_object_acl(input: &str) -> Result<ObjectCannedAcl, String> {
+    let name = input.trim().to_lowercase();
+    match ObjectCannedAcl::values().contains(&name.as_str()) {
+        true => Ok(ObjectCannedAcl::from(name.as_str())),
+        false => Err(format!("unknown canned ACL, expected one of {0}", ObjectCannedAcl::values().join(", "))),
+    }
+}
+
+fn parse_bucket_acl(input: &str) -> Result<BucketCannedAcl, String> {
+    let name = input.trim().to_lowercase();
+    match BucketCannedAcl::values().contains(&name.as_str()) {
+        true => Ok(BucketCannedAcl::from(name.as_str())),
+        false => Err(format!("unknown canned ACL, expected one of {0}", BucketCannedAcl::values().join(", "))),
+    }
+}
+
+fn parse_metadata_pair(input: &str) -> Result<(String, String), String> {
+    crate::parse_metadata_pair(input)
+        .ok_or("expected key=value, with a key of ASCII letters, digits, hyphens, underscores and periods, and a printable ASCII value".to_string())
+}
+
+fn parse_tags(input: &str) -> Result<String, String> {
+    match crate::is_valid_tagging(input) {
+        true => Ok(input.to_string()),
+        false => Err("expected up to 10 key=value pairs separated by &, with keys up to 128 and values up to 256 characters".to_string()),
+    }
+}
@@ -0,0 +1,163 @@
+use anyhow::{bail, Context};
+use serde::Deserialize;
+use std::path::Path;
+
+use crate::cli::{AutoTuneArgs, BucketInfoArgs, CreateBucketArgs, CleanupArgs, ConsistencyTestArgs, CopyObjectsArgs, CreateObjectsArgs, DeleteBucketArgs, DownloadDirectoryArgs, GlobalArgs, HeadObjectsArgs, LifecycleArgs, ListBenchmarkArgs, MirrorBucketArgs, MixedWorkloadArgs, PresignArgs, ReadObjectsArgs, UploadDirectoryArgs};
+use crate::sizes::SizeDistribution;
+
+// Run parameters loaded from a TOML file passed with --config, so benchmark scenarios can be
+// committed and repeated. Values given on the command line take precedence over the file, and
+// anything set in neither place is prompted for interactively.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RunConfig {
+    pub bucket: Option<String>,
+    pub region: Option<String>,
+    pub object_count: Option<u32>,
+    // Human-readable size such as "4KB" or "10MB". Create objects also takes a mix of sizes, eg. "4KB-1MB",
+    // which the other operations ignore.
+    pub object_size: Option<String>,
+    pub concurrency: Option<u32>,
+    // Prefix prepended to created object keys, and the only keys removed by cleanup
+    pub key_prefix: Option<String>,
+    pub endpoint_url: Option<String>,
+    pub force_path_style: Option<bool>,
+    pub requester_pays: Option<bool>,
+    pub accelerate: Option<bool>,
+    pub profile: Option<String>,
+    // Per-unit prices in USD for the cost estimate, for regions or backends the built-in table doesn't match
+    pub price_per_gb_month: Option<f64>,
+    pub price_per_1000_requests: Option<f64>,
+}
+
+impl RunConfig {
+    // Reads and validates the config file, failing with a readable message if it can't be used
+    pub fn load(path: &Path) -> anyhow::Result<RunConfig> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Unable to read config file {0}", path.display()))?;
+        let config: RunConfig = toml::from_str(&contents)
+            .with_context(|| format!("Invalid config file {0}", path.display()))?;
+
+        if let Some(object_size) = &config.object_size {
+            if SizeDistribution::parse(object_size).is_err() {
+                bail!("Invalid object_size in config file: {0}", object_size);
+            }
+        }
+        if [config.price_per_gb_month, config.price_per_1000_requests].iter().flatten().any(|price| *price < 0.0) {
+            bail!("Prices in config file can't be negative");
+        }
+        if config.concurrency == Some(0) {
+            bail!("concurrency in config file must be at least 1");
+        }
+        Ok(config)
+    }
+
+    pub fn apply_to_global(&self, global: &mut GlobalArgs) {
+        global.profile = global.profile.take().or(self.profile.clone());
+        global.region = global.region.take().or(self.region.clone());
+        global.requester_pays = global.requester_pays || self.requester_pays.unwrap_or_default();
+        global.accelerate = global.accelerate || self.accelerate.unwrap_or_default();
+        global.price_per_gb_month = global.price_per_gb_month.or(self.price_per_gb_month);
+        global.price_per_thousand_requests = global.price_per_thousand_requests.or(self.price_per_1000_requests);
+    }
+
+    pub fn apply_to_auto_tune(&self, args: &mut AutoTuneArgs) {
+        args.bucket = args.bucket.take().or(self.bucket.clone());
+        args.size = args.size.or(self.object_size.as_deref().and_then(crate::parse_size));
+        args.prefix = args.prefix.take().or(self.key_prefix.clone());
+    }
+
+    pub fn apply_to_bucket_info(&self, args: &mut BucketInfoArgs) {
+        args.bucket = args.bucket.take().or(self.bucket.clone());
+        args.prefix = args.prefix.take().or(self.key_prefix.clone());
+    }
+
+    pub fn apply_to_create_bucket(&self, args: &mut CreateBucketArgs) {
+        args.bucket = args.bucket.take().or(self.bucket.clone());
+    }
+
+    pub fn apply_to_delete_bucket(&self, args: &mut DeleteBucketArgs) {
+        args.bucket = args.bucket.take().or(self.bucket.clone());
+    }
+
+    pub fn apply_to_cleanup(&self, args: &mut CleanupArgs) {
+        args.bucket = args.bucket.take().or(self.bucket.clone());
+        args.prefix = args.prefix.take().or(self.key_prefix.clone());
+        args.concurrency = args.concurrency.or(self.concurrency);
+    }
+
+    pub fn apply_to_consistency_test(&self, args: &mut ConsistencyTestArgs) {
+        args.bucket = args.bucket.take().or(self.bucket.clone());
+        args.count = args.count.or(self.object_count);
+        args.size = args.size.or(self.object_size.as_deref().and_then(crate::parse_size));
+        args.concurrency = args.concurrency.or(self.concurrency);
+    }
+
+    pub fn apply_to_copy_objects(&self, args: &mut CopyObjectsArgs) {
+        args.bucket = args.bucket.take().or(self.bucket.clone());
+        args.source_prefix = args.source_prefix.take().or(self.key_prefix.clone());
+        args.count = args.count.or(self.object_count);
+        args.concurrency = args.concurrency.or(self.concurrency);
+    }
+
+    pub fn apply_to_create_objects(&self, args: &mut CreateObjectsArgs) {
+        args.bucket = args.bucket.take().or(self.bucket.clone());
+        // A --duration given on the command line replaces the file's object count rather than competing with it
+        args.count = args.count.or(self.object_count.filter(|_| args.duration.is_none()));
+        args.size = args.size.take().or(self.object_size.as_deref().and_then(|object_size| SizeDistribution::parse(object_size).ok()));
+        args.prefix = args.prefix.take().or(self.key_prefix.clone());
+        args.concurrency = args.concurrency.or(self.concurrency);
+    }
+
+    pub fn apply_to_download_directory(&self, args: &mut DownloadDirectoryArgs) {
+        args.bucket = args.bucket.take().or(self.bucket.clone());
+        args.prefix = args.prefix.take().or(self.key_prefix.clone());
+        args.concurrency = args.concurrency.or(self.concurrency);
+    }
+
+    pub fn apply_to_mirror_bucket(&self, args: &mut MirrorBucketArgs) {
+        args.bucket = args.bucket.take().or(self.bucket.clone());
+        args.prefix = args.prefix.take().or(self.key_prefix.clone());
+        args.concurrency = args.concurrency.or(self.concurrency);
+    }
+
+    pub fn apply_to_mixed_workload(&self, args: &mut MixedWorkloadArgs) {
+        args.bucket = args.bucket.take().or(self.bucket.clone());
+        args.count = args.count.or(self.object_count.filter(|_| args.duration.is_none()));
+        args.size = args.size.or(self.object_size.as_deref().and_then(crate::parse_size));
+        args.concurrency = args.concurrency.or(self.concurrency);
+    }
+
+    pub fn apply_to_list_benchmark(&self, args: &mut ListBenchmarkArgs) {
+        args.bucket = args.bucket.take().or(self.bucket.clone());
+        args.prefix = args.prefix.take().or(self.key_prefix.clone());
+    }
+
+    pub fn apply_to_presigned(&self, args: &mut PresignArgs) {
+        args.bucket = args.bucket.take().or(self.bucket.clone());
+        args.count = args.count.or(self.object_count);
+        args.size = args.size.or(self.object_size.as_deref().and_then(crate::parse_size));
+        args.concurrency = args.concurrency.or(self.concurrency);
+    }
+
+    pub fn apply_to_head_objects(&self, args: &mut HeadObjectsArgs) {
+        args.bucket = args.bucket.take().or(self.bucket.clone());
+        args.concurrency = args.concurrency.or(self.concurrency);
+    }
+
+    pub fn apply_to_lifecycle(&self, args: &mut LifecycleArgs) {
+        args.bucket = args.bucket.take().or(self.bucket.clone());
+        args.prefix = args.prefix.take().or(self.key_prefix.clone());
+    }
+
+    pub fn apply_to_read_objects(&self, args: &mut ReadObjectsArgs) {
+        args.bucket = args.bucket.take().or(self.bucket.clone());
+        args.concurrency = args.concurrency.or(self.concurrency);
+    }
+
+    pub fn apply_to_upload_directory(&self, args: &mut UploadDirectoryArgs) {
+        args.bucket = args.bucket.take().or(self.bucket.clone());
+        args.prefix = args.prefix.take().or(self.key_prefix.clone());
+        args.concurrency = args.concurrency.or(self.concurrency);
+    }
+}
@@ -0,0 +1,150 @@
+use aws_sdk_s3::{config::{interceptors::{BeforeDeserializationInterceptorContextRef, BeforeTransmitInterceptorContextMut}, timeout::TimeoutConfig, ConfigBag, Intercept, RuntimeComponents, SharedHttpClient}, Client};
+use aws_smithy_runtime::client::http::hyper_014::HyperClientBuilder;
+use aws_smithy_runtime_api::{box_error::BoxError, client::orchestrator::Metadata};
+use std::{sync::{atomic::{AtomicU64, Ordering}, Arc}, time::Duration};
+
+// HTTP tuning for high concurrency runs, from --max-connections, --connect-timeout and --operation-timeout.
+// Anything left unset keeps the SDK default.
+#[derive(Clone, Debug, Default)]
+pub struct HttpSettings {
+    // Idle connections kept open for reuse. The default pool has no limit, so it grows to match however
+    // many workers are running; a limit makes workers beyond it open a new connection for each request.
+    pub max_connections: Option<usize>,
+    // Defaults to 3.1 seconds
+    pub connect_timeout: Option<Duration>,
+    // Time allowed for a whole request including retries, with no limit by default
+    pub operation_timeout: Option<Duration>,
+}
+
+impl HttpSettings {
+    pub fn http_client(&self) -> Option<SharedHttpClient> {
+        let max_connections = self.max_connections?;
+        let mut hyper_builder = hyper::Client::builder();
+        hyper_builder.pool_max_idle_per_host(max_connections);
+        Some(HyperClientBuilder::new().hyper_builder(hyper_builder).build_https())
+    }
+
+    // Layers the configured timeouts over the ones already in effect, since a client's timeout config
+    // replaces the SDK defaults as a whole rather than merging with them
+    pub fn timeout_config(&self, current: Option<&TimeoutConfig>) -> Option<TimeoutConfig> {
+        if self.connect_timeout.is_none() && self.operation_timeout.is_none() {
+            return None;
+        }
+        let mut builder = TimeoutConfig::builder();
+        builder.set_connect_timeout(self.connect_timeout);
+        builder.set_operation_timeout(self.operation_timeout);
+        let builder = match current {
+            Some(current) => builder.take_unset_from(current.to_builder()),
+            None => builder,
+        };
+        Some(builder.build())
+    }
+}
+
+// How create objects workers get their S3 client, for measuring the effect of connection reuse
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum ConnectionStrategy {
+    // One client and connection pool for every worker. Connections are kept alive and reused, so
+    // after the first few requests there are no TCP or TLS handshakes. This is how real applications
+    // behave and gives the best throughput.
+    Shared,
+    // A client with its own connection pool per worker. Each worker still reuses its connections,
+    // but they can't be shared across workers, so more connections are opened in total.
+    PerWorker,
+    // A new client for every request, so every request opens a new connection and pays for a full
+    // handshake. Latency includes connection setup, which shows how much reuse is saving.
+    PerRequest,
+}
+
+impl ConnectionStrategy {
+    pub fn all() -> Vec<ConnectionStrategy> {
+        vec![ConnectionStrategy::Shared, ConnectionStrategy::PerWorker, ConnectionStrategy::PerRequest]
+    }
+}
+
+impl std::fmt::Display for ConnectionStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ConnectionStrategy::Shared => "Shared client",
+            ConnectionStrategy::PerWorker => "Client per worker",
+            ConnectionStrategy::PerRequest => "Client per request",
+        };
+        write!(f, "{0}", name)
+    }
+}
+
+// Builds a client with the same configuration as an existing one but its own HTTP connection pool.
+// Cloning a Client, or building one from a cloned config, would share the original pool. Timeouts carry
+// over with the config, while the pool limit doesn't, since each of these clients serves a single worker.
+pub fn fresh_client(s3_client: &Client) -> Client {
+    let s3_config = s3_client.config().to_builder()
+        .http_client(HyperClientBuilder::new().build_https())
+        .build();
+    Client::from_conf(s3_config)
+}
+
+// Object and listing operations that requester pays buckets charge to the caller. Bucket-level
+// operations such as CreateBucket are left alone, since they're always paid for by the bucket owner.
+const REQUESTER_PAYS_OPERATIONS: [&str; 15] = [
+    "PutObject", "GetObject", "HeadObject", "CopyObject", "DeleteObject", "DeleteObjects",
+    "ListObjectsV2", "ListObjectVersions", "ListMultipartUploads", "ListParts",
+    "CreateMultipartUpload", "UploadPart", "UploadPartCopy", "CompleteMultipartUpload", "AbortMultipartUpload",
+];
+
+// Adds x-amz-request-payer: requester to every request that supports it, from --requester-pays. Without it,
+// requester pays buckets refuse access from anyone but the owner with a 403. Done once for the client
+// rather than on each request builder, so no operation can miss it.
+#[derive(Debug)]
+pub struct RequesterPays;
+
+impl Intercept for RequesterPays {
+    fn name(&self) -> &'static str {
+        "RequesterPays"
+    }
+
+    // The header is added before signing, since S3 requires it to be covered by the signature
+    fn modify_before_signing(&self, context: &mut BeforeTransmitInterceptorContextMut<'_>, _runtime_components: &RuntimeComponents, cfg: &mut ConfigBag) -> Result<(), BoxError> {
+        let operation = cfg.load::<Metadata>().map(|metadata| metadata.name());
+        if operation.is_some_and(|operation| REQUESTER_PAYS_OPERATIONS.contains(&operation)) {
+            context.request_mut().headers_mut().insert("x-amz-request-payer", "requester");
+        }
+        Ok(())
+    }
+}
+
+// Counts every HTTP response, and the 503 Slow Down responses among them. Attempts the SDK retries on
+// its own never reach the caller, so counting them here is the only way to see the true throttling rate.
+#[derive(Clone, Debug, Default)]
+pub struct ThrottleCounter {
+    responses: Arc<AtomicU64>,
+    throttled: Arc<AtomicU64>,
+}
+
+impl ThrottleCounter {
+    // Returns a client that shares the counters, leaving the original client uncounted
+    pub fn attach(&self, s3_client: &Client) -> Client {
+        Client::from_conf(s3_client.config().to_builder().interceptor(self.clone()).build())
+    }
+
+    pub fn responses(&self) -> u64 {
+        self.responses.load(Ordering::Relaxed)
+    }
+
+    pub fn throttled(&self) -> u64 {
+        self.throttled.load(Ordering::Relaxed)
+    }
+}
+
+impl Intercept for ThrottleCounter {
+    fn name(&self) -> &'static str {
+        "ThrottleCounter"
+    }
+
+    fn read_before_deserialization(&self, context: &BeforeDeserializationInterceptorContextRef<'_>, _runtime_components: &RuntimeComponents, _cfg: &mut ConfigBag) -> Result<(), BoxError> {
+        self.responses.fetch_add(1, Ordering::Relaxed);
+        if context.response().status().as_u16() == 503 {
+            self.throttled.fetch_add(1, Ordering::Relaxed);
+        }
+        Ok(())
+    }
+}
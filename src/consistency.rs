@@ -0,0 +1,194 @@
+use anyhow::Context;
+use aws_sdk_s3::{primitives::{ByteStream, SdkBody}, Client};
+use futures::future::join_all;
+use rand::{rngs::SmallRng, SeedableRng};
+use std::{sync::{atomic::{AtomicU64, Ordering}, Arc}, time::{Duration, Instant}};
+use tokio_util::sync::CancellationToken;
+
+use crate::cli::ConsistencyTestArgs;
+use crate::interrupt;
+use crate::stats::RunStats;
+use crate::{generate_payload, new_progress_bar, parse_count, parse_size, prompt_worker_count, retry, split_object_count, track_progress, validate_number, validate_size, PayloadMode};
+
+// Test objects are written under their own prefix and removed after each cycle
+const KEY_PREFIX: &str = "consistency-test/";
+
+// How often a stale object is read again, and how long to wait before giving up on it
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+const VISIBILITY_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Settings and shared counters for every worker taking part in a consistency test
+struct ConsistencyTestContext {
+    bucket_name: String,
+    object_size: u64,
+    // Cycles where the first read after the write already saw the new object
+    consistent_count: AtomicU64,
+    // Cycles where the first read saw a missing or outdated object
+    stale_count: AtomicU64,
+    // Stale cycles where the object still wasn't visible when the timeout ran out
+    never_visible_count: AtomicU64,
+    cancel: CancellationToken,
+    completed_count: Arc<AtomicU64>,
+    run_stats: RunStats,
+}
+
+// What a read found compared with the object just written
+enum ReadOutcome {
+    Visible,
+    Stale,
+}
+
+#[tracing::instrument(skip_all, fields(bucket = %bucket_name))]
+pub async fn operation_consistency_test(s3_client: Client, bucket_name: String, args: ConsistencyTestArgs) -> anyhow::Result<()> {
+    let cycle_count = match args.count {
+        Some(count) => count,
+        None => {
+            let cycle_count = inquire::Text::new("How many write-then-read cycles should I run?")
+                .with_default("100")
+                .with_validator(validate_number)
+                .prompt()?;
+            parse_count(&cycle_count).context("Invalid cycle count")?
+        }
+    };
+
+    let object_size = match args.size {
+        Some(size) => size,
+        None => {
+            let object_size = inquire::Text::new("What size should each test object be? (eg. 4KB, 1MB, 10MB)")
+                .with_default("1KB")
+                .with_validator(validate_size)
+                .prompt()?;
+            parse_size(&object_size).context("Invalid object size")?
+        }
+    };
+
+    let worker_count = match args.concurrency {
+        Some(concurrency) => concurrency,
+        None => prompt_worker_count()?,
+    };
+
+    let operation = interrupt::start_operation();
+    let context = Arc::new(ConsistencyTestContext {
+        bucket_name,
+        object_size,
+        consistent_count: AtomicU64::new(0),
+        stale_count: AtomicU64::new(0),
+        never_visible_count: AtomicU64::new(0),
+        cancel: operation.token(),
+        completed_count: Arc::new(AtomicU64::new(0)),
+        run_stats: RunStats::default(),
+    });
+
+    let progress = new_progress_bar(cycle_count as u64);
+    let progress_task = tokio::spawn(track_progress(progress.clone(), context.completed_count.clone(), vec![context.run_stats.byte_counter()]));
+
+    let start_time = Instant::now();
+
+    let mut join_handle_list = vec![];
+    for worker_cycle_count in split_object_count(cycle_count, worker_count) {
+        join_handle_list.push(tokio::spawn(consistency_worker(s3_client.clone(), context.clone(), worker_cycle_count)));
+    }
+    join_all(join_handle_list).await;
+
+    progress_task.abort();
+    progress.finish_and_clear();
+    interrupt::report_if_interrupted(&context.cancel);
+
+    // Latencies here are visibility latencies: the time from the write completing to the first read that saw it
+    context.run_stats.summarize(start_time.elapsed())
+        .with_setting("Consistent", context.consistent_count.load(Ordering::Relaxed))
+        .with_setting("Stale reads", context.stale_count.load(Ordering::Relaxed))
+        .with_setting("Never visible", context.never_visible_count.load(Ordering::Relaxed))
+        .print("Consistency test", &context.bucket_name);
+    Ok(())
+}
+
+async fn consistency_worker(s3_client: Client, context: Arc<ConsistencyTestContext>, cycle_count: u32) {
+    let mut rng = SmallRng::from_entropy();
+
+    for _ in 1..=cycle_count {
+        if context.cancel.is_cancelled() {
+            break;
+        }
+        let key = format!("{0}{1}", KEY_PREFIX, uuid::Uuid::new_v4());
+        // A fresh body for every cycle gives every write a distinct ETag to look for
+        let payload = generate_payload(&mut rng, PayloadMode::Random, context.object_size);
+
+        let put_result = s3_client.put_object()
+            .bucket(&context.bucket_name)
+            .key(&key)
+            .body(ByteStream::new(SdkBody::from(payload)))
+            .send().await;
+
+        match put_result {
+            Ok(output) => {
+                let etag = output.e_tag.unwrap_or_default();
+                check_visibility(&s3_client, &context, &key, &etag).await;
+                let _delete_result = s3_client.delete_object()
+                    .bucket(&context.bucket_name)
+                    .key(&key)
+                    .send().await;
+            }
+            Err(err) => context.run_stats.record_failure(format!("Write failed: {0}", retry::error_reason(&err))),
+        }
+        context.completed_count.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+// Reads the object straight after the write, then keeps polling until it's visible with the expected
+// size and ETag or the timeout runs out
+async fn check_visibility(s3_client: &Client, context: &ConsistencyTestContext, key: &str, etag: &str) {
+    let write_completed = Instant::now();
+    let mut first_read = true;
+
+    loop {
+        let outcome = match read_object(s3_client, context, key, etag).await {
+            Ok(outcome) => outcome,
+            Err(reason) => {
+                context.run_stats.record_failure(format!("Read failed: {0}", reason));
+                return;
+            }
+        };
+
+        match outcome {
+            ReadOutcome::Visible => {
+                match first_read {
+                    true => context.consistent_count.fetch_add(1, Ordering::Relaxed),
+                    false => context.stale_count.fetch_add(1, Ordering::Relaxed),
+                };
+                context.run_stats.record(write_completed.elapsed(), context.object_size);
+                return;
+            }
+            ReadOutcome::Stale if write_completed.elapsed() >= VISIBILITY_TIMEOUT || context.cancel.is_cancelled() => {
+                context.stale_count.fetch_add(1, Ordering::Relaxed);
+                context.never_visible_count.fetch_add(1, Ordering::Relaxed);
+                return;
+            }
+            ReadOutcome::Stale => {
+                first_read = false;
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+        }
+    }
+}
+
+// A 404 or an object with the wrong size or ETag both count as stale, while any other error is a failure
+async fn read_object(s3_client: &Client, context: &ConsistencyTestContext, key: &str, etag: &str) -> Result<ReadOutcome, String> {
+    let head_result = s3_client.head_object()
+        .bucket(&context.bucket_name)
+        .key(key)
+        .send().await;
+
+    match head_result {
+        Ok(output) => {
+            let size_matches = output.content_length.unwrap_or_default() as u64 == context.object_size;
+            let etag_matches = output.e_tag.as_deref() == Some(etag);
+            match size_matches && etag_matches {
+                true => Ok(ReadOutcome::Visible),
+                false => Ok(ReadOutcome::Stale),
+            }
+        }
+        Err(err) if err.as_service_error().is_some_and(|service_err| service_err.is_not_found()) => Ok(ReadOutcome::Stale),
+        Err(err) => Err(retry::error_reason(&err)),
+    }
+}
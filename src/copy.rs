@@ -0,0 +1,255 @@
+use anyhow::Context;
+use aws_sdk_s3::{types::{CompletedMultipartUpload, CompletedPart}, Client};
+use colorize::AnsiColor;
+use futures::{future::join_all, stream, StreamExt};
+use std::{sync::{atomic::{AtomicU64, Ordering}, Arc}, time::Instant};
+use tokio_util::sync::CancellationToken;
+
+use crate::cli::CopyObjectsArgs;
+use crate::interrupt;
+use crate::output::status;
+use crate::retry::SdkContext;
+use crate::stats::RunStats;
+use crate::{new_progress_bar, parse_count, prompt_worker_count, retry, split_object_count, track_progress, validate_number, MAX_SINGLE_PUT_SIZE};
+
+const DEFAULT_DESTINATION_PREFIX: &str = "copies/";
+
+// Copy parts can be up to 5GB, so larger parts than uploads use keep the request count down
+const COPY_PART_SIZE: u64 = 512 * 1024 * 1024;
+const MAX_PART_COUNT: u64 = 10_000;
+
+// Number of parts of a single object copied at the same time
+const PART_CONCURRENCY: usize = 4;
+
+// An object to copy, with the size needed to choose between a single copy and a multipart copy
+pub struct SourceObject {
+    pub key: String,
+    pub size: u64,
+}
+
+// Settings and shared counters for every worker taking part in a copy objects run
+struct CopyObjectsContext {
+    bucket_name: String,
+    sources: Vec<SourceObject>,
+    source_prefix: String,
+    destination_prefix: String,
+    // Position of the next source to copy, wrapping around when more copies than sources are requested
+    next_index: AtomicU64,
+    cancel: CancellationToken,
+    completed_count: Arc<AtomicU64>,
+    run_stats: RunStats,
+}
+
+#[tracing::instrument(skip_all, fields(bucket = %bucket_name))]
+pub async fn operation_copy_objects(s3_client: Client, bucket_name: String, args: CopyObjectsArgs) -> anyhow::Result<()> {
+    let source_prefix = match args.source_prefix {
+        Some(prefix) => prefix,
+        None => inquire::Text::new("Copy objects under which prefix? (leave blank for the whole bucket)").prompt()?,
+    };
+
+    let destination_prefix = match args.destination_prefix {
+        Some(prefix) => prefix,
+        None => inquire::Text::new("Prefix for the copied objects")
+            .with_default(DEFAULT_DESTINATION_PREFIX)
+            .prompt()?,
+    };
+    if destination_prefix == source_prefix {
+        anyhow::bail!("The destination prefix must differ from the source prefix, or objects would be copied onto themselves");
+    }
+
+    let sources = list_source_objects(&s3_client, &bucket_name, &source_prefix, &destination_prefix).await?;
+    if sources.is_empty() {
+        status!("{0}", "No objects found to copy".blue());
+        return Ok(());
+    }
+    status!("Found {0} objects to copy", sources.len());
+
+    let copy_count = match args.count {
+        Some(count) => count,
+        None => {
+            let copy_count = inquire::Text::new("How many copies should I make?")
+                .with_default(&sources.len().to_string())
+                .with_validator(validate_number)
+                .prompt()?;
+            parse_count(&copy_count).context("Invalid copy count")?
+        }
+    };
+
+    let worker_count = match args.concurrency {
+        Some(concurrency) => concurrency,
+        None => prompt_worker_count()?,
+    };
+
+    let operation = interrupt::start_operation();
+    let context = Arc::new(CopyObjectsContext {
+        bucket_name,
+        sources,
+        source_prefix,
+        destination_prefix,
+        next_index: AtomicU64::new(0),
+        cancel: operation.token(),
+        completed_count: Arc::new(AtomicU64::new(0)),
+        run_stats: RunStats::default(),
+    });
+
+    let progress = new_progress_bar(copy_count as u64);
+    let progress_task = tokio::spawn(track_progress(progress.clone(), context.completed_count.clone(), vec![context.run_stats.byte_counter()]));
+
+    let start_time = Instant::now();
+
+    let mut join_handle_list = vec![];
+    for worker_copy_count in split_object_count(copy_count, worker_count) {
+        join_handle_list.push(tokio::spawn(copy_object(s3_client.clone(), context.clone(), worker_copy_count)));
+    }
+    join_all(join_handle_list).await;
+
+    progress_task.abort();
+    progress.finish_and_clear();
+    interrupt::report_if_interrupted(&context.cancel);
+    context.run_stats.summarize(start_time.elapsed())
+        .with_setting("Destination prefix", &context.destination_prefix)
+        .print("Copy objects", &context.bucket_name);
+    Ok(())
+}
+
+// Lists the objects under the source prefix, skipping anything already under the destination prefix
+// so earlier copies aren't copied again when the source covers the whole bucket
+pub async fn list_source_objects(s3_client: &Client, bucket_name: &String, source_prefix: &str, destination_prefix: &str) -> anyhow::Result<Vec<SourceObject>> {
+    let mut sources = vec![];
+
+    let mut page_token = None;
+    loop {
+        let object_list = s3_client.list_objects_v2()
+            .bucket(bucket_name)
+            .prefix(source_prefix)
+            .set_continuation_token(page_token)
+            .send().await
+            .sdk_context("Unable to list objects")?;
+
+        page_token = object_list.next_continuation_token;
+        sources.extend(object_list.contents.unwrap_or_default().into_iter()
+            .filter_map(|object| Some(SourceObject { size: object.size.unwrap_or_default() as u64, key: object.key? }))
+            .filter(|source| destination_prefix.is_empty() || !source.key.starts_with(destination_prefix)));
+        if page_token.is_none() { break; }
+    }
+    Ok(sources)
+}
+
+async fn copy_object(s3_client: Client, context: Arc<CopyObjectsContext>, copy_count: u32) {
+    for _ in 1..=copy_count {
+        if context.cancel.is_cancelled() {
+            break;
+        }
+        let index = context.next_index.fetch_add(1, Ordering::Relaxed) as usize % context.sources.len();
+        let source = &context.sources[index];
+
+        // The copy keeps the part of the key after the source prefix, so the layout is mirrored under the destination
+        let relative_key = source.key.strip_prefix(&context.source_prefix).unwrap_or(&source.key);
+        let destination_key = format!("{0}{1}", context.destination_prefix, relative_key);
+        let copy_source = copy_source(&context.bucket_name, &source.key);
+
+        let request_start = Instant::now();
+        let copy_result = match source.size > MAX_SINGLE_PUT_SIZE {
+            true => copy_multipart(&s3_client, &context.bucket_name, &context.cancel, &copy_source, &destination_key, source.size).await,
+            false => s3_client.copy_object()
+                .bucket(&context.bucket_name)
+                .key(&destination_key)
+                .copy_source(&copy_source)
+                .send().await
+                .map(|_| ())
+                .map_err(|err| retry::error_reason(&err)),
+        };
+
+        match copy_result {
+            Ok(()) => context.run_stats.record(request_start.elapsed(), source.size),
+            Err(reason) => context.run_stats.record_failure(reason),
+        }
+        context.completed_count.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+// Objects over 5GB can't be copied in one request, so they're copied in byte ranges with upload_part_copy.
+// If any part fails, the upload is aborted so the parts already copied don't linger and incur storage charges.
+pub async fn copy_multipart(s3_client: &Client, bucket_name: &str, cancel: &CancellationToken, copy_source: &str, destination_key: &str, object_size: u64) -> Result<(), String> {
+    let create_output = s3_client.create_multipart_upload()
+        .bucket(bucket_name)
+        .key(destination_key)
+        .send().await
+        .map_err(|err| retry::error_reason(&err))?;
+    let upload_id = create_output.upload_id.unwrap_or_default();
+
+    let part_size = COPY_PART_SIZE.max(object_size.div_ceil(MAX_PART_COUNT));
+    let part_count = object_size.div_ceil(part_size);
+
+    let part_results: Vec<Result<CompletedPart, String>> = stream::iter(1..=part_count)
+        .map(|part_number| {
+            let upload_id = &upload_id;
+            async move {
+                if cancel.is_cancelled() {
+                    return Err("Interrupted".to_string());
+                }
+                // Byte ranges are inclusive, and only the final part can be smaller than the others
+                let range_start = (part_number - 1) * part_size;
+                let range_end = (range_start + part_size).min(object_size) - 1;
+
+                let copy_output = s3_client.upload_part_copy()
+                    .bucket(bucket_name)
+                    .key(destination_key)
+                    .upload_id(upload_id)
+                    .part_number(part_number as i32)
+                    .copy_source(copy_source)
+                    .copy_source_range(format!("bytes={0}-{1}", range_start, range_end))
+                    .send().await
+                    .map_err(|err| retry::error_reason(&err))?;
+
+                Ok(CompletedPart::builder()
+                    .part_number(part_number as i32)
+                    .set_e_tag(copy_output.copy_part_result.and_then(|result| result.e_tag))
+                    .build())
+            }
+        })
+        .buffer_unordered(PART_CONCURRENCY)
+        .collect().await;
+
+    let mut completed_parts = match part_results.into_iter().collect::<Result<Vec<CompletedPart>, String>>() {
+        Ok(parts) => parts,
+        Err(reason) => {
+            abort_copy(s3_client, bucket_name, destination_key, &upload_id).await;
+            return Err(reason);
+        }
+    };
+    completed_parts.sort_by_key(|part| part.part_number);
+
+    let complete_result = s3_client.complete_multipart_upload()
+        .bucket(bucket_name)
+        .key(destination_key)
+        .upload_id(&upload_id)
+        .multipart_upload(CompletedMultipartUpload::builder().set_parts(Some(completed_parts)).build())
+        .send().await;
+
+    if let Err(err) = complete_result {
+        abort_copy(s3_client, bucket_name, destination_key, &upload_id).await;
+        return Err(retry::error_reason(&err));
+    }
+    Ok(())
+}
+
+async fn abort_copy(s3_client: &Client, bucket_name: &str, key: &str, upload_id: &str) {
+    let _abort_result = s3_client.abort_multipart_upload()
+        .bucket(bucket_name)
+        .key(key)
+        .upload_id(upload_id)
+        .send().await;
+}
+
+// The copy source header is "bucket/key" with the key URL-encoded, keeping slashes as they are
+pub fn copy_source(bucket_name: &str, key: &str) -> String {
+    let mut encoded_key = String::with_capacity(key.len());
+    for byte in key.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => encoded_key.push(byte as char),
+            _ => encoded_key.push_str(&format!("%{0:02X}", byte)),
+        }
+    }
+    format!("{0}/{1}", bucket_name, encoded_key)
+}
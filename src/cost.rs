@@ -0,0 +1,89 @@
+use aws_sdk_s3::types::StorageClass;
+use colorize::AnsiColor;
+use std::sync::OnceLock;
+
+use crate::output::status;
+
+const BYTES_PER_GB: f64 = 1024.0 * 1024.0 * 1024.0;
+
+// Per-unit prices in USD, set with --price-per-gb-month and --price-per-1000-requests for other
+// regions or S3-compatible backends. Anything not overridden comes from the built-in table.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PriceOverrides {
+    pub per_gb_month: Option<f64>,
+    pub per_thousand_requests: Option<f64>,
+}
+
+static OVERRIDES: OnceLock<PriceOverrides> = OnceLock::new();
+
+// Sets the price overrides for the rest of the process. Called once from main after parsing arguments.
+pub fn init(overrides: PriceOverrides) {
+    OVERRIDES.set(overrides).expect("price overrides already initialized");
+}
+
+struct Prices {
+    per_gb_month: f64,
+    // PUT, COPY, POST and LIST requests share this rate. GET is billed lower and DELETE is free.
+    per_thousand_requests: f64,
+}
+
+// AWS list prices for us-east-1, which other regions are usually close to but rarely below
+fn list_prices(storage_class: &StorageClass) -> Prices {
+    let (per_gb_month, per_thousand_requests) = match storage_class {
+        StorageClass::StandardIa => (0.0125, 0.01),
+        StorageClass::OnezoneIa => (0.01, 0.01),
+        StorageClass::GlacierIr => (0.004, 0.02),
+        StorageClass::Glacier => (0.0036, 0.03),
+        StorageClass::DeepArchive => (0.00099, 0.05),
+        StorageClass::ReducedRedundancy => (0.024, 0.005),
+        // Standard, Intelligent-Tiering in its frequent access tier, and any class newer than this table
+        _ => (0.023, 0.005),
+    };
+    Prices { per_gb_month, per_thousand_requests }
+}
+
+fn prices(storage_class: &StorageClass) -> Prices {
+    let overrides = OVERRIDES.get().copied().unwrap_or_default();
+    let list_prices = list_prices(storage_class);
+    Prices {
+        per_gb_month: overrides.per_gb_month.unwrap_or(list_prices.per_gb_month),
+        per_thousand_requests: overrides.per_thousand_requests.unwrap_or(list_prices.per_thousand_requests),
+    }
+}
+
+fn price_source() -> &'static str {
+    let overrides = OVERRIDES.get().copied().unwrap_or_default();
+    match (overrides.per_gb_month, overrides.per_thousand_requests) {
+        (Some(_), Some(_)) => "prices given on the command line",
+        (None, None) => "us-east-1 list prices, override with --price-per-gb-month and --price-per-1000-requests",
+        _ => "us-east-1 list prices with the overrides given on the command line",
+    }
+}
+
+// Prints the request charges of a create run and what keeping the objects would cost each month.
+// Minimum storage durations and object sizes of the infrequent access and archive classes aren't included.
+pub fn print_create_estimate(storage_class: &StorageClass, request_count: u64, stored_bytes: u64) {
+    let prices = prices(storage_class);
+    let gigabytes = stored_bytes as f64 / BYTES_PER_GB;
+    status!("{0}", format!("Estimated cost ({0})", price_source()).bold());
+    status!("  {0:<14} {1} for {2} write requests", "Requests",
+        format_usd(request_count as f64 / 1000.0 * prices.per_thousand_requests).yellow(), request_count);
+    status!("  {0:<14} {1} per month for {2:.3} GB in {3}", "Storage",
+        format_usd(gigabytes * prices.per_gb_month).yellow(), gigabytes, storage_class.as_str());
+}
+
+// Prints the request charges of a cleanup. Only the listing is charged, since deletes are free.
+pub fn print_cleanup_estimate(list_request_count: u64) {
+    let prices = prices(&StorageClass::Standard);
+    status!("{0}", format!("Estimated cost ({0})", price_source()).bold());
+    status!("  {0:<14} {1} for {2} list requests, deletes are free", "Requests",
+        format_usd(list_request_count as f64 / 1000.0 * prices.per_thousand_requests).yellow(), list_request_count);
+}
+
+// Small runs cost fractions of a cent, so extra decimal places are kept for them
+fn format_usd(amount: f64) -> String {
+    match amount < 1.0 {
+        true => format!("${0:.4}", amount),
+        false => format!("${0:.2}", amount),
+    }
+}
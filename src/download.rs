@@ -0,0 +1,205 @@
+use aws_sdk_s3::Client;
+use colorize::AnsiColor;
+use futures::future::join_all;
+use std::{path::{Path, PathBuf}, sync::{atomic::{AtomicU64, Ordering}, Arc}, time::Instant};
+use tokio::io::AsyncWriteExt;
+use tokio_util::sync::CancellationToken;
+
+use crate::cli::DownloadDirectoryArgs;
+use crate::interrupt;
+use crate::output::status;
+use crate::retry::SdkContext;
+use crate::stats::RunStats;
+use crate::{new_progress_bar, prompt_worker_count, retry, track_progress};
+
+// An object to download, with the file it's written to
+struct RemoteObject {
+    key: String,
+    path: PathBuf,
+}
+
+// Settings and shared counters for every worker taking part in a directory download
+struct DownloadDirectoryContext {
+    bucket_name: String,
+    objects: Vec<RemoteObject>,
+    overwrite: bool,
+    // Position of the next object to download, shared so workers pick up whichever object is next
+    next_index: AtomicU64,
+    skipped_count: AtomicU64,
+    cancel: CancellationToken,
+    completed_count: Arc<AtomicU64>,
+    run_stats: RunStats,
+}
+
+#[tracing::instrument(skip_all, fields(bucket = %bucket_name))]
+pub async fn operation_download_directory(s3_client: Client, bucket_name: String, args: DownloadDirectoryArgs) -> anyhow::Result<()> {
+    let prefix = match args.prefix {
+        Some(prefix) => prefix,
+        None => inquire::Text::new("Download objects under which prefix? (leave blank for the whole bucket)").prompt()?,
+    };
+
+    let directory = match args.directory {
+        Some(directory) => directory,
+        None => inquire::Text::new("Which local directory should I download to?")
+            .with_default(".")
+            .prompt()?.into(),
+    };
+
+    let objects = list_remote_objects(&s3_client, &bucket_name, &prefix, &directory).await?;
+    if objects.is_empty() {
+        status!("{0}", "No objects found to download".blue());
+        return Ok(());
+    }
+    status!("Found {0} objects to download", objects.len());
+
+    let worker_count = match args.concurrency {
+        Some(concurrency) => concurrency,
+        None => prompt_worker_count()?,
+    };
+
+    let overwrite = match args.overwrite {
+        Some(overwrite) => overwrite,
+        None => inquire::Confirm::new("Overwrite local files that already exist?")
+            .with_default(false)
+            .prompt()?,
+    };
+
+    let operation = interrupt::start_operation();
+    let object_count = objects.len() as u64;
+    let context = Arc::new(DownloadDirectoryContext {
+        bucket_name,
+        objects,
+        overwrite,
+        next_index: AtomicU64::new(0),
+        skipped_count: AtomicU64::new(0),
+        cancel: operation.token(),
+        completed_count: Arc::new(AtomicU64::new(0)),
+        run_stats: RunStats::default(),
+    });
+
+    let progress = new_progress_bar(object_count);
+    let progress_task = tokio::spawn(track_progress(progress.clone(), context.completed_count.clone(), vec![context.run_stats.byte_counter()]));
+
+    let start_time = Instant::now();
+
+    let mut join_handle_list = vec![];
+    for _ in 0..worker_count.min(object_count as u32) {
+        join_handle_list.push(tokio::spawn(download_worker(s3_client.clone(), context.clone())));
+    }
+    join_all(join_handle_list).await;
+
+    progress_task.abort();
+    progress.finish_and_clear();
+    interrupt::report_if_interrupted(&context.cancel);
+    context.run_stats.summarize(start_time.elapsed())
+        .with_setting("Directory", directory.display())
+        .with_setting("Skipped", context.skipped_count.load(Ordering::Relaxed))
+        .print("Download directory", &context.bucket_name);
+    Ok(())
+}
+
+// Lists the objects under the prefix and works out where each one is written. The part of the key after
+// the prefix becomes the path under the directory, with each / separated segment a subdirectory.
+async fn list_remote_objects(s3_client: &Client, bucket_name: &String, prefix: &str, directory: &Path) -> anyhow::Result<Vec<RemoteObject>> {
+    let mut objects = vec![];
+    let mut unsafe_count = 0;
+
+    let mut page_token = None;
+    loop {
+        let object_list = s3_client.list_objects_v2()
+            .bucket(bucket_name)
+            .prefix(prefix)
+            .set_continuation_token(page_token)
+            .send().await
+            .sdk_context("Unable to list objects")?;
+
+        page_token = object_list.next_continuation_token;
+        for key in object_list.contents.unwrap_or_default().into_iter().filter_map(|object| object.key) {
+            // Keys ending in / are folder placeholders created by the console, with no file to write
+            if key.ends_with('/') {
+                continue;
+            }
+            let relative_key = key.strip_prefix(prefix).unwrap_or(&key);
+            match local_path(directory, relative_key) {
+                Some(path) => objects.push(RemoteObject { key, path }),
+                None => unsafe_count += 1,
+            }
+        }
+        if page_token.is_none() { break; }
+    }
+
+    if unsafe_count > 0 {
+        status!("{0}", format!("Skipping {0} objects whose keys can't be mapped to a path inside the directory", unsafe_count).yellow());
+    }
+    Ok(objects)
+}
+
+// Keys are arbitrary strings, so any with empty, . or .. segments are refused rather than risk
+// writing outside the target directory
+fn local_path(directory: &Path, relative_key: &str) -> Option<PathBuf> {
+    let mut path = directory.to_path_buf();
+    for segment in relative_key.split('/') {
+        if segment.is_empty() || segment == "." || segment == ".." {
+            return None;
+        }
+        path.push(segment);
+    }
+    Some(path)
+}
+
+async fn download_worker(s3_client: Client, context: Arc<DownloadDirectoryContext>) {
+    loop {
+        if context.cancel.is_cancelled() {
+            break;
+        }
+        let index = context.next_index.fetch_add(1, Ordering::Relaxed) as usize;
+        let Some(object) = context.objects.get(index) else {
+            break;
+        };
+
+        if !context.overwrite && object.path.exists() {
+            context.skipped_count.fetch_add(1, Ordering::Relaxed);
+            context.completed_count.fetch_add(1, Ordering::Relaxed);
+            continue;
+        }
+
+        let request_start = Instant::now();
+        match download_object(&s3_client, &context, object).await {
+            Ok(bytes) => context.run_stats.record(request_start.elapsed(), bytes),
+            Err(reason) => {
+                // A partly written file would look like a complete download to a later run that skips existing files
+                let _remove_result = tokio::fs::remove_file(&object.path).await;
+                context.run_stats.record_failure(reason);
+            }
+        }
+        context.completed_count.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+// Streams the body to disk chunk by chunk as it arrives, so memory use doesn't grow with object size.
+// Returns the number of bytes written.
+async fn download_object(s3_client: &Client, context: &DownloadDirectoryContext, object: &RemoteObject) -> Result<u64, String> {
+    let mut output = s3_client.get_object()
+        .bucket(&context.bucket_name)
+        .key(&object.key)
+        .send().await
+        .map_err(|err| retry::error_reason(&err))?;
+
+    if let Some(parent) = object.path.parent() {
+        tokio::fs::create_dir_all(parent).await
+            .map_err(|err| format!("Unable to create directory {0}: {1}", parent.display(), err))?;
+    }
+    let mut file = tokio::fs::File::create(&object.path).await
+        .map_err(|err| format!("Unable to create {0}: {1}", object.path.display(), err))?;
+
+    let mut bytes_written = 0;
+    while let Some(chunk) = output.body.next().await {
+        let chunk = chunk.map_err(|err| format!("Download interrupted: {0}", err))?;
+        file.write_all(&chunk).await
+            .map_err(|err| format!("Unable to write {0}: {1}", object.path.display(), err))?;
+        bytes_written += chunk.len() as u64;
+    }
+    file.flush().await
+        .map_err(|err| format!("Unable to write {0}: {1}", object.path.display(), err))?;
+    Ok(bytes_written)
+}
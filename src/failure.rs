@@ -0,0 +1,73 @@
+use inquire::InquireError;
+
+// Broad causes of a failed run, each ending the process with its own exit code so scripts can react
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    // Missing, invalid or expired credentials
+    Auth,
+    // Credentials were accepted but aren't allowed to do what was asked
+    Permission,
+    NotFound,
+    // S3 kept asking for requests to slow down
+    Throttling,
+    // Timeouts and connection failures
+    Network,
+    // Escape or Ctrl-C at a prompt
+    Cancelled,
+    Other,
+}
+
+impl ErrorKind {
+    // 2 is left out, since clap already exits with it for invalid arguments. A cancelled prompt uses 130,
+    // the same as quitting with Ctrl-C.
+    pub fn exit_code(self) -> i32 {
+        match self {
+            ErrorKind::Other => 1,
+            ErrorKind::Auth => 3,
+            ErrorKind::Permission => 4,
+            ErrorKind::NotFound => 5,
+            ErrorKind::Throttling => 6,
+            ErrorKind::Network => 7,
+            ErrorKind::Cancelled => 130,
+        }
+    }
+
+    // Failed credentials fail every later operation the same way, so there's no point returning to the menu
+    pub fn is_recoverable(self) -> bool {
+        self != ErrorKind::Auth
+    }
+}
+
+// An error with a known cause, kept through anyhow so the cause can be found again in main
+#[derive(Debug)]
+pub struct Failure {
+    pub kind: ErrorKind,
+    message: String,
+}
+
+impl Failure {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Failure {
+        Failure { kind, message: message.into() }
+    }
+}
+
+impl std::fmt::Display for Failure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{0}", self.message)
+    }
+}
+
+impl std::error::Error for Failure {}
+
+// Uses the first cause that's known anywhere in the error's chain, so added context doesn't hide it
+pub fn classify(err: &anyhow::Error) -> ErrorKind {
+    for cause in err.chain() {
+        if let Some(failure) = cause.downcast_ref::<Failure>() {
+            return failure.kind;
+        }
+        if let Some(InquireError::OperationCanceled | InquireError::OperationInterrupted) = cause.downcast_ref::<InquireError>() {
+            return ErrorKind::Cancelled;
+        }
+    }
+    ErrorKind::Other
+}
@@ -0,0 +1,142 @@
+use anyhow::Context;
+use aws_sdk_s3::Client;
+use colorize::AnsiColor;
+use futures::future::join_all;
+use inquire::{validator::Validation, CustomUserError};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+use std::{sync::{atomic::{AtomicU64, Ordering}, Arc}, time::Instant};
+use tokio_util::sync::CancellationToken;
+
+use crate::cli::HeadObjectsArgs;
+use crate::interrupt;
+use crate::output::status;
+use crate::stats::RunStats;
+use crate::{list_object_keys, new_progress_bar, parse_count, prompt_worker_count, retry, split_object_count, track_progress, validate_number};
+
+// Settings and shared counters for every worker taking part in a head objects run
+struct HeadObjectsContext {
+    bucket_name: String,
+    keys: Vec<String>,
+    // Percentage of requests sent for keys that don't exist, from 0 to 100
+    miss_percent: u32,
+    hit_count: AtomicU64,
+    miss_count: AtomicU64,
+    cancel: CancellationToken,
+    completed_count: Arc<AtomicU64>,
+    run_stats: RunStats,
+}
+
+#[tracing::instrument(skip_all, fields(bucket = %bucket_name))]
+pub async fn operation_head_objects(s3_client: Client, bucket_name: String, args: HeadObjectsArgs) -> anyhow::Result<()> {
+    let keys = list_object_keys(&s3_client, &bucket_name).await?;
+    if keys.is_empty() {
+        status!("{0}", "No objects found in this bucket".blue());
+        return Ok(());
+    }
+    status!("Found {0} objects to sample", keys.len());
+
+    let request_count = match args.count {
+        Some(count) => count,
+        None => {
+            let request_count = inquire::Text::new("How many HEAD requests should I send?")
+                .with_default(&keys.len().to_string())
+                .with_validator(validate_number)
+                .prompt()?;
+            parse_count(&request_count).context("Invalid request count")?
+        }
+    };
+
+    let worker_count = match args.concurrency {
+        Some(concurrency) => concurrency,
+        None => prompt_worker_count()?,
+    };
+
+    let miss_percent = match args.miss_percent {
+        Some(miss_percent) => miss_percent,
+        None => inquire::Text::new("Percentage of requests for missing keys")
+            .with_default("0")
+            .with_validator(validate_percent)
+            .prompt()?.parse()?,
+    };
+
+    let operation = interrupt::start_operation();
+    let context = Arc::new(HeadObjectsContext {
+        bucket_name,
+        keys,
+        miss_percent,
+        hit_count: AtomicU64::new(0),
+        miss_count: AtomicU64::new(0),
+        cancel: operation.token(),
+        completed_count: Arc::new(AtomicU64::new(0)),
+        run_stats: RunStats::default(),
+    });
+
+    let progress = new_progress_bar(request_count as u64);
+    let progress_task = tokio::spawn(track_progress(progress.clone(), context.completed_count.clone(), vec![context.run_stats.byte_counter()]));
+
+    let start_time = Instant::now();
+
+    let mut join_handle_list = vec![];
+    for worker_request_count in split_object_count(request_count, worker_count) {
+        join_handle_list.push(tokio::spawn(head_object(s3_client.clone(), context.clone(), worker_request_count)));
+    }
+    join_all(join_handle_list).await;
+
+    progress_task.abort();
+    progress.finish_and_clear();
+    interrupt::report_if_interrupted(&context.cancel);
+
+    let hit_count = context.hit_count.load(Ordering::Relaxed);
+    let miss_count = context.miss_count.load(Ordering::Relaxed);
+    let hit_rate = hit_count as f64 * 100.0 / (hit_count + miss_count).max(1) as f64;
+    context.run_stats.summarize(start_time.elapsed())
+        .with_setting("Hits", hit_count)
+        .with_setting("Misses", miss_count)
+        .with_setting("Hit rate", format!("{0:.1}%", hit_rate))
+        .print("Head objects", &context.bucket_name);
+    Ok(())
+}
+
+async fn head_object(s3_client: Client, context: Arc<HeadObjectsContext>, request_count: u32) {
+    let mut rng = SmallRng::from_entropy();
+
+    for _ in 1..=request_count {
+        if context.cancel.is_cancelled() {
+            break;
+        }
+
+        // Missing keys are made by appending a random suffix to an existing key, so they share its prefix
+        let key = &context.keys[rng.gen_range(0..context.keys.len())];
+        let key = match rng.gen_range(0..100) < context.miss_percent {
+            true => format!("{0}.missing-{1}", key, uuid::Uuid::new_v4().simple()),
+            false => key.clone(),
+        };
+
+        let request_start = Instant::now();
+        let head_result = s3_client.head_object()
+            .bucket(&context.bucket_name)
+            .key(key)
+            .send().await;
+
+        // A 404 is a successful answer to "does this exist?", so it counts towards latency rather than failures
+        match head_result {
+            Ok(_) => {
+                context.hit_count.fetch_add(1, Ordering::Relaxed);
+                context.run_stats.record(request_start.elapsed(), 0);
+            }
+            Err(err) if err.as_service_error().is_some_and(|service_err| service_err.is_not_found()) => {
+                context.miss_count.fetch_add(1, Ordering::Relaxed);
+                context.run_stats.record(request_start.elapsed(), 0);
+            }
+            Err(err) => context.run_stats.record_failure(retry::error_reason(&err)),
+        }
+        context.completed_count.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+fn validate_percent(input: &str) -> Result<Validation, CustomUserError> {
+    match input.parse::<u32>() {
+        Ok(percent) if percent <= 100 => Ok(Validation::Valid),
+        _ => Ok(Validation::Invalid("Invalid percentage specified. Please use a value from 0 - 100".into())),
+    }
+}
@@ -0,0 +1,138 @@
+use aws_sdk_s3::Client;
+use colorize::AnsiColor;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::cli::BucketInfoArgs;
+use crate::interrupt;
+use crate::output::{self, status};
+use crate::retry::SdkContext;
+use crate::{parse_count, validate_optional_number};
+
+// Object counts and sizes for one storage class
+#[derive(Default, Serialize)]
+struct StorageClassUsage {
+    objects: u64,
+    bytes: u64,
+}
+
+// Current contents of a bucket, as found by listing it
+#[derive(Serialize)]
+struct BucketInfoReport {
+    bucket: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    prefix: String,
+    objects: u64,
+    total_bytes: u64,
+    // Set when the scan stopped at the object limit or was interrupted, so the totals are a lower bound
+    truncated: bool,
+    storage_classes: BTreeMap<String, StorageClassUsage>,
+}
+
+#[tracing::instrument(skip_all, fields(bucket = %bucket_name))]
+pub async fn operation_bucket_info(s3_client: Client, bucket_name: String, args: BucketInfoArgs) -> anyhow::Result<()> {
+    let prefix = match args.prefix {
+        Some(prefix) => prefix,
+        None => inquire::Text::new("Only count objects under which prefix? (leave blank for the whole bucket)").prompt()?,
+    };
+
+    // Listing returns 1,000 objects per request, so a bucket with hundreds of millions of objects takes a long time to scan
+    let max_objects = match args.max_objects {
+        Some(max_objects) => max_objects,
+        None => {
+            let max_objects = inquire::Text::new("Stop after scanning how many objects? (leave blank for no limit)")
+                .with_validator(validate_optional_number)
+                .prompt()?;
+            parse_count(&max_objects).map(u64::from).unwrap_or_default()
+        }
+    };
+
+    let operation = interrupt::start_operation();
+    let cancel = operation.token();
+    let mut report = BucketInfoReport {
+        bucket: bucket_name,
+        prefix,
+        objects: 0,
+        total_bytes: 0,
+        truncated: false,
+        storage_classes: BTreeMap::new(),
+    };
+
+    let mut page_token = None;
+    loop {
+        if cancel.is_cancelled() || (max_objects > 0 && report.objects >= max_objects) {
+            report.truncated = true;
+            break;
+        }
+        let object_list = s3_client.list_objects_v2()
+            .bucket(&report.bucket)
+            .prefix(&report.prefix)
+            .set_continuation_token(page_token)
+            .send().await
+            .sdk_context("Unable to list objects")?;
+
+        for object in object_list.contents.unwrap_or_default() {
+            let size = object.size.unwrap_or_default() as u64;
+            // Some S3-compatible backends leave the storage class out for objects in the default class
+            let storage_class = object.storage_class.map(|class| class.as_str().to_string()).unwrap_or("STANDARD".to_string());
+            let usage = report.storage_classes.entry(storage_class).or_default();
+            usage.objects += 1;
+            usage.bytes += size;
+            report.objects += 1;
+            report.total_bytes += size;
+        }
+
+        page_token = object_list.next_continuation_token;
+        if page_token.is_none() { break; }
+        if report.objects.is_multiple_of(100_000) {
+            status!("Scanned {0} objects so far", report.objects);
+        }
+    }
+    interrupt::report_if_interrupted(&cancel);
+
+    print_report(&report);
+    Ok(())
+}
+
+fn print_report(report: &BucketInfoReport) {
+    if output::is_json() {
+        println!("{0}", serde_json::to_string(report).unwrap());
+        return;
+    }
+
+    println!("{0}", format!("Bucket info for {0}", report.bucket).bold());
+    if !report.prefix.is_empty() {
+        println!("  {0:<14} {1}", "Prefix", report.prefix);
+    }
+    let approximate = if report.truncated { " (lower bound, the scan stopped early)" } else { "" };
+    println!("  {0:<14} {1}{2}", "Objects", report.objects.to_string().green(), approximate.yellow());
+    println!("  {0:<14} {1}{2}", "Total size", format_size(report.total_bytes).green(), approximate.yellow());
+
+    if report.storage_classes.is_empty() {
+        return;
+    }
+    println!("  Storage classes");
+    for (storage_class, usage) in &report.storage_classes {
+        let share = usage.bytes as f64 * 100.0 / report.total_bytes.max(1) as f64;
+        // Padding is applied before coloring, since the escape codes would otherwise count towards the width
+        println!("  {0:>24}  {1} objects  {2}  {3}",
+            storage_class,
+            format!("{0:>10}", usage.objects).green(),
+            format!("{0:>10}", format_size(usage.bytes)).green(),
+            format!("{0:>5.1}%", share).blue());
+    }
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    match unit {
+        0 => format!("{0} B", bytes),
+        _ => format!("{0:.2} {1}", size, UNITS[unit]),
+    }
+}
@@ -0,0 +1,69 @@
+use colorize::AnsiColor;
+use std::sync::{atomic::{AtomicBool, Ordering}, Mutex};
+use tokio_util::sync::CancellationToken;
+
+// Token for the operation currently running, if any. The first Ctrl-C cancels it so workers can wind
+// down and report partial results, while a Ctrl-C with nothing left to cancel exits straight away.
+static CURRENT_OPERATION: Mutex<Option<CancellationToken>> = Mutex::new(None);
+
+// Whether the most recent operation was cut short, for callers chaining several operations together
+static LAST_INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+// Replaces the default Ctrl-C behavior for the rest of the process. Called once from main.
+pub fn install() {
+    tokio::spawn(async {
+        while tokio::signal::ctrl_c().await.is_ok() {
+            match CURRENT_OPERATION.lock().unwrap().take() {
+                Some(token) => {
+                    eprintln!("{0}", "Interrupted, waiting for in-flight requests. Press Ctrl-C again to quit immediately".yellow());
+                    LAST_INTERRUPTED.store(true, Ordering::Relaxed);
+                    token.cancel();
+                }
+                None => {
+                    crate::session::print_report();
+                    std::process::exit(130)
+                }
+            }
+        }
+    });
+}
+
+// Marks the start of an interruptible operation, which lasts until the returned guard is dropped
+pub fn start_operation() -> OperationGuard {
+    let token = CancellationToken::new();
+    LAST_INTERRUPTED.store(false, Ordering::Relaxed);
+    *CURRENT_OPERATION.lock().unwrap() = Some(token.clone());
+    OperationGuard { token }
+}
+
+// Clears the flag before something that may not start an interruptible operation of its own
+pub fn reset_interrupted() {
+    LAST_INTERRUPTED.store(false, Ordering::Relaxed);
+}
+
+pub fn last_operation_interrupted() -> bool {
+    LAST_INTERRUPTED.load(Ordering::Relaxed)
+}
+
+pub struct OperationGuard {
+    token: CancellationToken,
+}
+
+impl OperationGuard {
+    pub fn token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+}
+
+impl Drop for OperationGuard {
+    fn drop(&mut self) {
+        CURRENT_OPERATION.lock().unwrap().take();
+    }
+}
+
+// Flags a summary as covering only part of the run, printed just before the summary itself
+pub fn report_if_interrupted(token: &CancellationToken) {
+    if token.is_cancelled() {
+        crate::output::status!("{0}", "Run interrupted, results below are partial".yellow());
+    }
+}
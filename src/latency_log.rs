@@ -0,0 +1,52 @@
+use anyhow::Context;
+use std::{fs::File, io::{BufWriter, Write}, path::Path, sync::{Mutex, OnceLock}, time::{Duration, SystemTime, UNIX_EPOCH}};
+
+// Rows are collected in memory and written out in large chunks, so workers rarely wait on the disk
+const BUFFER_SIZE: usize = 1024 * 1024;
+
+// CSV file given with --latency-out, with one row per request. Unset unless the option was given.
+static LOG: OnceLock<Mutex<BufWriter<File>>> = OnceLock::new();
+
+pub fn init(path: &Path) -> anyhow::Result<()> {
+    let file = File::create(path).with_context(|| format!("Unable to create latency file {0}", path.display()))?;
+    let mut writer = BufWriter::with_capacity(BUFFER_SIZE, file);
+    writeln!(writer, "timestamp,operation,key,size,latency_ms,outcome")?;
+    LOG.set(Mutex::new(writer)).map_err(|_| anyhow::anyhow!("latency log already initialized"))
+}
+
+// Records a finished request. The timestamp is when the request started, in seconds since the Unix epoch,
+// and the outcome is "ok" or the reason the request failed. Requests are logged at debug level too,
+// whether or not a latency file was asked for.
+pub fn record(operation: &str, key: &str, size: u64, latency: Duration, outcome: Result<(), &str>) {
+    let latency_ms = latency.as_secs_f64() * 1000.0;
+    match outcome {
+        Ok(()) => tracing::debug!(operation, key, size, latency_ms, "request succeeded"),
+        Err(reason) => tracing::warn!(operation, key, latency_ms, reason, "request failed"),
+    }
+    let Some(log) = LOG.get() else {
+        return;
+    };
+    let started = SystemTime::now().checked_sub(latency).unwrap_or(SystemTime::now());
+    let timestamp = started.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs_f64();
+    let outcome = outcome.err().unwrap_or("ok");
+
+    let mut writer = log.lock().unwrap();
+    let _write_result = writeln!(writer, "{0:.3},{1},{2},{3},{4:.3},{5}",
+        timestamp, operation, csv_field(key), size, latency_ms, csv_field(outcome));
+}
+
+// Writes out any buffered rows. Called once each operation finishes, so the file is complete even if
+// the process is later ended with Ctrl-C.
+pub fn flush() {
+    if let Some(log) = LOG.get() {
+        let _flush_result = log.lock().unwrap().flush();
+    }
+}
+
+// Keys and error messages can contain commas or quotes, which need quoting to keep the columns aligned
+fn csv_field(value: &str) -> String {
+    match value.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{0}\"", value.replace('"', "\"\"")),
+        false => value.to_string(),
+    }
+}
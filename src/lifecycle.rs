@@ -0,0 +1,107 @@
+use anyhow::Context;
+use aws_sdk_s3::{error::ProvideErrorMetadata, types::{AbortIncompleteMultipartUpload, BucketLifecycleConfiguration, ExpirationStatus, LifecycleExpiration, LifecycleRule, LifecycleRuleFilter}, Client};
+use colorize::AnsiColor;
+
+use crate::cli::LifecycleArgs;
+use crate::retry::SdkContext;
+use crate::{confirm_destructive, parse_count, validate_number};
+
+// ID of the rule this tool manages. Applying it again replaces the earlier version rather than adding another.
+const RULE_ID: &str = "s3-stress";
+
+#[tracing::instrument(skip_all, fields(bucket = %bucket_name))]
+pub async fn operation_configure_lifecycle(s3_client: Client, bucket_name: String, args: LifecycleArgs, assume_yes: bool, dry_run: bool) -> anyhow::Result<()> {
+    let prefix = match args.prefix {
+        Some(prefix) => prefix,
+        None => inquire::Text::new("Apply the rule to objects under which prefix? (leave blank for the whole bucket)").prompt()?,
+    };
+
+    let expire_days = match args.expire_days {
+        Some(days) => days,
+        None => {
+            let expire_days = inquire::Text::new("Expire objects after how many days? (0 to never expire)")
+                .with_default("0")
+                .with_validator(validate_number)
+                .prompt()?;
+            parse_count(&expire_days).context("Invalid number of days")?
+        }
+    };
+
+    let abort_multipart_days = match args.abort_multipart_days {
+        Some(days) => days,
+        None => {
+            let abort_multipart_days = inquire::Text::new("Abort incomplete multipart uploads after how many days? (0 to leave them)")
+                .with_default("1")
+                .with_validator(validate_number)
+                .prompt()?;
+            parse_count(&abort_multipart_days).context("Invalid number of days")?
+        }
+    };
+
+    if expire_days == 0 && abort_multipart_days == 0 {
+        anyhow::bail!("Nothing to configure: set the expiration days, the multipart abort days, or both");
+    }
+
+    let rule = LifecycleRule::builder()
+        .id(RULE_ID)
+        .status(ExpirationStatus::Enabled)
+        .filter(LifecycleRuleFilter::builder().prefix(prefix.clone()).build())
+        .set_expiration((expire_days > 0).then(|| LifecycleExpiration::builder().days(expire_days as i32).build()))
+        .set_abort_incomplete_multipart_upload((abort_multipart_days > 0)
+            .then(|| AbortIncompleteMultipartUpload::builder().days_after_initiation(abort_multipart_days as i32).build()))
+        .build()?;
+
+    let scope = match prefix.is_empty() {
+        true => "every object".to_string(),
+        false => format!("objects under {0}", prefix),
+    };
+    let mut actions = vec![];
+    if expire_days > 0 {
+        actions.push(format!("expire {0} after {1} days", scope, expire_days));
+    }
+    if abort_multipart_days > 0 {
+        actions.push(format!("abort incomplete multipart uploads after {0} days", abort_multipart_days));
+    }
+    let description = actions.join(" and ");
+
+    if dry_run {
+        println!("{0}", format!("Dry run: a lifecycle rule would {0} in bucket {1}", description, bucket_name).yellow());
+        return Ok(());
+    }
+    // Expiration permanently deletes data once it runs, so it needs the same confirmation as a cleanup
+    if expire_days > 0 && !confirm_destructive(&format!("Apply a lifecycle rule to {0} in bucket {1}?", description, bucket_name), assume_yes)? {
+        return Ok(());
+    }
+
+    // Putting a lifecycle configuration replaces every rule on the bucket, so any rules set up by
+    // other means are kept alongside this one
+    let mut rules: Vec<LifecycleRule> = existing_rules(&s3_client, &bucket_name).await?.into_iter()
+        .filter(|existing| existing.id() != Some(RULE_ID))
+        .collect();
+    let kept_count = rules.len();
+    rules.push(rule);
+
+    s3_client.put_bucket_lifecycle_configuration()
+        .bucket(&bucket_name)
+        .lifecycle_configuration(BucketLifecycleConfiguration::builder().set_rules(Some(rules)).build()?)
+        .send().await
+        .sdk_context(&format!("Unable to apply the lifecycle rule to bucket {0}", bucket_name))?;
+
+    println!("{0}", format!("Lifecycle rule applied: {0}", description).green());
+    if kept_count > 0 {
+        println!("Kept {0} existing lifecycle rules on the bucket", kept_count);
+    }
+    println!("{0}", "S3 evaluates lifecycle rules about once a day, so objects aren't removed the moment they become eligible".blue());
+    Ok(())
+}
+
+async fn existing_rules(s3_client: &Client, bucket_name: &String) -> anyhow::Result<Vec<LifecycleRule>> {
+    let get_result = s3_client.get_bucket_lifecycle_configuration()
+        .bucket(bucket_name)
+        .send().await;
+    match get_result {
+        Ok(output) => Ok(output.rules.unwrap_or_default()),
+        Err(err) if err.code() == Some("NoSuchLifecycleConfiguration") => Ok(vec![]),
+        Err(err) => Err(err).sdk_context(&format!("Unable to read the lifecycle configuration of bucket {0}", bucket_name)),
+    }
+}
@@ -0,0 +1,207 @@
+use anyhow::Context;
+use aws_sdk_s3::Client;
+use colorize::AnsiColor;
+use inquire::{validator::Validation, CustomUserError};
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+use crate::cli::ListBenchmarkArgs;
+use crate::interrupt;
+use crate::output::{self, status};
+use crate::retry;
+use crate::stats::RunStats;
+use crate::{parse_count, validate_number};
+
+// ListObjectsV2 returns at most this many keys per page, whatever max-keys asks for
+pub const MAX_PAGE_SIZE: i32 = 1000;
+
+// Results of every pass at one page size
+#[derive(Serialize)]
+struct PageSizeResult {
+    page_size: i32,
+    pages: u64,
+    // Keys and common prefixes, since a delimited listing returns "directories" in place of keys
+    keys: u64,
+    pages_per_second: f64,
+    keys_per_second: f64,
+    // Averages over the passes, of how soon the first page arrived and how long the whole listing took
+    first_page_ms: f64,
+    pass_seconds: f64,
+    p50_ms: f64,
+    p99_ms: f64,
+    failed: u64,
+}
+
+#[derive(Serialize)]
+struct ListBenchmarkReport {
+    bucket: String,
+    prefix: String,
+    delimiter: Option<String>,
+    passes: u32,
+    results: Vec<PageSizeResult>,
+}
+
+#[tracing::instrument(skip_all, fields(bucket = %bucket_name))]
+pub async fn operation_list_benchmark(s3_client: Client, bucket_name: String, args: ListBenchmarkArgs) -> anyhow::Result<()> {
+    let prefix = match args.prefix {
+        Some(prefix) => prefix,
+        None => inquire::Text::new("List which prefix? (leave blank for the whole bucket)").prompt()?,
+    };
+
+    // An empty delimiter lists every key, while "/" lists one "directory" level at a time
+    let delimiter = match args.delimiter {
+        Some(delimiter) => delimiter,
+        None => inquire::Text::new("Delimiter for directory-style listing? (eg. /, leave blank for a flat listing)").prompt()?,
+    };
+    let delimiter = Some(delimiter).filter(|delimiter| !delimiter.is_empty());
+
+    let page_sizes = match args.page_sizes.is_empty() {
+        false => args.page_sizes,
+        true => {
+            let page_sizes = inquire::Text::new("Page sizes to compare, separated by commas")
+                .with_default("100,500,1000")
+                .with_validator(validate_page_sizes)
+                .prompt()?;
+            parse_page_sizes(&page_sizes).context("Invalid page sizes")?
+        }
+    };
+
+    let passes = match args.passes {
+        Some(passes) => passes,
+        None => {
+            let passes = inquire::Text::new("How many times should each page size list the prefix?")
+                .with_default("3")
+                .with_validator(validate_number)
+                .prompt()?;
+            parse_count(&passes).context("Invalid pass count")?.max(1)
+        }
+    };
+
+    let operation = interrupt::start_operation();
+    let cancel = operation.token();
+    let mut report = ListBenchmarkReport { bucket: bucket_name, prefix, delimiter, passes, results: vec![] };
+
+    for page_size in page_sizes {
+        status!("Listing with {0} keys per page, {1} passes", page_size, passes);
+        let run_stats = RunStats::default();
+        let mut key_count: u64 = 0;
+        let (mut first_page_total, mut first_pages) = (Duration::ZERO, 0);
+        let (mut pass_total, mut completed_passes) = (Duration::ZERO, 0);
+        let start_time = Instant::now();
+
+        'passes: for _ in 0..passes {
+            let mut page_token: Option<String> = None;
+            let pass_start = Instant::now();
+            let mut first_page = true;
+            loop {
+                if cancel.is_cancelled() {
+                    break 'passes;
+                }
+                let request_start = Instant::now();
+                let page_result = s3_client.list_objects_v2()
+                    .bucket(&report.bucket)
+                    .prefix(&report.prefix)
+                    .set_delimiter(report.delimiter.clone())
+                    .max_keys(page_size)
+                    .set_continuation_token(page_token.take())
+                    .send().await;
+
+                // A failed page ends the pass, since the listing can't carry on without its continuation token
+                let page = match page_result {
+                    Ok(page) => page,
+                    Err(err) => {
+                        run_stats.record_failure(retry::error_reason(&err));
+                        break;
+                    }
+                };
+                run_stats.record(request_start.elapsed(), 0);
+                if first_page {
+                    first_page_total += pass_start.elapsed();
+                    first_pages += 1;
+                    first_page = false;
+                }
+                key_count += (page.contents().len() + page.common_prefixes().len()) as u64;
+
+                page_token = page.next_continuation_token;
+                if page_token.is_none() {
+                    pass_total += pass_start.elapsed();
+                    completed_passes += 1;
+                    break;
+                }
+            }
+        }
+
+        if cancel.is_cancelled() {
+            break;
+        }
+        let summary = run_stats.summarize(start_time.elapsed());
+        let result = PageSizeResult {
+            page_size,
+            pages: summary.object_count,
+            keys: key_count,
+            pages_per_second: summary.objects_per_second(),
+            keys_per_second: key_count as f64 / summary.elapsed.as_secs_f64().max(f64::EPSILON),
+            first_page_ms: first_page_total.as_secs_f64() * 1000.0 / first_pages.max(1) as f64,
+            pass_seconds: pass_total.as_secs_f64() / completed_passes.max(1) as f64,
+            p50_ms: summary.p50.as_secs_f64() * 1000.0,
+            p99_ms: summary.p99.as_secs_f64() * 1000.0,
+            failed: summary.failed_count,
+        };
+        print_result(&result);
+        report.results.push(result);
+    }
+    interrupt::report_if_interrupted(&cancel);
+
+    print_report(&report);
+    Ok(())
+}
+
+fn print_result(result: &PageSizeResult) {
+    // Padding is applied before coloring, since the escape codes would otherwise count towards the width
+    status!("  {0:>5} keys/page  {1} pages/s  {2} keys/s  first page {3}  p50 {4}  p99 {5}  {6} failed",
+        result.page_size,
+        format!("{0:>8.2}", result.pages_per_second).green(),
+        format!("{0:>10.2}", result.keys_per_second).green(),
+        format!("{0:>8.1}ms", result.first_page_ms).yellow(),
+        format!("{0:>8.1}ms", result.p50_ms).yellow(),
+        format!("{0:>8.1}ms", result.p99_ms).yellow(),
+        result.failed.to_string().red());
+}
+
+fn print_report(report: &ListBenchmarkReport) {
+    if output::is_json() {
+        println!("{0}", serde_json::to_string(report).unwrap());
+        return;
+    }
+
+    println!("{0}", format!("List benchmark summary for {0}", report.bucket).bold());
+    let Some(fastest) = report.results.iter().max_by(|a, b| a.keys_per_second.total_cmp(&b.keys_per_second)) else {
+        println!("{0}", "No page size completed a listing".yellow());
+        return;
+    };
+    let listing = match &report.delimiter {
+        Some(delimiter) => format!("delimited by {0}", delimiter),
+        None => "flat".to_string(),
+    };
+    println!("  {0:<14} {1}", "Listing", listing);
+    println!("  {0:<14} {1}", "Keys per pass", (fastest.keys / report.passes as u64).to_string().green());
+    println!("  {0:<14} {1}", "Fastest page", fastest.page_size.to_string().green());
+    println!("  {0:<14} {1}", "Keys/sec", format!("{0:.2}", fastest.keys_per_second).green());
+    println!("  {0:<14} {1}", "Pages/sec", format!("{0:.2}", fastest.pages_per_second).green());
+    println!("  {0:<14} {1}", "First page", format!("{0:.1}ms", fastest.first_page_ms).yellow());
+    println!("  {0:<14} {1}", "Time per pass", format!("{0:.3}s", fastest.pass_seconds).yellow());
+}
+
+// Page sizes are separated by commas, and each has to be one S3 accepts
+pub fn parse_page_sizes(input: &str) -> Option<Vec<i32>> {
+    input.split(',')
+        .map(|page_size| page_size.trim().parse::<i32>().ok().filter(|page_size| (1..=MAX_PAGE_SIZE).contains(page_size)))
+        .collect()
+}
+
+fn validate_page_sizes(input: &str) -> Result<Validation, CustomUserError> {
+    match parse_page_sizes(input) {
+        Some(_) => Ok(Validation::Valid),
+        None => Ok(Validation::Invalid(format!("Invalid page sizes specified. Please use values from 1 - {0}, separated by commas, eg. 100,1000", MAX_PAGE_SIZE).into())),
+    }
+}
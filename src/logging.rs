@@ -0,0 +1,71 @@
+use std::io::IsTerminal;
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::prelude::*;
+
+// How much diagnostic logging is written to stderr, separate from the results printed on stdout
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum)]
+pub enum LogLevel {
+    #[default]
+    Off,
+    Error,
+    Warn,
+    Info,
+    // Adds a line for every request, with its key, latency and outcome, and every retry attempt
+    Debug,
+    // Adds the SDK's own spans for each request attempt, signing and connection handling
+    Trace,
+}
+
+impl LogLevel {
+    fn filter(self) -> LevelFilter {
+        match self {
+            LogLevel::Off => LevelFilter::OFF,
+            LogLevel::Error => LevelFilter::ERROR,
+            LogLevel::Warn => LevelFilter::WARN,
+            LogLevel::Info => LevelFilter::INFO,
+            LogLevel::Debug => LevelFilter::DEBUG,
+            LogLevel::Trace => LevelFilter::TRACE,
+        }
+    }
+}
+
+// Picks the most verbose of --log-level and -v (debug) or -vv (trace). With neither given, RUST_LOG is
+// used when it holds a plain level name such as debug.
+pub fn effective_level(log_level: LogLevel, verbose: u8) -> LogLevel {
+    let verbose_level = match verbose {
+        0 => LogLevel::Off,
+        1 => LogLevel::Debug,
+        _ => LogLevel::Trace,
+    };
+    let env_level = match (log_level, verbose) {
+        (LogLevel::Off, 0) => std::env::var("RUST_LOG").ok()
+            .and_then(|value| <LogLevel as clap::ValueEnum>::from_str(value.trim(), true).ok())
+            .unwrap_or_default(),
+        _ => LogLevel::Off,
+    };
+    log_level.max(verbose_level).max(env_level)
+}
+
+// Sends logs to stderr, so JSON results and the interactive prompts on stdout aren't interleaved with them.
+// The SDK is only logged in full at trace level, since at debug it writes several lines per request.
+pub fn init(level: LogLevel) {
+    if level == LogLevel::Off {
+        return;
+    }
+    let sdk_level = match level {
+        LogLevel::Trace => LevelFilter::TRACE,
+        other => other.filter().min(LevelFilter::INFO),
+    };
+    let targets = Targets::new()
+        .with_target("s3_stress", level.filter())
+        .with_target("aws", sdk_level)
+        .with_default(LevelFilter::WARN.min(level.filter()));
+
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer()
+            .with_writer(std::io::stderr)
+            .with_ansi(std::io::stderr().is_terminal()))
+        .with(targets)
+        .init();
+}
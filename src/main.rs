@@ -1,20 +1,118 @@
-use aws_config::Region;
+mod autotune;
+mod checkpoint;
+mod checksum;
+mod cli;
+mod config;
+mod connection;
+mod cost;
+mod consistency;
+mod copy;
+mod download;
+mod failure;
+mod head;
+mod info;
+mod interrupt;
+mod latency_log;
+mod lifecycle;
+mod listing;
+mod logging;
+mod mirror;
+mod mixed;
+mod multipart;
+mod output;
+mod presign;
+mod rate_limit;
+mod read;
+mod recent;
+mod regions;
+mod report;
+mod retry;
+mod scenario;
+mod session;
+mod sizes;
+mod stats;
+mod upload;
+
+use aws_config::{sts::AssumeRoleProvider, Region};
 use aws_sdk_account as acct;
 use colorize::AnsiColor;
-use std::{str::FromStr, sync::Arc};
+use std::{collections::{BTreeSet, HashMap}, io::IsTerminal, path::Path, sync::{atomic::{AtomicU64, Ordering}, Arc, Mutex}, time::{Duration, Instant, SystemTime}};
+
+use aws_runtime::env_config::file::{EnvConfigFileKind, EnvConfigFiles};
+use anyhow::{bail, Context};
+use aws_types::{sdk_config::SharedCredentialsProvider, SdkConfig};
+use aws_sdk_s3::config::ProvideCredentials;
+use bytes::Bytes;
+use clap::Parser;
+use aws_sdk_s3::config::{RequestChecksumCalculation, ResponseChecksumValidation};
+use aws_sdk_s3::primitives::DateTime;
+use aws_sdk_s3::{self as s3, primitives::{ByteStream, SdkBody}, types::{BucketAccelerateStatus, BucketCannedAcl, BucketLocationConstraint, CreateBucketConfiguration, BucketVersioningStatus, ChecksumMode, Delete, ObjectCannedAcl, ObjectIdentifier, ObjectOwnership, ServerSideEncryption, StorageClass, VersioningConfiguration}, Client};
+use futures::{future::join_all, stream, StreamExt};
+use tracing::Instrument;
+use indicatif::{ProgressBar, ProgressStyle};
+use inquire::{validator::Validation, CustomUserError, InquireError};
+use rand::{rngs::SmallRng, Rng, RngCore, SeedableRng};
+use tokio::{sync::mpsc, task::JoinHandle};
+use tokio_util::sync::CancellationToken;
 
-use aws_runtime::env_config::file::Builder;
-use aws_types::SdkConfig;
-use aws_sdk_s3::{self as s3, operation::list_objects_v2::ListObjectsV2Output, primitives::{ByteStream, SdkBody}, types::{builders::CreateBucketConfigurationBuilder, BucketLocationConstraint}, Client};
-use futures::future::join_all;
-use inquire::{validator::Validation, CustomUserError};
+use checksum::Checksum;
+use checkpoint::{CheckpointTracker, CleanupCheckpoint, ListPosition};
+use cli::{AutoTuneArgs, BucketInfoArgs, CleanupArgs, Cli, Command, ConsistencyTestArgs, CopyObjectsArgs, CreateBucketArgs, CreateObjectsArgs, DownloadDirectoryArgs, GlobalArgs, HeadObjectsArgs, LifecycleArgs, ListBenchmarkArgs, MirrorBucketArgs, MixedWorkloadArgs, PresignArgs, ReadObjectsArgs, UploadDirectoryArgs};
+use config::RunConfig;
+use failure::{ErrorKind, Failure};
+use connection::{ConnectionStrategy, HttpSettings, ThrottleCounter};
+use output::status;
+use rate_limit::RateLimiter;
+use recent::Setting;
+use regions::RegionClients;
+use retry::SdkContext;
+use sizes::{SizeDistribution, SizeHistogram};
+use stats::RunStats;
 
 #[tokio::main]
 async fn main() {
-    operation_select().await;
+    if let Err(err) = run().await {
+        eprintln!("{0}", format!("Error: {0:#}", err).red());
+        std::process::exit(failure::classify(&err).exit_code());
+    }
+}
+
+async fn run() -> anyhow::Result<()> {
+    let mut cli = Cli::parse();
+    interrupt::install();
+
+    // Precedence is command line flags, then the config file, then interactive prompts
+    let config = match &cli.global.config {
+        Some(path) => RunConfig::load(path)?,
+        None => RunConfig::default(),
+    };
+    config.apply_to_global(&mut cli.global);
+    output::init(cli.global.output);
+    logging::init(logging::effective_level(cli.global.log_level, cli.global.verbose));
+    cost::init(cost::PriceOverrides {
+        per_gb_month: cli.global.price_per_gb_month,
+        per_thousand_requests: cli.global.price_per_thousand_requests,
+    });
+    if let Some(path) = &cli.global.latency_out {
+        latency_log::init(path)?;
+    }
+    if let Some(path) = &cli.global.report_out {
+        report::init(path, cli.global.report_format)?;
+    }
+    recent::init(!cli.global.no_save);
+
+    let run_result = match (cli.global.scenario.clone(), cli.command) {
+        (Some(_), Some(_)) => bail!("--scenario runs the operations listed in the file, so it can't be combined with a subcommand"),
+        (Some(path), None) => scenario::run_scenario(cli.global, config, &path).await,
+        (None, Some(command)) => run_command(cli.global, &config, command).await,
+        (None, None) => operation_select(cli.global, config).await,
+    };
+    recent::save();
+    run_result
 }
 
 // This trait makes it easier to get an Arc<T> from various types
+#[allow(clippy::wrong_self_convention)]
 trait AsArc {
     fn as_arc(self) -> Arc<Self> where Self: Sized {
         Arc::new(self)
@@ -26,272 +124,3515 @@ impl AsArc for acct::Client {}
 impl AsArc for String {}
 impl AsArc for Vec<String> {}
 impl AsArc for SdkConfig {}
+impl AsArc for ConnectionOptions {}
+
+// Settings that control how S3 clients reach the service, applied to every client we construct
+#[derive(Clone, Debug, Default)]
+struct ConnectionOptions {
+    // Overrides the AWS endpoint for S3-compatible backends, eg. http://localhost:9000 for MinIO
+    endpoint_url: Option<String>,
+    // Address buckets as host/bucket/key instead of bucket.host/key, which most non-AWS backends require
+    force_path_style: bool,
+    // Region given with --region, used for every bucket instead of looking up each bucket's location
+    region_override: Option<String>,
+    http: HttpSettings,
+    // Whether requests carry x-amz-request-payer, for requester pays buckets
+    requester_pays: bool,
+    // Set with --yes, which also accepts switching to a bucket's region without asking
+    assume_yes: bool,
+    // Leaves out the CRC32 the SDK otherwise adds to every upload and checks on every download
+    checksums_when_required: bool,
+    // Sends requests to the accelerate endpoint of buckets with Transfer Acceleration enabled
+    accelerate: bool,
+}
+
+// Uses the endpoint from the config file when there is one, and only prompts when running interactively
+fn select_connection_options(global: &GlobalArgs, config: &RunConfig, interactive: bool) -> anyhow::Result<ConnectionOptions> {
+    let region_override = global.region.clone();
+    let http = HttpSettings {
+        max_connections: global.max_connections.map(|max_connections| max_connections as usize),
+        connect_timeout: global.connect_timeout,
+        operation_timeout: global.operation_timeout,
+    };
+    let requester_pays = global.requester_pays;
+    // The accelerate endpoint only exists on AWS, so there's nothing to switch to on a custom endpoint
+    if global.accelerate && config.endpoint_url.is_some() {
+        bail!("Transfer Acceleration can't be used with a custom endpoint");
+    }
+    if config.endpoint_url.is_some() || !interactive {
+        return Ok(ConnectionOptions {
+            endpoint_url: config.endpoint_url.clone(),
+            force_path_style: config.force_path_style.unwrap_or(config.endpoint_url.is_some()),
+            region_override,
+            http,
+            requester_pays,
+            assume_yes: global.yes,
+            checksums_when_required: global.no_sdk_checksums,
+            accelerate: global.accelerate,
+        });
+    }
+
+    let endpoint_url = inquire::Text::new("S3 endpoint URL (leave blank for AWS)").prompt()?;
+    let endpoint_url = Some(endpoint_url).filter(|url| !url.is_empty());
+    if global.accelerate && endpoint_url.is_some() {
+        bail!("Transfer Acceleration can't be used with a custom endpoint");
+    }
+
+    // Only S3-compatible backends are likely to need path-style addressing
+    let force_path_style = endpoint_url.is_some() && inquire::Confirm::new("Use path-style addressing?")
+        .with_default(true)
+        .prompt()?;
+
+    Ok(ConnectionOptions {
+        endpoint_url,
+        force_path_style,
+        region_override,
+        http,
+        requester_pays,
+        assume_yes: global.yes,
+        checksums_when_required: global.no_sdk_checksums,
+        accelerate: global.accelerate,
+    })
+}
+
+fn new_s3_client(aws_cfg: &SdkConfig, connection: &ConnectionOptions) -> Client {
+    let mut s3_config = s3::config::Builder::from(aws_cfg);
+    if let Some(endpoint_url) = &connection.endpoint_url {
+        s3_config = s3_config.endpoint_url(endpoint_url);
+    }
+    s3_config = s3_config.force_path_style(connection.force_path_style);
+    if let Some(http_client) = connection.http.http_client() {
+        s3_config = s3_config.http_client(http_client);
+    }
+    if let Some(timeout_config) = connection.http.timeout_config(aws_cfg.timeout_config()) {
+        s3_config = s3_config.timeout_config(timeout_config);
+    }
+    if connection.requester_pays {
+        s3_config = s3_config.interceptor(connection::RequesterPays);
+    }
+    // Checksums chosen with --checksum, and the ones read back with --verify, are still sent and checked
+    if connection.checksums_when_required {
+        s3_config = s3_config
+            .request_checksum_calculation(RequestChecksumCalculation::WhenRequired)
+            .response_checksum_validation(ResponseChecksumValidation::WhenRequired);
+    }
+    Client::from_conf(s3_config.build())
+}
 
 // Main entry point of the application. Select a bucket and operation to perform.
-async fn operation_select() {
-    let aws_cfg = select_authentication().await.as_arc();
+async fn operation_select(global: GlobalArgs, config: RunConfig) -> anyhow::Result<()> {
+    let (aws_cfg, profile_name) = load_aws_config(&global, true).await?;
+    let mut aws_cfg = refresh_sso_session(&global, &aws_cfg, profile_name.as_deref()).await?.unwrap_or(aws_cfg).as_arc();
+    let connection = select_connection_options(&global, &config, true)?.as_arc();
 
     // Create AWS service clients
     let acct_client_arc = acct::Client::new(&aws_cfg).as_arc();
-    let s3_client_arc = s3::Client::new(&aws_cfg).as_arc();
+    let mut s3_client_arc = new_s3_client(&aws_cfg, &connection).as_arc();
 
-    let region_list = get_aws_regions(acct_client_arc.clone()).await.as_arc();
+    // S3-compatible backends don't have AWS regions to choose from, so only offer the configured one
+    let region_list = if connection.endpoint_url.is_some() {
+        vec![aws_cfg.region().map(|r| r.to_string()).unwrap_or("us-east-1".into())].as_arc()
+    }
+    else {
+        get_aws_regions(acct_client_arc.clone()).await?.as_arc()
+    };
 
     loop {
-        let operation_list = vec!["Cleanup bucket", "Create objects", "Create bucket", "Delete bucket", "List objects"];
-        let selected_operation = inquire::Select::new("Select an operation", operation_list).prompt().unwrap();
-    
-        match selected_operation {
-            "Cleanup bucket" => {
-                let bucket_name = select_bucket(s3_client_arc.clone()).await;
-                let s3_client = get_s3_client_for_bucket(s3_client_arc.clone(), aws_cfg.clone(), &bucket_name).await;
-                operation_cleanup_bucket(s3_client, bucket_name.into()).await;
+        let operation_list = vec!["Auto-tune concurrency", "Bucket info", "Cleanup bucket", "Consistency test", "Copy objects", "Create objects", "Create bucket", "Delete bucket", "Delete bucket (force)", "Delete by age", "Download directory", "Head objects", "Lifecycle rule", "List benchmark", "List objects", "Mirror bucket", "Mixed workload", "Presigned URLs", "Read objects", "Upload directory", "Quit"];
+        // Escape or Ctrl-C at the menu ends the session the same way as choosing Quit
+        let selected_operation = match inquire::Select::new("Select an operation", operation_list).prompt() {
+            Ok(selected_operation) => selected_operation,
+            Err(InquireError::OperationCanceled | InquireError::OperationInterrupted) => "Quit",
+            Err(err) => return Err(err.into()),
+        };
+        if selected_operation == "Quit" {
+            session::print_report();
+            return Ok(());
+        }
+
+        let start_time = session::begin_operation();
+        interrupt::reset_interrupted();
+
+        // A failed operation is reported and the menu shown again, rather than ending the session
+        let operation_result: anyhow::Result<()> = async {
+            match selected_operation {
+                "Bucket info" => {
+                    let mut args = BucketInfoArgs::default();
+                    config.apply_to_bucket_info(&mut args);
+                    let bucket_name = bucket_or_select(s3_client_arc.clone(), args.bucket.clone(), global.skip_bucket_check).await?;
+                    let s3_client = get_s3_client_for_bucket(s3_client_arc.clone(), aws_cfg.clone(), connection.clone(), &bucket_name).await?;
+                    info::operation_bucket_info(s3_client, bucket_name, args).await?;
+                }
+                "Cleanup bucket" => {
+                    // Always checkpointed from the menu, so an interrupted cleanup is offered for resuming next time
+                    let mut args = CleanupArgs { checkpoint: true, ..Default::default() };
+                    config.apply_to_cleanup(&mut args);
+                    let bucket_name = bucket_or_select(s3_client_arc.clone(), args.bucket.clone(), global.skip_bucket_check).await?;
+                    let s3_client = get_s3_client_for_bucket(s3_client_arc.clone(), aws_cfg.clone(), connection.clone(), &bucket_name).await?;
+                    operation_cleanup_bucket(s3_client, bucket_name, args, global.yes, global.dry_run).await?;
+                }
+                "Delete by age" => {
+                    let mut args = CleanupArgs::default();
+                    config.apply_to_cleanup(&mut args);
+                    let bucket_name = bucket_or_select(s3_client_arc.clone(), args.bucket.clone(), global.skip_bucket_check).await?;
+                    let s3_client = get_s3_client_for_bucket(s3_client_arc.clone(), aws_cfg.clone(), connection.clone(), &bucket_name).await?;
+                    let older_than = inquire::Text::new("Delete objects last modified more than how long ago? (eg. 12h, 7d)")
+                        .with_default("7d")
+                        .with_validator(validate_duration)
+                        .prompt()?;
+                    args.older_than = Some(parse_duration(&older_than).context("Invalid age")?);
+                    operation_cleanup_bucket(s3_client, bucket_name, args, global.yes, global.dry_run).await?;
+                }
+                "Consistency test" => {
+                    let mut args = ConsistencyTestArgs::default();
+                    config.apply_to_consistency_test(&mut args);
+                    let bucket_name = bucket_or_select(s3_client_arc.clone(), args.bucket.clone(), global.skip_bucket_check).await?;
+                    let s3_client = get_s3_client_for_bucket(s3_client_arc.clone(), aws_cfg.clone(), connection.clone(), &bucket_name).await?;
+                    consistency::operation_consistency_test(s3_client, bucket_name, args).await?;
+                }
+                "Copy objects" => {
+                    let mut args = CopyObjectsArgs::default();
+                    config.apply_to_copy_objects(&mut args);
+                    let bucket_name = bucket_or_select(s3_client_arc.clone(), args.bucket.clone(), global.skip_bucket_check).await?;
+                    let s3_client = get_s3_client_for_bucket(s3_client_arc.clone(), aws_cfg.clone(), connection.clone(), &bucket_name).await?;
+                    copy::operation_copy_objects(s3_client, bucket_name, args).await?;
+                }
+                "Create objects" => {
+                    let mut args = CreateObjectsArgs::default();
+                    config.apply_to_create_objects(&mut args);
+                    let bucket_name = bucket_or_select(s3_client_arc.clone(), args.bucket.clone(), global.skip_bucket_check).await?;
+                    let s3_client = get_s3_client_for_bucket(s3_client_arc.clone(), aws_cfg.clone(), connection.clone(), &bucket_name).await?;
+                    operation_create_objects(s3_client, bucket_name, args, global.dry_run).await?;
+                }
+                "Create bucket" => {
+                    let mut args = CreateBucketArgs::default();
+                    config.apply_to_create_bucket(&mut args);
+                    operation_create_bucket(aws_cfg.clone(), connection.clone(), region_list.clone(), args, None, global.dry_run).await?;
+                }
+                "Delete bucket" => {
+                    let bucket_name = bucket_or_select(s3_client_arc.clone(), config.bucket.clone(), global.skip_bucket_check).await?;
+                    let s3_client = get_s3_client_for_bucket(s3_client_arc.clone(), aws_cfg.clone(), connection.clone(), &bucket_name).await?;
+                    delete_bucket(s3_client, &bucket_name, global.yes, global.dry_run).await?;
+                }
+                "Delete bucket (force)" => {
+                    let bucket_name = bucket_or_select(s3_client_arc.clone(), config.bucket.clone(), global.skip_bucket_check).await?;
+                    let s3_client = get_s3_client_for_bucket(s3_client_arc.clone(), aws_cfg.clone(), connection.clone(), &bucket_name).await?;
+                    let max_passes = inquire::Text::new("Cleanup passes to run before giving up on emptying the bucket")
+                        .with_default(&DEFAULT_CLEANUP_PASSES.to_string())
+                        .with_validator(validate_pass_count)
+                        .prompt()?.parse()?;
+                    force_delete_bucket(s3_client, bucket_name, Some(max_passes), None, global.yes, global.dry_run).await?;
+                }
+                "Download directory" => {
+                    let mut args = DownloadDirectoryArgs::default();
+                    config.apply_to_download_directory(&mut args);
+                    let bucket_name = bucket_or_select(s3_client_arc.clone(), args.bucket.clone(), global.skip_bucket_check).await?;
+                    let s3_client = get_s3_client_for_bucket(s3_client_arc.clone(), aws_cfg.clone(), connection.clone(), &bucket_name).await?;
+                    download::operation_download_directory(s3_client, bucket_name, args).await?;
+                }
+                "Head objects" => {
+                    let mut args = HeadObjectsArgs::default();
+                    config.apply_to_head_objects(&mut args);
+                    let bucket_name = bucket_or_select(s3_client_arc.clone(), args.bucket.clone(), global.skip_bucket_check).await?;
+                    let s3_client = get_s3_client_for_bucket(s3_client_arc.clone(), aws_cfg.clone(), connection.clone(), &bucket_name).await?;
+                    head::operation_head_objects(s3_client, bucket_name, args).await?;
+                }
+                "Lifecycle rule" => {
+                    let mut args = LifecycleArgs::default();
+                    config.apply_to_lifecycle(&mut args);
+                    let bucket_name = bucket_or_select(s3_client_arc.clone(), args.bucket.clone(), global.skip_bucket_check).await?;
+                    let s3_client = get_s3_client_for_bucket(s3_client_arc.clone(), aws_cfg.clone(), connection.clone(), &bucket_name).await?;
+                    lifecycle::operation_configure_lifecycle(s3_client, bucket_name, args, global.yes, global.dry_run).await?;
+                }
+                "List objects" => {
+                    let bucket_name = bucket_or_select(s3_client_arc.clone(), config.bucket.clone(), global.skip_bucket_check).await?;
+                    let s3_client = get_s3_client_for_bucket(s3_client_arc.clone(), aws_cfg.clone(), connection.clone(), &bucket_name).await?;
+                    operation_list_objects(s3_client, &bucket_name).await?;
+                }
+                "Auto-tune concurrency" => {
+                    let mut args = AutoTuneArgs::default();
+                    config.apply_to_auto_tune(&mut args);
+                    let bucket_name = bucket_or_select(s3_client_arc.clone(), args.bucket.clone(), global.skip_bucket_check).await?;
+                    let s3_client = get_s3_client_for_bucket(s3_client_arc.clone(), aws_cfg.clone(), connection.clone(), &bucket_name).await?;
+                    autotune::operation_auto_tune(s3_client, bucket_name, args).await?;
+                }
+                "Mirror bucket" => {
+                    let mut args = MirrorBucketArgs::default();
+                    config.apply_to_mirror_bucket(&mut args);
+                    let bucket_name = bucket_or_select(s3_client_arc.clone(), args.bucket.clone(), global.skip_bucket_check).await?;
+                    if args.destination_bucket.is_none() {
+                        status!("Now select the bucket to copy objects to");
+                    }
+                    let destination_bucket = bucket_or_select(s3_client_arc.clone(), args.destination_bucket.clone(), global.skip_bucket_check).await?;
+                    let mut region_clients = RegionClients::new(s3_client_arc.clone(), aws_cfg.clone(), connection.clone());
+                    let [source_client, destination_client] = region_clients.clients_for_buckets(&[bucket_name.clone(), destination_bucket.clone()]).await?
+                        .try_into().expect("one client per bucket");
+                    mirror::operation_mirror_bucket(source_client, bucket_name, destination_client, destination_bucket, args).await?;
+                }
+                "Mixed workload" => {
+                    let mut args = MixedWorkloadArgs::default();
+                    config.apply_to_mixed_workload(&mut args);
+                    let bucket_name = bucket_or_select(s3_client_arc.clone(), args.bucket.clone(), global.skip_bucket_check).await?;
+                    let s3_client = get_s3_client_for_bucket(s3_client_arc.clone(), aws_cfg.clone(), connection.clone(), &bucket_name).await?;
+                    mixed::operation_mixed_workload(s3_client, bucket_name, args).await?;
+                }
+                "List benchmark" => {
+                    let mut args = ListBenchmarkArgs::default();
+                    config.apply_to_list_benchmark(&mut args);
+                    let bucket_name = bucket_or_select(s3_client_arc.clone(), args.bucket.clone(), global.skip_bucket_check).await?;
+                    let s3_client = get_s3_client_for_bucket(s3_client_arc.clone(), aws_cfg.clone(), connection.clone(), &bucket_name).await?;
+                    listing::operation_list_benchmark(s3_client, bucket_name, args).await?;
+                }
+                "Presigned URLs" => {
+                    let mut args = PresignArgs::default();
+                    config.apply_to_presigned(&mut args);
+                    let bucket_name = bucket_or_select(s3_client_arc.clone(), args.bucket.clone(), global.skip_bucket_check).await?;
+                    let s3_client = get_s3_client_for_bucket(s3_client_arc.clone(), aws_cfg.clone(), connection.clone(), &bucket_name).await?;
+                    presign::operation_presigned_load(s3_client, bucket_name, args).await?;
+                }
+                "Read objects" => {
+                    let mut args = ReadObjectsArgs::default();
+                    config.apply_to_read_objects(&mut args);
+                    let bucket_name = bucket_or_select(s3_client_arc.clone(), args.bucket.clone(), global.skip_bucket_check).await?;
+                    let s3_client = get_s3_client_for_bucket(s3_client_arc.clone(), aws_cfg.clone(), connection.clone(), &bucket_name).await?;
+                    read::operation_read_objects(s3_client, bucket_name, args).await?;
+                }
+                "Upload directory" => {
+                    let mut args = UploadDirectoryArgs::default();
+                    config.apply_to_upload_directory(&mut args);
+                    let bucket_name = bucket_or_select(s3_client_arc.clone(), args.bucket.clone(), global.skip_bucket_check).await?;
+                    let s3_client = get_s3_client_for_bucket(s3_client_arc.clone(), aws_cfg.clone(), connection.clone(), &bucket_name).await?;
+                    upload::operation_upload_directory(s3_client, bucket_name, args).await?;
+                }
+                _ => { }
             }
-            "Create objects" => {
-                let bucket_name = select_bucket(s3_client_arc.clone()).await;
-                let s3_client = get_s3_client_for_bucket(s3_client_arc.clone(), aws_cfg.clone(), &bucket_name).await;
-                operation_create_objects(s3_client, bucket_name.into()).await;
+            Ok(())
+        }.await;
+
+        let outcome = match &operation_result {
+            Err(_) => session::Outcome::Failed,
+            Ok(()) if interrupt::last_operation_interrupted() => session::Outcome::Interrupted,
+            Ok(()) => session::Outcome::Completed,
+        };
+        session::end_operation(selected_operation, start_time, outcome);
+        recent::save();
+
+        if let Err(err) = operation_result {
+            eprintln!("{0}", format!("Error: {0:#}", err).red());
+
+            // An expired SSO session fails every later operation too, so offer to log in again and carry on
+            let refreshed = match refresh_sso_session(&global, &aws_cfg, profile_name.as_deref()).await {
+                Ok(Some(refreshed_cfg)) => {
+                    aws_cfg = refreshed_cfg.as_arc();
+                    s3_client_arc = new_s3_client(&aws_cfg, &connection).as_arc();
+                    true
+                }
+                Ok(None) => false,
+                Err(err) => {
+                    eprintln!("{0}", format!("Error: {0:#}", err).red());
+                    false
+                }
+            };
+            // Credentials that still don't work would fail whatever is chosen next, so the session ends
+            // with the same exit code a single command would
+            let kind = failure::classify(&err);
+            if !refreshed && !kind.is_recoverable() {
+                session::print_report();
+                std::process::exit(kind.exit_code());
             }
-            "Create bucket" => {
-                operation_create_bucket(aws_cfg.clone(), region_list.clone()).await;
+        }
+    }
+}
+
+// Runs a single operation from the command line. Setup prompts are skipped, falling back to the
+// default credential chain and the AWS endpoint unless the config file says otherwise, while any missing operation parameters are still prompted for.
+async fn run_command(global: GlobalArgs, config: &RunConfig, command: Command) -> anyhow::Result<()> {
+    let (aws_cfg, profile_name) = load_aws_config(&global, false).await?;
+    let aws_cfg = refresh_sso_session(&global, &aws_cfg, profile_name.as_deref()).await?.unwrap_or(aws_cfg).as_arc();
+    let connection = select_connection_options(&global, config, false)?.as_arc();
+    let s3_client_arc = new_s3_client(&aws_cfg, &connection).as_arc();
+
+    match command {
+        Command::CreateObjects(mut args) => {
+            config.apply_to_create_objects(&mut args);
+            let bucket_name = bucket_or_select(s3_client_arc.clone(), args.bucket.clone(), global.skip_bucket_check).await?;
+            let s3_client = get_s3_client_for_bucket(s3_client_arc.clone(), aws_cfg.clone(), connection.clone(), &bucket_name).await?;
+            operation_create_objects(s3_client, bucket_name, args, global.dry_run).await?;
+        }
+        Command::Cleanup(mut args) => {
+            config.apply_to_cleanup(&mut args);
+            let bucket_name = bucket_or_select(s3_client_arc.clone(), args.bucket.clone(), global.skip_bucket_check).await?;
+            let s3_client = get_s3_client_for_bucket(s3_client_arc.clone(), aws_cfg.clone(), connection.clone(), &bucket_name).await?;
+            operation_cleanup_bucket(s3_client, bucket_name, args, global.yes, global.dry_run).await?;
+        }
+        Command::BucketInfo(mut args) => {
+            config.apply_to_bucket_info(&mut args);
+            let bucket_name = bucket_or_select(s3_client_arc.clone(), args.bucket.clone(), global.skip_bucket_check).await?;
+            let s3_client = get_s3_client_for_bucket(s3_client_arc.clone(), aws_cfg.clone(), connection.clone(), &bucket_name).await?;
+            info::operation_bucket_info(s3_client, bucket_name, args).await?;
+        }
+        Command::ConsistencyTest(mut args) => {
+            config.apply_to_consistency_test(&mut args);
+            let bucket_name = bucket_or_select(s3_client_arc.clone(), args.bucket.clone(), global.skip_bucket_check).await?;
+            let s3_client = get_s3_client_for_bucket(s3_client_arc.clone(), aws_cfg.clone(), connection.clone(), &bucket_name).await?;
+            consistency::operation_consistency_test(s3_client, bucket_name, args).await?;
+        }
+        Command::CopyObjects(mut args) => {
+            config.apply_to_copy_objects(&mut args);
+            let bucket_name = bucket_or_select(s3_client_arc.clone(), args.bucket.clone(), global.skip_bucket_check).await?;
+            let s3_client = get_s3_client_for_bucket(s3_client_arc.clone(), aws_cfg.clone(), connection.clone(), &bucket_name).await?;
+            copy::operation_copy_objects(s3_client, bucket_name, args).await?;
+        }
+        Command::CreateBucket(mut args) => {
+            config.apply_to_create_bucket(&mut args);
+            // The region list is only needed to prompt for a location when none was given
+            let region_list = match (&global.region, args.regions.is_empty()) {
+                (None, true) => get_aws_regions(acct::Client::new(&aws_cfg).as_arc()).await?,
+                _ => vec![],
+            };
+            operation_create_bucket(aws_cfg.clone(), connection.clone(), region_list.as_arc(), args, global.region.clone(), global.dry_run).await?;
+        }
+        Command::DeleteBucket(mut args) => {
+            config.apply_to_delete_bucket(&mut args);
+            let bucket_name = bucket_or_select(s3_client_arc.clone(), args.bucket, global.skip_bucket_check).await?;
+            let s3_client = get_s3_client_for_bucket(s3_client_arc.clone(), aws_cfg.clone(), connection.clone(), &bucket_name).await?;
+            if args.force {
+                force_delete_bucket(s3_client, bucket_name, args.max_passes, args.retries, global.yes, global.dry_run).await?;
             }
-            "Delete bucket" => {
-                let bucket_name = select_bucket(s3_client_arc.clone()).await;
-                let s3_client = get_s3_client_for_bucket(s3_client_arc.clone(), aws_cfg.clone(), &bucket_name).await;
-                delete_bucket(s3_client, &bucket_name).await;
+            else {
+                delete_bucket(s3_client, &bucket_name, global.yes, global.dry_run).await?;
             }
-            "List objects" => {
-                let bucket_name = select_bucket(s3_client_arc.clone()).await;
-                let s3_client = get_s3_client_for_bucket(s3_client_arc.clone(), aws_cfg.clone(), &bucket_name).await;
-                operation_list_objects(s3_client, &bucket_name).await;
+        }
+        Command::AutoTune(mut args) => {
+            config.apply_to_auto_tune(&mut args);
+            let bucket_name = bucket_or_select(s3_client_arc.clone(), args.bucket.clone(), global.skip_bucket_check).await?;
+            let s3_client = get_s3_client_for_bucket(s3_client_arc.clone(), aws_cfg.clone(), connection.clone(), &bucket_name).await?;
+            autotune::operation_auto_tune(s3_client, bucket_name, args).await?;
+        }
+        Command::MirrorBucket(mut args) => {
+            config.apply_to_mirror_bucket(&mut args);
+            let bucket_name = bucket_or_select(s3_client_arc.clone(), args.bucket.clone(), global.skip_bucket_check).await?;
+            if args.destination_bucket.is_none() {
+                status!("Now select the bucket to copy objects to");
             }
-            "q" | "quit" | "exit" => { std::process::exit(0) }
-            _ => { }
+            let destination_bucket = bucket_or_select(s3_client_arc.clone(), args.destination_bucket.clone(), global.skip_bucket_check).await?;
+            let mut region_clients = RegionClients::new(s3_client_arc.clone(), aws_cfg.clone(), connection.clone());
+            let [source_client, destination_client] = region_clients.clients_for_buckets(&[bucket_name.clone(), destination_bucket.clone()]).await?
+                .try_into().expect("one client per bucket");
+            mirror::operation_mirror_bucket(source_client, bucket_name, destination_client, destination_bucket, args).await?;
+        }
+        Command::MixedWorkload(mut args) => {
+            config.apply_to_mixed_workload(&mut args);
+            let bucket_name = bucket_or_select(s3_client_arc.clone(), args.bucket.clone(), global.skip_bucket_check).await?;
+            let s3_client = get_s3_client_for_bucket(s3_client_arc.clone(), aws_cfg.clone(), connection.clone(), &bucket_name).await?;
+            mixed::operation_mixed_workload(s3_client, bucket_name, args).await?;
+        }
+        Command::ListBenchmark(mut args) => {
+            config.apply_to_list_benchmark(&mut args);
+            let bucket_name = bucket_or_select(s3_client_arc.clone(), args.bucket.clone(), global.skip_bucket_check).await?;
+            let s3_client = get_s3_client_for_bucket(s3_client_arc.clone(), aws_cfg.clone(), connection.clone(), &bucket_name).await?;
+            listing::operation_list_benchmark(s3_client, bucket_name, args).await?;
+        }
+        Command::Presigned(mut args) => {
+            config.apply_to_presigned(&mut args);
+            let bucket_name = bucket_or_select(s3_client_arc.clone(), args.bucket.clone(), global.skip_bucket_check).await?;
+            let s3_client = get_s3_client_for_bucket(s3_client_arc.clone(), aws_cfg.clone(), connection.clone(), &bucket_name).await?;
+            presign::operation_presigned_load(s3_client, bucket_name, args).await?;
+        }
+        Command::HeadObjects(mut args) => {
+            config.apply_to_head_objects(&mut args);
+            let bucket_name = bucket_or_select(s3_client_arc.clone(), args.bucket.clone(), global.skip_bucket_check).await?;
+            let s3_client = get_s3_client_for_bucket(s3_client_arc.clone(), aws_cfg.clone(), connection.clone(), &bucket_name).await?;
+            head::operation_head_objects(s3_client, bucket_name, args).await?;
+        }
+        Command::Lifecycle(mut args) => {
+            config.apply_to_lifecycle(&mut args);
+            let bucket_name = bucket_or_select(s3_client_arc.clone(), args.bucket.clone(), global.skip_bucket_check).await?;
+            let s3_client = get_s3_client_for_bucket(s3_client_arc.clone(), aws_cfg.clone(), connection.clone(), &bucket_name).await?;
+            lifecycle::operation_configure_lifecycle(s3_client, bucket_name, args, global.yes, global.dry_run).await?;
+        }
+        Command::ReadObjects(mut args) => {
+            config.apply_to_read_objects(&mut args);
+            let bucket_name = bucket_or_select(s3_client_arc.clone(), args.bucket.clone(), global.skip_bucket_check).await?;
+            let s3_client = get_s3_client_for_bucket(s3_client_arc.clone(), aws_cfg.clone(), connection.clone(), &bucket_name).await?;
+            read::operation_read_objects(s3_client, bucket_name, args).await?;
+        }
+        Command::DownloadDirectory(mut args) => {
+            config.apply_to_download_directory(&mut args);
+            let bucket_name = bucket_or_select(s3_client_arc.clone(), args.bucket.clone(), global.skip_bucket_check).await?;
+            let s3_client = get_s3_client_for_bucket(s3_client_arc.clone(), aws_cfg.clone(), connection.clone(), &bucket_name).await?;
+            download::operation_download_directory(s3_client, bucket_name, args).await?;
+        }
+        Command::UploadDirectory(mut args) => {
+            config.apply_to_upload_directory(&mut args);
+            let bucket_name = bucket_or_select(s3_client_arc.clone(), args.bucket.clone(), global.skip_bucket_check).await?;
+            let s3_client = get_s3_client_for_bucket(s3_client_arc.clone(), aws_cfg.clone(), connection.clone(), &bucket_name).await?;
+            upload::operation_upload_directory(s3_client, bucket_name, args).await?;
         }
     }
+    Ok(())
 }
 
-async fn operation_list_objects(s3_client: Client, bucket_name: &String) {
-    let object_list_result = s3_client.list_objects_v2()
-        .bucket(bucket_name)
-        .send().await;
+// Loads the AWS configuration from the --profile flag if given, otherwise prompts for an authentication
+// method when running interactively. The --region flag overrides whichever region the configuration resolved.
+// Also returns the name of the profile the configuration came from, if any.
+async fn load_aws_config(global: &GlobalArgs, interactive: bool) -> anyhow::Result<(SdkConfig, Option<String>)> {
+    let profile_files = env_config_files(global)?;
+    let (aws_cfg, profile_name) = match &global.profile {
+        Some(profile_name) => (aws_config::from_env().profile_files(profile_files).profile_name(profile_name).load().await, Some(profile_name.clone())),
+        None if interactive => select_authentication(&profile_files).await?,
+        None => (aws_config::from_env().profile_files(profile_files).load().await, None),
+    };
+    Ok((with_region_override(global, aws_cfg), profile_name))
+}
 
-    if object_list_result.as_ref().is_err() {
-        let sdk_err = object_list_result.err().unwrap().into_service_error();
-        let svc_err = sdk_err.meta().message();
-        let message = svc_err.unwrap().to_string();
-        println!("Error occurred while listing objects: {0}", message.red());
-        return;
+fn with_region_override(global: &GlobalArgs, aws_cfg: SdkConfig) -> SdkConfig {
+    match &global.region {
+        Some(region) => aws_cfg.into_builder().region(Region::new(region.clone())).build(),
+        None => aws_cfg,
+    }
+}
+
+// Phrases from the SDK and the SSO service that mean the cached SSO token is missing, expired or revoked
+const SSO_TOKEN_ERRORS: [&str; 3] = ["sso token", "token has expired", "session token not found or invalid"];
+
+fn is_sso_token_error(err: &(dyn std::error::Error + 'static)) -> bool {
+    let mut current = Some(err);
+    while let Some(err) = current {
+        let message = err.to_string().to_lowercase();
+        if SSO_TOKEN_ERRORS.iter().any(|phrase| message.contains(phrase)) {
+            return true;
+        }
+        current = err.source();
+    }
+    false
+}
+
+// Checks whether a profile's credentials fail because its SSO session has expired, and if so offers to run
+// aws sso login. Returns the configuration reloaded with fresh credentials after a successful login, or
+// None when there was nothing to refresh. Any other credentials problem is left to surface from the request that hits it.
+async fn refresh_sso_session(global: &GlobalArgs, aws_cfg: &SdkConfig, profile_name: Option<&str>) -> anyhow::Result<Option<SdkConfig>> {
+    let (Some(profile_name), Some(credentials_provider)) = (profile_name, aws_cfg.credentials_provider()) else {
+        return Ok(None);
+    };
+    match credentials_provider.provide_credentials().await {
+        Err(err) if is_sso_token_error(&err) => {}
+        _ => return Ok(None),
+    }
+
+    let login_message = format!("The SSO session for profile {0} has expired. Run aws sso login --profile {0} and try again", profile_name);
+    if !std::io::stdin().is_terminal() {
+        bail!(Failure::new(ErrorKind::Auth, login_message));
+    }
+    let login = inquire::Confirm::new(&format!("The SSO session for profile {0} has expired. Log in again now?", profile_name))
+        .with_default(true)
+        .prompt()?;
+    if !login {
+        bail!(Failure::new(ErrorKind::Auth, login_message));
+    }
+
+    let login_status = std::process::Command::new("aws")
+        .args(["sso", "login", "--profile", profile_name])
+        .status()
+        .context("Unable to run aws sso login, is the AWS CLI installed?")?;
+    if !login_status.success() {
+        bail!("aws sso login did not complete successfully");
+    }
+
+    let profile_files = env_config_files(global)?;
+    let aws_cfg = aws_config::from_env().profile_files(profile_files).profile_name(profile_name).load().await;
+    status!("{0}", "Logged in, continuing with refreshed credentials".green());
+    Ok(Some(with_region_override(global, aws_cfg)))
+}
+
+// Profile files to read, which are the defaults in ~/.aws unless --config-file or --credentials-file point elsewhere
+fn env_config_files(global: &GlobalArgs) -> anyhow::Result<EnvConfigFiles> {
+    let mut builder = EnvConfigFiles::builder()
+        .include_default_config_file(global.config_file.is_none())
+        .include_default_credentials_file(global.credentials_file.is_none());
+
+    // The SDK quietly skips files it can't find, which would hide a mistyped path behind a confusing auth failure
+    let custom_files = [
+        (EnvConfigFileKind::Config, "config", &global.config_file),
+        (EnvConfigFileKind::Credentials, "credentials", &global.credentials_file),
+    ];
+    for (kind, file_name, path) in custom_files {
+        if let Some(path) = path {
+            if !path.is_file() {
+                bail!("AWS {0} file not found: {1}", file_name, path.display());
+            }
+            builder = builder.with_file(kind, path);
+        }
     }
+    Ok(builder.build())
+}
+
+async fn operation_list_objects(s3_client: Client, bucket_name: &String) -> anyhow::Result<()> {
+    let object_list = s3_client.list_objects_v2()
+        .bucket(bucket_name)
+        .send().await
+        .sdk_context("Error occurred while listing objects")?
+        .contents.unwrap_or_default();
 
-    let object_list = object_list_result.unwrap().contents;
-    if object_list.is_none() {
+    if object_list.is_empty() {
         println!("{0}", "No objects found in this bucket".blue());
-        return;
+        return Ok(());
     }
-    let object_list = object_list.unwrap();
     println!("There are {0} objects in this bucket", object_list.len());
 
     for object in object_list {
-        println!("{0:>25} Size: {1}", object.key.unwrap(), object.size.unwrap());
+        println!("{0:>25} Size: {1}", object.key.unwrap_or_default(), object.size.unwrap_or_default());
     }
+    Ok(())
+}
+
+// A bucket named on the command line or in the config file is used directly, without listing every
+// bucket in the account, after checking that it exists unless --skip-bucket-check was given
+async fn bucket_or_select(s3_client: Arc<Client>, bucket_name: Option<String>, skip_bucket_check: bool) -> anyhow::Result<String> {
+    let bucket_name = match bucket_name {
+        Some(bucket_name) if skip_bucket_check => bucket_name,
+        Some(bucket_name) => {
+            check_bucket_exists(&s3_client, &bucket_name).await?;
+            bucket_name
+        }
+        None => select_bucket(s3_client).await?,
+    };
+    recent::remember_bucket(&bucket_name);
+    Ok(bucket_name)
 }
 
-async fn select_bucket(s3_client: Arc<Client>) -> String {
-    let bucket_list = s3_client.clone().list_buckets().send().await.unwrap().buckets.unwrap();
-    let bucket_list = bucket_list.iter().map(|i| i.name.as_ref().unwrap()).collect();
-    inquire::Select::new("Please select an S3 bucket", bucket_list).prompt().unwrap().to_owned()
+// Only a definite 404 or 403 is treated as a problem. The shared client may be pointed at another region
+// than the bucket, in which case HeadBucket answers with a redirect, and the bucket's own region is found
+// later when its client is created.
+async fn check_bucket_exists(s3_client: &Client, bucket_name: &String) -> anyhow::Result<()> {
+    let head_result = s3_client.head_bucket().bucket(bucket_name).send().await;
+    let Err(err) = head_result else {
+        return Ok(());
+    };
+    if err.as_service_error().is_some_and(|service_err| service_err.is_not_found()) {
+        bail!(Failure::new(ErrorKind::NotFound, format!("Bucket {0} does not exist", bucket_name)));
+    }
+    if err.raw_response().is_some_and(|response| response.status().as_u16() == 403) {
+        bail!(Failure::new(ErrorKind::Permission,
+            format!("Access to bucket {0} is denied. Use --skip-bucket-check if the bucket can be used without HeadBucket", bucket_name)));
+    }
+    Ok(())
 }
 
-async fn get_s3_client_for_bucket(s3_client: Arc<Client>, aws_cfg: Arc<SdkConfig>, bucket_name: &String) -> s3::Client {
-    let bucket_location = s3_client.get_bucket_location()
-        .bucket(bucket_name).send().await.unwrap().location_constraint.unwrap().to_string();
-    println!("Bucket location: {0}", bucket_location.clone().green());
+// Most buckets offered by the selector. Typing filters the whole offered list, but rendering and
+// filtering many thousands of options makes every keystroke sluggish.
+const MAX_SELECTABLE_BUCKETS: usize = 1000;
+
+// Accounts with more buckets than this are offered a name prefix and region filter before the selector
+const BUCKET_FILTER_THRESHOLD: usize = 50;
+
+const ANY_REGION: &str = "Any region";
+
+// Offers the account's buckets in alphabetical order. Typing narrows the list with a fuzzy match
+// on the bucket name, so there's no need to scroll through large accounts.
+async fn select_bucket(s3_client: Arc<Client>) -> anyhow::Result<String> {
+    // Listed a page at a time, which also returns each bucket's region without a request per bucket
+    let spinner = new_spinner("Listing buckets");
+    let mut buckets = vec![];
+    let mut continuation_token: Option<String> = None;
+    loop {
+        let page = s3_client.list_buckets()
+            .max_buckets(MAX_SELECTABLE_BUCKETS as i32)
+            .set_continuation_token(continuation_token.take())
+            .send().await
+            .sdk_context("Unable to list buckets")?;
+        buckets.extend(page.buckets.unwrap_or_default());
+        continuation_token = page.continuation_token;
+        if continuation_token.is_none() {
+            break;
+        }
+    }
+    spinner.finish_and_clear();
+
+    let mut bucket_list: Vec<(String, Option<String>)> = buckets.into_iter()
+        .filter_map(|bucket| Some((bucket.name?, bucket.bucket_region)))
+        .collect();
+    if bucket_list.is_empty() {
+        bail!("No buckets found in this account");
+    }
+
+    if bucket_list.len() > BUCKET_FILTER_THRESHOLD {
+        let prefix = inquire::Text::new(&format!("Found {0} buckets. Only show names starting with (leave blank for all)", bucket_list.len())).prompt()?;
+        bucket_list.retain(|(bucket, _)| bucket.starts_with(&prefix));
+
+        let regions: BTreeSet<String> = bucket_list.iter().filter_map(|(_, region)| region.clone()).collect();
+        if regions.len() > 1 {
+            let region_options: Vec<String> = std::iter::once(ANY_REGION.to_string()).chain(regions).collect();
+            let region = inquire::Select::new("Only show buckets in region", region_options).prompt()?;
+            if region != ANY_REGION {
+                bucket_list.retain(|(_, bucket_region)| bucket_region.as_ref() == Some(&region));
+            }
+        }
+        if bucket_list.is_empty() {
+            bail!("No buckets match the filter");
+        }
+    }
+    let mut bucket_list: Vec<String> = bucket_list.into_iter().map(|(bucket, _)| bucket).collect();
+    bucket_list.sort();
+
+    if bucket_list.len() > MAX_SELECTABLE_BUCKETS {
+        status!("{0}", format!("Showing the first {0} of {1} buckets. Use --bucket to choose any other bucket by name", MAX_SELECTABLE_BUCKETS, bucket_list.len()).yellow());
+        bucket_list.truncate(MAX_SELECTABLE_BUCKETS);
+    }
+    // Starts on the bucket used last time, when it's still there
+    let starting_cursor = recent::last_bucket()
+        .and_then(|last_bucket| bucket_list.iter().position(|bucket| *bucket == last_bucket))
+        .unwrap_or_default();
+    Ok(inquire::Select::new("Please select an S3 bucket", bucket_list)
+        .with_help_message("Type to filter, ↑↓ to move, enter to select")
+        .with_page_size(15)
+        .with_starting_cursor(starting_cursor)
+        .prompt()?)
+}
 
-    let bucket_region = Region::new(bucket_location);
-    let new_aws_cfg = aws_cfg.as_ref().clone().into_builder()
-        .region(bucket_region).build();
-    s3::Client::new(&new_aws_cfg)
+async fn get_s3_client_for_bucket(s3_client: Arc<Client>, aws_cfg: Arc<SdkConfig>, connection: Arc<ConnectionOptions>, bucket_name: &String) -> anyhow::Result<s3::Client> {
+    // A custom endpoint serves every bucket itself, so there's no regional endpoint to switch to. With
+    // an explicit region the shared client is already pointed at it, which saves a GetBucketLocation
+    // request and the permission it needs.
+    let bucket_client = match lookup_bucket_location(&s3_client, &connection, bucket_name).await? {
+        None => s3_client.as_ref().clone(),
+        Some(bucket_location) => {
+            status!("Bucket location: {0}", bucket_location.clone().green());
+            match client_region(&aws_cfg, &connection, bucket_name, bucket_location)? {
+                Some(region) => {
+                    let new_aws_cfg = aws_cfg.as_ref().clone().into_builder()
+                        .region(Region::new(region)).build();
+                    new_s3_client(&new_aws_cfg, &connection)
+                }
+                None => s3_client.as_ref().clone(),
+            }
+        }
+    };
+    stats::set_bucket_region(bucket_name, &bucket_client);
+    Ok(with_acceleration(bucket_client, &connection, bucket_name).await)
 }
 
-async fn delete_bucket(s3_client: Client, bucket_name: &String) {
-    let delete_result = s3_client.delete_bucket()
+// Switches a bucket's client to the accelerate endpoint when --accelerate is given. The endpoint rejects
+// every request for a bucket without acceleration enabled, so those buckets keep the regular endpoint.
+async fn with_acceleration(s3_client: Client, connection: &ConnectionOptions, bucket_name: &String) -> Client {
+    if !connection.accelerate {
+        return s3_client;
+    }
+    let accelerate_status = s3_client.get_bucket_accelerate_configuration()
         .bucket(bucket_name)
-        .send().await;
+        .send().await
+        .ok().and_then(|output| output.status);
+    let accelerated = accelerate_status == Some(BucketAccelerateStatus::Enabled);
+    stats::set_acceleration(bucket_name, accelerated);
+
+    if !accelerated {
+        status!("{0}", format!("Transfer Acceleration isn't enabled on bucket {0}, using the regular endpoint instead", bucket_name).yellow());
+        return s3_client;
+    }
+    status!("Using Transfer Acceleration for bucket {0}", bucket_name);
+    Client::from_conf(s3_client.config().to_builder().accelerate(true).build())
+}
+
+// Returns the bucket's region, or None when the connection options mean the shared client is used regardless
+async fn lookup_bucket_location(s3_client: &Client, connection: &ConnectionOptions, bucket_name: &String) -> anyhow::Result<Option<String>> {
+    if connection.endpoint_url.is_some() || connection.region_override.is_some() {
+        return Ok(None);
+    }
+
+    let location_output = s3_client.get_bucket_location()
+        .bucket(bucket_name).send().await
+        .sdk_context(&format!("Unable to find the location of bucket {0}", bucket_name))?;
+    Ok(Some(bucket_region(location_output.location_constraint.as_ref())))
+}
+
+// Decides which region requests for a bucket go to, asking first when it isn't the active region. Returns
+// None when the shared client can be used as it is.
+fn client_region(aws_cfg: &SdkConfig, connection: &ConnectionOptions, bucket_name: &String, bucket_location: String) -> anyhow::Result<Option<String>> {
+    // Region names are only used to build endpoint hostnames, so anything that doesn't look like one
+    // is safer ignored than turned into an endpoint that can't resolve
+    if !is_region_name(&bucket_location) {
+        status!("{0}", format!("Bucket location {0} isn't a recognized region name, using the active region instead", bucket_location).yellow());
+        return Ok(None);
+    }
+
+    // The shared client already talks to the right region, so there's no need for another one
+    if aws_cfg.region().is_some_and(|region| region.as_ref() == bucket_location) {
+        return Ok(None);
+    }
+
+    if let Some(active_region) = aws_cfg.region() {
+        status!("{0}", format!("Bucket {0} is in {1}, not the active region {2}. Requests for it will be sent to {1}",
+            bucket_name, bucket_location, active_region).yellow());
+        // Scripts without a terminal carry on with the bucket's region, since that's where its requests have to go
+        let confirmed = connection.assume_yes || !std::io::stdin().is_terminal() ||
+            inquire::Confirm::new(&format!("Continue using region {0}?", bucket_location)).with_default(true).prompt()?;
+        if !confirmed {
+            bail!("Cancelled, bucket {0} is in region {1}", bucket_name, bucket_location);
+        }
+    }
+    Ok(Some(bucket_location))
+}
+
+// Maps a GetBucketLocation result to a region name. Buckets in us-east-1 report no location
+// constraint at all, and some very old eu-west-1 buckets still report the legacy "EU" value.
+fn bucket_region(location_constraint: Option<&BucketLocationConstraint>) -> String {
+    match location_constraint.map(|location| location.as_str()) {
+        None | Some("") => "us-east-1".to_string(),
+        Some("EU") => "eu-west-1".to_string(),
+        Some(location) => location.to_string(),
+    }
+}
 
-    if delete_result.is_err() {
-        let sdk_err = delete_result.err().unwrap().into_service_error();
-        let svc_err = sdk_err.meta().message();
-        let message = svc_err.unwrap().to_string();
-        println!("{0}", message.red());
+// Matches names such as us-east-1, us-gov-west-1 and ap-southeast-5
+fn is_region_name(name: &str) -> bool {
+    regex::Regex::new(r"^[a-z]{2,4}(-[a-z0-9]+)+-\d+$").unwrap().is_match(name)
+}
+
+async fn delete_bucket(s3_client: Client, bucket_name: &String, assume_yes: bool, dry_run: bool) -> anyhow::Result<()> {
+    if dry_run {
+        println!("{0}", format!("Dry run: bucket {0} would be deleted", bucket_name).yellow());
+        return Ok(());
+    }
+    if !confirm_destructive(&format!("Delete bucket {0}?", bucket_name), assume_yes)? {
+        return Ok(());
+    }
+
+    s3_client.delete_bucket()
+        .bucket(bucket_name)
+        .send().await
+        .sdk_context(&format!("Unable to delete bucket {0}", bucket_name))?;
+    Ok(())
+}
+
+// Empties a bucket, including every object version and delete marker, then deletes it. The cleanup
+// lists the bucket again after each pass, so the bucket is only deleted once it's really empty.
+async fn force_delete_bucket(s3_client: Client, bucket_name: String, max_passes: Option<u32>, retries: Option<u32>, assume_yes: bool, dry_run: bool) -> anyhow::Result<()> {
+    if !dry_run && !confirm_bucket_name(&bucket_name, assume_yes)? {
+        return Ok(());
+    }
+
+    // Confirmation has already been given for the whole operation, so the individual steps don't ask again
+    let cleanup_args = CleanupArgs { prefix: Some(String::new()), concurrency: Some(DEFAULT_DELETE_WORKERS), max_passes, retries, ..CleanupArgs::default() };
+    operation_cleanup_bucket(s3_client.clone(), bucket_name.clone(), cleanup_args, true, dry_run).await
+        .with_context(|| format!("Bucket {0} was not deleted", bucket_name))?;
+    if !dry_run && interrupt::last_operation_interrupted() {
+        bail!("Interrupted before bucket {0} was empty, so it was not deleted", bucket_name);
+    }
+    delete_bucket(s3_client, &bucket_name, true, dry_run).await
+}
+
+// Asks the user to confirm a destructive operation, unless --yes was passed. Without a terminal to
+// prompt on there's nobody to ask, so refuse outright rather than hanging or assuming consent.
+fn confirm_destructive(message: &str, assume_yes: bool) -> anyhow::Result<bool> {
+    if assume_yes {
+        return Ok(true);
+    }
+    if !std::io::stdin().is_terminal() {
+        bail!("Refusing to run a destructive operation without confirmation, pass --yes to proceed");
+    }
+    Ok(inquire::Confirm::new(message).with_default(false).prompt()?)
+}
+
+// Stronger confirmation for removing a bucket along with everything in it: the bucket name has to be
+// typed back, so a wrong selection in the menu can't be waved through with a single keypress
+fn confirm_bucket_name(bucket_name: &str, assume_yes: bool) -> anyhow::Result<bool> {
+    if assume_yes {
+        return Ok(true);
+    }
+    if !std::io::stdin().is_terminal() {
+        bail!("Refusing to run a destructive operation without confirmation, pass --yes to proceed");
+    }
+    let typed_name = inquire::Text::new(&format!("This deletes bucket {0} and all of its contents. Type the bucket name to confirm", bucket_name)).prompt()?;
+    if typed_name.trim() != bucket_name {
+        status!("{0}", "Bucket name didn't match, nothing was deleted".yellow());
+        return Ok(false);
     }
+    Ok(true)
 }
 
 // Query a list of available AWS regions
-async fn get_aws_regions(acct_client: Arc<acct::Client>) -> Vec<String> {
-    acct_client.list_regions()
+async fn get_aws_regions(acct_client: Arc<acct::Client>) -> anyhow::Result<Vec<String>> {
+    let region_list = acct_client.list_regions()
         .region_opt_status_contains(aws_sdk_account::types::RegionOptStatus::EnabledByDefault)
         .region_opt_status_contains(aws_sdk_account::types::RegionOptStatus::Enabled)
         .region_opt_status_contains(aws_sdk_account::types::RegionOptStatus::Enabling)
         .send().await
-        .unwrap().regions.unwrap()
-        .iter().map(|r| r.region_name.clone().unwrap()).collect()
+        .sdk_context("Unable to list AWS regions")?;
+    Ok(region_list.regions.unwrap_or_default().into_iter().filter_map(|r| r.region_name).collect())
 }
 
-async fn operation_create_bucket(aws_cfg: Arc<SdkConfig>, region_list: Arc<Vec<String>>) {
+async fn operation_create_bucket(aws_cfg: Arc<SdkConfig>, connection: Arc<ConnectionOptions>, region_list: Arc<Vec<String>>, args: CreateBucketArgs, new_bucket_location: Option<String>, dry_run: bool) -> anyhow::Result<()> {
 
-    let new_bucket_name = inquire::Text::new("🪣 Enter new bucket name")
-        .with_default(uuid::Uuid::new_v4().to_string().as_str())
-        .prompt().unwrap();
+    let new_bucket_name = match args.bucket {
+        Some(bucket_name) => {
+            if let Some(reason) = bucket_name_error(&bucket_name) {
+                bail!("Invalid bucket name {0}: {1}", bucket_name, reason);
+            }
+            bucket_name
+        }
+        None => inquire::Text::new("🪣 Enter new bucket name")
+            .with_default(uuid::Uuid::new_v4().to_string().as_str())
+            .with_validator(validate_bucket_name)
+            .prompt()?,
+    };
 
-    let new_bucket_location = inquire::Select::new("New bucket location", region_list.to_vec()).prompt().unwrap();
+    let new_bucket_locations = match (args.regions.is_empty(), new_bucket_location) {
+        (false, _) => args.regions,
+        (true, Some(location)) => vec![location],
+        (true, None) => {
+            let last_region: Vec<usize> = recent::get(Setting::Region)
+                .and_then(|last_region| region_list.iter().position(|region| *region == last_region))
+                .into_iter().collect();
+            let locations = inquire::MultiSelect::new("New bucket locations (select several to create one bucket per region)", region_list.to_vec())
+                .with_default(&last_region)
+                .with_validator(|selected: &[inquire::list_option::ListOption<&String>]| match selected.is_empty() {
+                    true => Ok(Validation::Invalid("Select at least one region".into())),
+                    false => Ok(Validation::Valid),
+                })
+                .prompt()?;
+            recent::remember(Setting::Region, &locations[0]);
+            locations
+        }
+    };
 
-    let new_aws_cfg = aws_cfg.as_ref().clone().into_builder()
-        .region(Region::new(new_bucket_location.clone()))
-        .build();
-    let s3_client = s3::Client::new(&new_aws_cfg);
+    // Bucket names are global, so when creating in several regions each one is suffixed with its region
+    let new_buckets: Vec<(String, String)> = match new_bucket_locations.len() {
+        1 => vec![(new_bucket_name, new_bucket_locations[0].clone())],
+        _ => new_bucket_locations.into_iter()
+            .map(|location| (format!("{0}-{1}", new_bucket_name, location), location))
+            .collect(),
+    };
+    for (bucket_name, _) in &new_buckets {
+        if let Some(reason) = bucket_name_error(bucket_name) {
+            bail!("Invalid bucket name {0}: {1}", bucket_name, reason);
+        }
+    }
+
+    let enable_versioning = match args.versioning {
+        Some(versioning) => versioning,
+        None => inquire::Confirm::new("Enable versioning on the new bucket?").with_default(false).prompt()?,
+    };
+
+    let acl = match args.acl {
+        Some(acl) => Some(acl),
+        None => {
+            let acl_options = [&["None"], BucketCannedAcl::values()].concat();
+            match inquire::Select::new("Select a canned ACL for the new bucket", acl_options).prompt()? {
+                "None" => None,
+                acl => Some(BucketCannedAcl::from(acl)),
+            }
+        }
+    };
+
+    if dry_run {
+        let versioning = if enable_versioning { "with versioning" } else { "without versioning" };
+        let acl = acl.as_ref().map(|acl| format!(" and the {0} ACL", acl.as_str())).unwrap_or_default();
+        for (bucket_name, location) in &new_buckets {
+            println!("{0}", format!("Dry run: bucket {0} would be created in {1} {2}{3}", bucket_name, location, versioning, acl).yellow());
+        }
+        return Ok(());
+    }
+
+    if let [(bucket_name, location)] = new_buckets.as_slice() {
+        return create_bucket_in_region(&aws_cfg, &connection, bucket_name, location, enable_versioning, acl).await;
+    }
+
+    // Each region gets its own client, and the buckets are created at the same time
+    let results = join_all(new_buckets.iter()
+        .map(|(bucket_name, location)| create_bucket_in_region(&aws_cfg, &connection, bucket_name, location, enable_versioning, acl.clone()))).await;
+    let mut failed_count = 0;
+    for ((_, location), result) in new_buckets.iter().zip(results) {
+        if let Err(err) = result {
+            println!("{0}", format!("{0}: {1:#}", location, err).red());
+            failed_count += 1;
+        }
+    }
+    if failed_count > 0 {
+        bail!("{0} of {1} buckets could not be created", failed_count, new_buckets.len());
+    }
+    Ok(())
+}
 
-    let location = BucketLocationConstraint::from_str(new_bucket_location.as_str()).unwrap();
-    let cbc = CreateBucketConfigurationBuilder::default()
-        .location_constraint(location)
+// us-east-1 is the default location and S3 rejects an explicit us-east-1 location constraint, so
+// the configuration is left out entirely there. Every other region needs the constraint, eg.
+// creating in us-west-2 without it fails with IllegalLocationConstraintException.
+fn create_bucket_configuration(location: &str) -> Option<CreateBucketConfiguration> {
+    match location {
+        "us-east-1" => None,
+        location => Some(CreateBucketConfiguration::builder()
+            .location_constraint(BucketLocationConstraint::from(location))
+            .build()),
+    }
+}
+
+async fn create_bucket_in_region(aws_cfg: &SdkConfig, connection: &ConnectionOptions, new_bucket_name: &String, new_bucket_location: &str, enable_versioning: bool, acl: Option<BucketCannedAcl>) -> anyhow::Result<()> {
+    let new_aws_cfg = aws_cfg.clone().into_builder()
+        .region(Region::new(new_bucket_location.to_string()))
         .build();
+    let s3_client = new_s3_client(&new_aws_cfg, connection);
+
+    let cbc = create_bucket_configuration(new_bucket_location);
 
-    let create_result = s3_client.create_bucket()
+    // New buckets have ACLs disabled by default, so they're enabled alongside an ACL, with the
+    // bucket owner still owning objects uploaded with bucket-owner-full-control
+    let object_ownership = acl.is_some().then_some(ObjectOwnership::BucketOwnerPreferred);
+
+    s3_client.create_bucket()
         .bucket(new_bucket_name)
-        .create_bucket_configuration(cbc)
-        .send().await;
+        .set_create_bucket_configuration(cbc)
+        .set_acl(acl)
+        .set_object_ownership(object_ownership)
+        .send().await
+        .sdk_context(&format!("Unable to create bucket {0}", new_bucket_name))?;
+    status!("{0}", format!("Created bucket {0} in {1}", new_bucket_name, new_bucket_location).green());
+
+    if enable_versioning {
+        let versioning_result = s3_client.put_bucket_versioning()
+            .bucket(new_bucket_name)
+            .versioning_configuration(VersioningConfiguration::builder().status(BucketVersioningStatus::Enabled).build())
+            .send().await;
 
-    if create_result.is_err() {
-        println!("{0:?}", create_result.err());
+        // The bucket exists either way, so a failure here is reported as a versioning problem rather than a failed create
+        match versioning_result {
+            Ok(_) => status!("{0}", format!("Enabled versioning on {0}", new_bucket_name).green()),
+            Err(err) => println!("{0}", format!("Bucket {0} created, but enabling versioning failed: {1}", new_bucket_name, retry::error_reason(&err)).red()),
+        }
     }
+    Ok(())
 }
 
-async fn operation_create_objects(s3_client: Client, bucket_name: String) {
-    let object_count: u32 = inquire::Text::new("How many objects should I create?")
-        .with_validator(validate_number)
-        .prompt().unwrap().parse().unwrap();
+// What ends a create objects or mixed workload run: a number of operations, or a length of time for soak testing
+#[derive(Clone, Copy, Debug)]
+enum RunLength {
+    Count(u32),
+    Duration(Duration),
+}
 
-    let mut join_handle_list = vec![];
-    for _ in 1..=16 {
-        let new_future = create_object(s3_client.clone(), bucket_name.clone(), object_count);
-        join_handle_list.push(tokio::spawn(new_future));
+#[tracing::instrument(skip_all, fields(bucket = %bucket_name))]
+async fn operation_create_objects(s3_client: Client, bucket_name: String, args: CreateObjectsArgs, dry_run: bool) -> anyhow::Result<()> {
+    let file_keys = args.keys_file.as_deref().map(read_keys_file).transpose()?;
+    if file_keys.as_ref().is_some_and(|keys| keys.len() > MAX_COUNT as usize) {
+        bail!("The keys file can hold at most {0} keys", MAX_COUNT);
     }
-    join_all(join_handle_list).await;
 
-}
+    let run_length = match (&file_keys, args.count, args.duration) {
+        (Some(keys), _, _) => RunLength::Count(keys.len() as u32),
+        (None, Some(count), _) => RunLength::Count(count),
+        (None, None, Some(duration)) => RunLength::Duration(duration),
+        (None, None, None) => match inquire::Select::new("Run for a number of objects or a length of time?", vec!["Object count", "Duration"]).prompt()? {
+            "Duration" => {
+                let duration = inquire::Text::new("How long should I keep creating objects? (eg. 30s, 5m, 1h)")
+                    .with_default("5m")
+                    .with_validator(validate_duration)
+                    .prompt()?;
+                RunLength::Duration(parse_duration(&duration).context("Invalid duration")?)
+            }
+            _ => {
+                let last_count = recent::get(Setting::ObjectCount);
+                let mut object_count_prompt = inquire::Text::new("How many objects should I create?")
+                    .with_validator(validate_number);
+                if let Some(last_count) = &last_count {
+                    object_count_prompt = object_count_prompt.with_default(last_count);
+                }
+                let object_count = object_count_prompt.prompt()?;
+                recent::remember(Setting::ObjectCount, &object_count);
+                RunLength::Count(parse_count(&object_count).context("Invalid object count")?)
+            }
+        },
+    };
 
+    let sizes = match args.size.clone() {
+        Some(sizes) => sizes,
+        None => {
+            let sizes = inquire::Text::new("What size should each object be? (eg. 4KB, 1MB, or a mix such as 4KB-1MB or 4KB:70,1MB:30)")
+                .with_default(&recent::get(Setting::ObjectSize).unwrap_or("1KB".into()))
+                .with_validator(validate_size_distribution)
+                .prompt()?;
+            recent::remember(Setting::ObjectSize, &sizes);
+            SizeDistribution::parse(&sizes).map_err(anyhow::Error::msg).context("Invalid object size")?
+        }
+    };
+    if let RunLength::Count(object_count) = run_length {
+        let total_bytes = sizes.mean().saturating_mul(object_count as u64);
+        if total_bytes > LARGE_UPLOAD_WARNING {
+            status!("{0}", format!("This run will upload {0} in total, which is billed as storage until cleaned up",
+                info::format_size(total_bytes)).yellow());
+        }
+    }
 
+    let payload_mode = match args.payload {
+        Some(payload_mode) => payload_mode,
+        None => inquire::Select::new("Select object payload content", PayloadMode::all()).prompt()?,
+    };
 
-async fn operation_cleanup_bucket(s3_client: Client, bucket_name: String) {
-    let mut delete_tasks = vec![]; // Holds the JoinHandle instances to delete all object batches
+    let storage_class = match args.storage_class {
+        Some(storage_class) => storage_class,
+        None => {
+            let storage_classes = StorageClass::values().to_vec();
+            let standard_index = storage_classes.iter().position(|name| *name == "STANDARD").unwrap_or_default();
+            let storage_class = inquire::Select::new("Select object storage class", storage_classes)
+                .with_starting_cursor(standard_index)
+                .prompt()?;
+            StorageClass::from(storage_class)
+        }
+    };
 
-    let mut page_token = None;
-    loop {
-        let mut object_query = s3_client.clone().list_objects_v2()
-            .bucket(&bucket_name)
-            .max_keys(20);
+    let acl = match args.acl {
+        Some(acl) => Some(acl),
+        None => {
+            let acl_options = [&["None"], ObjectCannedAcl::values()].concat();
+            match inquire::Select::new("Select a canned ACL for created objects", acl_options).prompt()? {
+                "None" => None,
+                acl => Some(ObjectCannedAcl::from(acl)),
+            }
+        }
+    };
+    if acl.as_ref().is_some_and(|acl| *acl != ObjectCannedAcl::BucketOwnerFullControl) && acls_disabled(&s3_client, &bucket_name).await {
+        status!("{0}", "Object Ownership is set to bucket owner enforced on this bucket, which disables ACLs. Uploads with this ACL will be rejected".yellow());
+    }
 
-        if page_token != None {
-            object_query = object_query.continuation_token(&page_token.unwrap_or_default());
+    let encryption = match args.encryption {
+        Some(encryption) => encryption,
+        None => inquire::Select::new("Select server-side encryption", Encryption::all()).prompt()?,
+    };
+
+    let kms_key_id = match (encryption, args.kms_key_id) {
+        (Encryption::SseKms, Some(kms_key_id)) => Some(kms_key_id),
+        (Encryption::SseKms, None) => {
+            let kms_key_id = inquire::Text::new("KMS key ID (leave blank for the AWS managed key)").prompt()?;
+            Some(kms_key_id).filter(|kms_key_id| !kms_key_id.is_empty())
         }
-            
-        let object_list = object_query.send().await.unwrap();
+        // Any other encryption would upload without the key, so the objects wouldn't be encrypted the way that was asked for
+        (_, Some(_)) => bail!("--kms-key-id can only be used with --encryption sse-kms"),
+        (_, None) => None,
+    };
 
-        page_token = object_list.next_continuation_token.clone();
-        // println!("Next page token is: {0}", page_token.clone().unwrap_or_default());
-        let key_count = object_list.key_count.unwrap();
+    let checksum = match args.checksum {
+        Some(checksum) => checksum,
+        None => inquire::Select::new("Select upload checksum", Checksum::all()).prompt()?,
+    };
 
-        // Delete any objects returned in the request
-        let new_join_handle = tokio::spawn(
-            delete_objects(s3_client.clone(), bucket_name.clone(), object_list)
-        );
-        delete_tasks.push(new_join_handle);
-        println!("Spawned a new delete task for {0} objects", key_count);
-        if page_token == None { break; }
-    }
-    
-    // join_all(delete_tasks).await;
-}
+    let verify_checksums = match (checksum, args.verify) {
+        (Checksum::None, _) => false,
+        (_, Some(verify)) => verify,
+        (_, None) => inquire::Confirm::new("Read checksums back to verify each upload?")
+            .with_default(false)
+            .prompt()?,
+    };
 
+    // Downloading objects back catches corruption a backend's own stored checksum wouldn't reveal,
+    // so it's offered alongside checksum verification
+    let verify_sample = match args.verify_sample {
+        Some(verify_sample) => verify_sample,
+        None if !verify_checksums => 0,
+        None => inquire::Text::new("Percentage of objects to download again and compare with what was sent")
+            .with_default("0")
+            .with_validator(validate_percentage)
+            .prompt()?.parse()?,
+    };
 
-// Deletes the specified batch of objects from an Amazon S3 bucket
-async fn delete_objects(s3_client: Client, bucket_name: String, object_list: ListObjectsV2Output) {
-    for object in object_list.contents.unwrap() {
-        let _delete_result = s3_client.delete_object()
-            .bucket(&bucket_name)
-            .key(object.key.unwrap())
-            .send().await;
+    let metadata_count = match args.metadata_count {
+        Some(metadata_count) => metadata_count,
+        None => inquire::Text::new("Number of user metadata headers per object")
+            .with_default("0")
+            .with_validator(validate_metadata_count)
+            .prompt()?.parse()?,
+    };
+
+    let mut metadata = generate_metadata(metadata_count);
+    metadata.extend(args.metadata.iter().cloned());
+    let metadata_size: usize = metadata.iter().map(|(key, value)| key.len() + value.len()).sum();
+    if metadata_size > MAX_METADATA_SIZE {
+        bail!("User metadata takes {0} bytes per object, more than the 2KB S3 allows", metadata_size);
     }
 
-}
+    // S3 stores objects without a content type as binary/octet-stream
+    let content_type = match args.content_type {
+        Some(content_type) => Some(content_type),
+        None => Some(inquire::Text::new("Content-Type for created objects (leave blank for binary/octet-stream)").prompt()?),
+    }.filter(|content_type| !content_type.is_empty());
 
-async fn create_object(s3_client: Client, bucket_name: String, object_count: u32) {
-    for _ in 1..=object_count {
-        let key = uuid::Uuid::new_v4().to_string();
+    let tagging = match args.tags {
+        Some(tags) => tags,
+        None => inquire::Text::new("Object tags (leave blank for none, eg. team=storage&env=test)")
+            .with_validator(validate_tags)
+            .prompt()?,
+    };
+    let tagging = Some(tagging).filter(|tagging| !tagging.is_empty()).as_deref().map(encode_tagging);
 
-        let body = ByteStream::new(SdkBody::from(key.clone()));
-        
-        let put_result = s3_client.put_object()
-            .bucket(&bucket_name)
-            .key(key)
-            .body(body)
-            .send().await;
+    // Keys from a file are used exactly as written, so there's no prefix or naming scheme to ask about
+    let key_prefix = match (&file_keys, args.prefix) {
+        (Some(_), _) => String::new(),
+        (None, Some(prefix)) => prefix,
+        (None, None) => inquire::Text::new("Key prefix (leave blank for none, eg. stress/2024/)")
+            .prompt()?,
+    };
+
+    let key_naming = match (&file_keys, args.naming) {
+        (Some(_), _) => KeyNaming::Sequential,
+        (None, Some(key_naming)) => key_naming,
+        (None, None) => inquire::Select::new("Select object key naming scheme", KeyNaming::all()).prompt()?,
+    };
+
+    let keyspace = match (&file_keys, args.keyspace) {
+        (Some(_), _) => Keyspace::Hot,
+        (None, Some(keyspace)) => keyspace,
+        (None, None) => inquire::Select::new("Write to one prefix, or spread keys across several?", Keyspace::all()).prompt()?,
+    };
+
+    let spread_prefixes = match (keyspace, args.spread_prefixes) {
+        (Keyspace::Hot, _) => 1,
+        (Keyspace::Spread, Some(spread_prefixes)) => spread_prefixes,
+        (Keyspace::Spread, None) => inquire::Text::new("Spread keys across how many prefixes?")
+            .with_default("16")
+            .with_validator(validate_spread_prefixes)
+            .prompt()?.parse()?,
+    };
+
+    let worker_count = match args.concurrency {
+        Some(concurrency) => concurrency,
+        None => prompt_worker_count()?,
+    };
+
+    let requests_per_second = match args.rate {
+        Some(rate) => rate,
+        None => {
+            let requests_per_second = inquire::Text::new("Target requests per second (leave blank for unlimited)")
+                .with_validator(validate_optional_number)
+                .prompt()?;
+            parse_count(&requests_per_second).unwrap_or_default()
+        }
+    };
+    let rate_limiter = Some(requests_per_second).filter(|rps| *rps > 0).map(RateLimiter::new);
+
+    let max_retries = match args.retries {
+        Some(retries) => retries,
+        None => {
+            let max_retries = inquire::Text::new("Maximum retries for throttled or failed requests")
+                .with_default("3")
+                .with_validator(validate_retry_count)
+                .prompt()?;
+            max_retries.parse()?
+        }
+    };
+
+    // Only worth asking about once objects are big enough to be split into parts
+    let multipart_threshold = match args.multipart_threshold {
+        Some(threshold) => threshold,
+        None if sizes.max() <= multipart::MIN_PART_SIZE => MAX_SINGLE_PUT_SIZE,
+        None => {
+            let threshold = inquire::Text::new("Use multipart uploads for objects larger than")
+                .with_default("100MB")
+                .with_validator(validate_size)
+                .prompt()?;
+            parse_size(&threshold).context("Invalid multipart threshold")?
+        }
+    };
+    // A single PUT can't carry more than 5GB, so larger objects always go through multipart
+    let multipart_threshold = multipart_threshold.clamp(multipart::MIN_PART_SIZE, MAX_SINGLE_PUT_SIZE);
+
+    let part_size = match args.part_size {
+        Some(part_size) => part_size,
+        None if sizes.max() <= multipart_threshold => multipart::DEFAULT_PART_SIZE,
+        None => {
+            let part_size = inquire::Text::new("What size should each part be? (at least 5MB)")
+                .with_default("8MB")
+                .with_validator(validate_size)
+                .prompt()?;
+            parse_size(&part_size).context("Invalid part size")?
+        }
+    };
+    // Sized for the largest object, so every object stays within the part limit
+    let part_size = multipart::part_size(sizes.max(), part_size);
+
+    let connection_strategy = match args.connection_strategy {
+        Some(connection_strategy) => connection_strategy,
+        None => inquire::Select::new("Select connection strategy", ConnectionStrategy::all()).prompt()?,
+    };
 
-        if put_result.is_err() {
-            println!("Failed to create S3 object");
+    let warmup = match args.warmup {
+        Some(warmup) => warmup,
+        None => {
+            let warmup = inquire::Text::new("Warm up before measuring for how many requests, or how long? (eg. 100, 30s)")
+                .with_default("0")
+                .with_validator(validate_warmup)
+                .prompt()?;
+            parse_warmup(&warmup).context("Invalid warm-up")?
         }
+    };
+
+    // A timed run gives every worker an unlimited count and stops them at the deadline instead
+    let worker_object_counts = match run_length {
+        RunLength::Count(object_count) => split_object_count(object_count, worker_count),
+        RunLength::Duration(_) => vec![u32::MAX; worker_count as usize],
+    };
+
+    let operation = interrupt::start_operation();
+    let context = Arc::new(CreateObjectsContext {
+        bucket_name,
+        size_histogram: SizeHistogram::new(&sizes),
+        sizes,
+        payload_mode,
+        storage_class,
+        acl,
+        encryption,
+        kms_key_id,
+        checksum,
+        verify_checksums,
+        verify_sample,
+        metadata,
+        content_type,
+        tagging,
+        key_prefix,
+        key_naming,
+        spread_prefixes,
+        file_keys,
+        next_sequence: AtomicU64::new(0),
+        next_spread_prefix: AtomicU64::new(0),
+        rate_limiter,
+        max_retries,
+        multipart_threshold,
+        part_size,
+        connection_strategy,
+        cancel: operation.token(),
+        completed_count: Arc::new(AtomicU64::new(0)),
+        run_stats: RunStats::default(),
+        part_stats: RunStats::default(),
+    });
+
+    if dry_run {
+        create_dry_run(&s3_client, &context, run_length, worker_object_counts.len());
+        return Ok(());
     }
-}
 
-fn validate_number(input: &str) -> Result<Validation, CustomUserError> {
-    let regex = regex::Regex::new(r"^\d{1,6}$").unwrap();
-    if regex.is_match(input) {
-        return Ok(Validation::Valid);
+    match warmup {
+        RunLength::Count(0) => {}
+        RunLength::Duration(duration) if duration.is_zero() => {}
+        RunLength::Count(warmup_count) => {
+            status!("Warming up with {0} requests", warmup_count);
+            warm_up(&s3_client, &context, warmup, worker_count).await;
+        }
+        RunLength::Duration(duration) => {
+            status!("Warming up for {0}", format_duration(duration));
+            warm_up(&s3_client, &context, warmup, worker_count).await;
+        }
     }
-    Ok(Validation::Invalid("Invalid quantity specified. Please use a value from 1 - 999999".into()))
-}
+    // Workers bump a shared counter as each PUT completes, which the progress bar polls
+    let progress = match run_length {
+        RunLength::Count(object_count) => {
+            status!("Creating {0} objects across {1} workers", object_count, worker_object_counts.len());
+            new_progress_bar(object_count as u64)
+        }
+        RunLength::Duration(duration) => {
+            status!("Creating objects for {0} across {1} workers", format_duration(duration), worker_object_counts.len());
+            new_duration_progress_bar(duration)
+        }
+    };
+    let progress_task = tokio::spawn(track_progress(progress.clone(), context.completed_count.clone(), vec![context.run_stats.byte_counter()]));
 
-async fn select_authentication() -> SdkConfig {
-    
-    let auth_options = vec!["Default", "Environment Variables", "Profile", "SSO"];
-    let auth_selection = inquire::Select::new("Select AWS authentication option", auth_options).prompt().unwrap();
+    // Counts throttled responses from the measured run only, not the warm-up
+    let throttle_counter = ThrottleCounter::default();
+    let s3_client = throttle_counter.attach(&s3_client);
 
-    if auth_selection == "Profile" {
-        let profile_name = select_profile().await;
-        return aws_config::from_env().profile_name(profile_name).load().await;
+    let start_time = Instant::now();
+    let deadline = match run_length {
+        RunLength::Count(_) => None,
+        RunLength::Duration(duration) => Some(start_time + duration),
+    };
+
+    let mut join_handle_list = vec![];
+    for worker_object_count in worker_object_counts {
+        let worker_client = match connection_strategy {
+            ConnectionStrategy::PerWorker => connection::fresh_client(&s3_client),
+            _ => s3_client.clone(),
+        };
+        let new_future = create_object(worker_client, context.clone(), worker_object_count, deadline);
+        join_handle_list.push(tokio::spawn(new_future));
     }
-    else if auth_selection == "SSO" {
-        let sso_profile = select_sso_profile().await;
-        return aws_config::from_env().profile_name(sso_profile).load().await;
+    join_all(join_handle_list).await;
+    latency_log::flush();
+
+    progress_task.abort();
+    progress.finish_and_clear();
+    interrupt::report_if_interrupted(&context.cancel);
+    let mut summary = context.run_stats.summarize(start_time.elapsed());
+    if let RunLength::Duration(duration) = run_length {
+        summary = summary.with_setting("Duration", format_duration(duration));
     }
-    else if auth_selection == "Environment Variables" {
-        dotenvy::dotenv().unwrap();
+    if let Some(acl) = &context.acl {
+        summary = summary.with_setting("ACL", acl.as_str());
     }
-    return aws_config::load_from_env().await;
-}
+    if let Some(keys_file) = &args.keys_file {
+        summary = summary.with_setting("Keys file", keys_file.display());
+    }
+    if context.verify_sample > 0 {
+        summary = summary.with_setting("Downloaded", format!("{0}% of objects compared", context.verify_sample));
+    }
+    if !matches!(context.sizes, SizeDistribution::Fixed(_)) {
+        summary = summary
+            .with_setting("Sizes", &context.sizes)
+            .with_setting("Size histogram", context.size_histogram.describe(&context.sizes));
+    }
+    let keyspace_setting = match keyspace {
+        Keyspace::Hot => "Hot".to_string(),
+        Keyspace::Spread => format!("Spread across {0} prefixes", context.spread_prefixes),
+    };
+    let throttled_percent = throttle_counter.throttled() as f64 * 100.0 / throttle_counter.responses().max(1) as f64;
+    let summary = summary
+        .with_setting("Keyspace", keyspace_setting)
+        .with_setting("503 rate", format!("{0:.2}% ({1} of {2} responses)", throttled_percent, throttle_counter.throttled(), throttle_counter.responses()))
+        .with_setting("Storage class", context.storage_class.as_str())
+        .with_setting("Encryption", context.encryption)
+        .with_setting("Connections", context.connection_strategy)
+        .with_setting("Checksum", context.checksum)
+        .with_setting("Metadata", format!("{0} headers", context.metadata.len()))
+        .with_setting("Content type", context.content_type.as_deref().unwrap_or("binary/octet-stream"));
+    // Part-level results show how the multipart path itself performs, separate from whole objects
+    let summary = match context.sizes.max() > context.multipart_threshold {
+        true => {
+            let parts = context.part_stats.summarize(summary.elapsed);
+            summary
+                .with_setting("Part size", info::format_size(context.part_size))
+                .with_setting("Parts", format!("{0} uploaded, {1} failed", parts.object_count, parts.failed_count))
+                .with_setting("Part MB/sec", format!("{0:.2}", parts.megabytes_per_second()))
+                .with_setting("Part latency", format!("p50 {0:.1}ms  p99 {1:.1}ms", parts.p50.as_secs_f64() * 1000.0, parts.p99.as_secs_f64() * 1000.0))
+        }
+        false => summary,
+    };
+    summary.print("Create objects", &context.bucket_name);
 
-async fn select_sso_profile() -> String {
-    let loaded_profiles = get_aws_env_config_sections().await;
-    let prompt = "Select an SSO profile";
-    let profile_names = loaded_profiles.sso_sessions().into_iter().map(|x| x.to_string()).collect();
+    // Failed and retried requests are charged too
+    let request_count = ((summary.object_count + summary.failed_count) as f64 * requests_per_object(&context)).round() as u64 + summary.retry_count;
+    cost::print_create_estimate(&context.storage_class, request_count, summary.total_bytes);
+    Ok(())
+}
 
-    inquire::Select::new(prompt, profile_names).prompt().unwrap()
+// Multipart uploads are billed for starting the upload, each part, and completing it. With mixed sizes
+// this is the average over the objects that would be written.
+fn requests_per_object(context: &CreateObjectsContext) -> f64 {
+    context.sizes.expected(|object_size| match object_size > context.multipart_threshold {
+        true => (object_size.div_ceil(context.part_size) + 2) as f64,
+        false => 1.0,
+    })
 }
 
-async fn get_aws_env_config_sections() -> aws_config::profile::ProfileSet  {
-    let aws_creds = Builder::new().include_default_credentials_file(true).build();
-    
-    let fs = aws_types::os_shim_internal::Fs::real();
-    let env = aws_types::os_shim_internal::Env::real();
+// Number of planned keys shown by a create dry run
+const DRY_RUN_SAMPLE_KEYS: u32 = 5;
+
+// Reports what a create run would upload, with the first few keys it would write, without sending any requests
+fn create_dry_run(s3_client: &Client, context: &CreateObjectsContext, run_length: RunLength, worker_count: usize) {
+    let region = s3_client.config().region().map(|region| region.to_string()).unwrap_or_default();
+    let sample_count = match run_length {
+        RunLength::Count(object_count) => object_count.min(DRY_RUN_SAMPLE_KEYS),
+        RunLength::Duration(_) => DRY_RUN_SAMPLE_KEYS,
+    };
+    let sample_keys: Vec<String> = (0..sample_count).map(|_| next_object_key(context)).collect();
+
+    if output::is_json() {
+        let (object_count, duration_seconds) = match run_length {
+            RunLength::Count(object_count) => (Some(object_count), None),
+            RunLength::Duration(duration) => (None, Some(duration.as_secs())),
+        };
+        let report = serde_json::json!({
+            "operation": "Create objects (dry run)",
+            "bucket": context.bucket_name,
+            "region": region,
+            "objects": object_count,
+            "duration_seconds": duration_seconds,
+            "object_size": context.sizes.mean(),
+            "size_distribution": context.sizes.to_string(),
+            "workers": worker_count,
+            "storage_class": context.storage_class.as_str(),
+            "sample_keys": sample_keys,
+        });
+        println!("{0}", report);
+        return;
+    }
 
-    let loaded_profiles = aws_config::profile::load(&fs, &env, &aws_creds, None).await.unwrap();
-    return loaded_profiles;
+    println!("{0}", "Create objects dry run".bold());
+    println!("  {0:<14} {1}", "Bucket", context.bucket_name);
+    println!("  {0:<14} {1}", "Region", region);
+    match run_length {
+        RunLength::Count(object_count) => println!("  {0:<14} {1} of {2}, {3} in total", "Objects", object_count,
+            context.sizes, info::format_size((object_count as u64).saturating_mul(context.sizes.mean()))),
+        RunLength::Duration(duration) => println!("  {0:<14} {1} each, for {2}", "Objects", context.sizes, format_duration(duration)),
+    }
+    println!("  {0:<14} {1}", "Workers", worker_count);
+    println!("  {0:<14} {1}", "Storage class", context.storage_class.as_str());
+    println!("  {0:<14} {1}", "Encryption", context.encryption);
+    if context.sizes.max() > context.multipart_threshold {
+        println!("  {0:<14} {1} parts", "Multipart", info::format_size(context.part_size));
+    }
+    println!("  First keys");
+    for key in &sample_keys {
+        println!("    {0}", key);
+    }
+    if let RunLength::Count(object_count) = run_length {
+        let request_count = (object_count as f64 * requests_per_object(context)).round() as u64;
+        cost::print_create_estimate(&context.storage_class, request_count, (object_count as u64).saturating_mul(context.sizes.mean()));
+    }
+    println!("{0}", "Dry run: no objects were uploaded".yellow());
 }
 
-async fn select_profile() -> String {
-    let loaded_profiles = get_aws_env_config_sections().await;
-    let profile_names: Vec<&str> = loaded_profiles.profiles().collect();
-    let prompt = "Select an AWS profile";
-    inquire::Select::new(prompt, profile_names).prompt().unwrap().to_string()
+// With Object Ownership set to bucket owner enforced, S3 rejects any upload carrying an ACL other than
+// bucket-owner-full-control. A bucket without ownership controls, or one that can't be checked, is
+// assumed to accept ACLs.
+async fn acls_disabled(s3_client: &Client, bucket_name: &String) -> bool {
+    let Ok(output) = s3_client.get_bucket_ownership_controls().bucket(bucket_name).send().await else {
+        return false;
+    };
+    output.ownership_controls.is_some_and(|controls| controls.rules.iter()
+        .any(|rule| rule.object_ownership == ObjectOwnership::BucketOwnerEnforced))
 }
 
+// Settings and shared counters for every worker taking part in a create objects run
+struct CreateObjectsContext {
+    bucket_name: String,
+    // Each object's size is picked from this, and counted in the histogram once it's uploaded
+    sizes: SizeDistribution,
+    size_histogram: SizeHistogram,
+    payload_mode: PayloadMode,
+    storage_class: StorageClass,
+    acl: Option<ObjectCannedAcl>,
+    encryption: Encryption,
+    // Only set for SSE-KMS with a customer managed key
+    kms_key_id: Option<String>,
+    checksum: Checksum,
+    // Whether each object is checked with a HEAD request after upload
+    verify_checksums: bool,
+    // Percentage of objects downloaded again and compared with the payload in place of the HEAD check
+    verify_sample: u32,
+    // User metadata sent as x-amz-meta-* headers on every object
+    metadata: HashMap<String, String>,
+    content_type: Option<String>,
+    // Tag set in query string form, eg. k1=v1&k2=v2
+    tagging: Option<String>,
+    // Prepended to every key, eg. stress/2024/
+    key_prefix: String,
+    key_naming: KeyNaming,
+    // Number of prefixes keys are shared out between, which is 1 for a hot keyspace
+    spread_prefixes: u32,
+    // Keys read from --keys-file, handed out in order in place of generated ones
+    file_keys: Option<Vec<String>>,
+    // Last sequence number handed out for sequential naming, or the position in the keys file
+    next_sequence: AtomicU64,
+    // Spread prefixes are handed out in turn, so each one receives the same share of the writes
+    next_spread_prefix: AtomicU64,
+    rate_limiter: Option<RateLimiter>,
+    max_retries: u32,
+    // Objects larger than this are uploaded in parts
+    multipart_threshold: u64,
+    part_size: u64,
+    connection_strategy: ConnectionStrategy,
+    // Cancelled on Ctrl-C, after which workers stop starting new objects
+    cancel: CancellationToken,
+    completed_count: Arc<AtomicU64>,
+    run_stats: RunStats,
+    // Individual UploadPart requests of multipart uploads, kept apart from the whole-object results
+    part_stats: RunStats,
+}
+
+// Key written and read during warm-up, removed again before the measured run starts
+const WARMUP_KEY: &str = "s3-stress-warmup";
+
+// Sends untimed PUT and HEAD requests for a single small object, as many at once as there are workers,
+// so TLS handshakes and connection setup happen before the measured run rather than during it. A timed
+// warm-up stops starting requests once its time is up.
+async fn warm_up(s3_client: &Client, context: &CreateObjectsContext, warmup: RunLength, worker_count: u32) {
+    let warmup_key = format!("{0}{1}", context.key_prefix, WARMUP_KEY);
+    let (request_count, deadline) = match warmup {
+        RunLength::Count(warmup_count) => (warmup_count, None),
+        RunLength::Duration(duration) => (u32::MAX, Some(Instant::now() + duration)),
+    };
+    stream::iter(0..request_count)
+        .take_while(|_| futures::future::ready(deadline.is_none_or(|deadline| Instant::now() < deadline)))
+        .for_each_concurrent(worker_count as usize, |request_number| {
+            let warmup_key = &warmup_key;
+            async move {
+                // Alternating writes and reads warms up both request paths. Failures don't matter here,
+                // since a request that fails has still opened a connection.
+                if request_number % 2 == 0 {
+                    let _put_result = s3_client.put_object()
+                        .bucket(&context.bucket_name)
+                        .key(warmup_key)
+                        .body(ByteStream::from_static(b"warmup"))
+                        .send().await;
+                }
+                else {
+                    let _head_result = s3_client.head_object()
+                        .bucket(&context.bucket_name)
+                        .key(warmup_key)
+                        .send().await;
+                }
+            }
+        }).await;
+
+    let _delete_result = s3_client.delete_object()
+        .bucket(&context.bucket_name)
+        .key(&warmup_key)
+        .send().await;
+}
+
+fn new_progress_bar(length: u64) -> ProgressBar {
+    let style = ProgressStyle::with_template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({per_sec}, ETA {eta}) {msg}")
+        .unwrap()
+        .progress_chars("=> ");
+    // Progress bars would corrupt the JSON written to stdout by a script, so skip drawing them
+    if output::is_json() {
+        return ProgressBar::hidden();
+    }
+    ProgressBar::new(length).with_style(style)
+}
+
+// Shown while waiting on a request with nothing to count, such as listing buckets
+fn new_spinner(message: &'static str) -> ProgressBar {
+    if output::is_json() {
+        return ProgressBar::hidden();
+    }
+    let spinner = ProgressBar::new_spinner().with_message(message);
+    spinner.enable_steady_tick(Duration::from_millis(100));
+    spinner
+}
+
+// A timed run has no known total, so instead of a bar it shows the objects completed so far against the run length
+fn new_duration_progress_bar(duration: Duration) -> ProgressBar {
+    let template = format!("{{spinner:.green}} [{{elapsed_precise}} of {0}] {{pos}} objects ({{per_sec}}) {{msg}}", format_duration(duration));
+    let style = ProgressStyle::with_template(&template).unwrap();
+    if output::is_json() {
+        return ProgressBar::hidden();
+    }
+    ProgressBar::new(0).with_style(style)
+}
+
+// How often the live throughput shown next to the progress bar is recalculated
+const THROUGHPUT_INTERVAL: Duration = Duration::from_secs(1);
+
+// Mirrors a shared completion counter onto the progress bar until the task is aborted. Every second the
+// throughput over just that second is shown too, which makes ramp-up and throttling dips visible where the
+// average over the whole run would smooth them out.
+async fn track_progress(progress: ProgressBar, completed_count: Arc<AtomicU64>, byte_counters: Vec<Arc<AtomicU64>>) {
+    let total_bytes = || byte_counters.iter().map(|counter| counter.load(Ordering::Relaxed)).sum::<u64>();
+    let (mut sample_time, mut sample_count, mut sample_bytes) = (Instant::now(), 0, 0);
+    loop {
+        let completed = completed_count.load(Ordering::Relaxed);
+        progress.set_position(completed);
+
+        let interval = sample_time.elapsed();
+        if interval >= THROUGHPUT_INTERVAL {
+            let bytes = total_bytes();
+            let ops_per_second = (completed - sample_count) as f64 / interval.as_secs_f64();
+            let megabytes_per_second = (bytes - sample_bytes) as f64 / (1024.0 * 1024.0) / interval.as_secs_f64();
+            progress.set_message(format!("now {0:.0} ops/s, {1:.2} MB/s", ops_per_second, megabytes_per_second));
+            (sample_time, sample_count, sample_bytes) = (Instant::now(), completed, bytes);
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
+
+
+
+#[tracing::instrument(skip_all, fields(bucket = %bucket_name))]
+async fn operation_cleanup_bucket(s3_client: Client, bucket_name: String, args: CleanupArgs, assume_yes: bool, dry_run: bool) -> anyhow::Result<()> {
+    let file_keys = args.keys_file.as_deref().map(read_keys_file).transpose()?;
+    let prefix = match (&file_keys, args.prefix) {
+        (Some(_), _) => String::new(),
+        (None, Some(prefix)) => prefix,
+        (None, None) => inquire::Text::new("Only delete keys starting with (leave blank for the whole bucket)")
+            .prompt()?,
+    };
+    // An empty prefix matches every key, so it's treated the same as no prefix at all
+    let prefix = Some(prefix).filter(|prefix| !prefix.is_empty());
+    // Objects last modified before this are deleted, and anything newer is left alone
+    let cutoff = args.older_than.map(|age| DateTime::from(SystemTime::now() - age));
+
+    if let (true, Some(file_keys)) = (dry_run, &file_keys) {
+        println!("{0}", format!("Dry run: the {0} keys in the keys file would be deleted from bucket {1}", file_keys.len(), bucket_name).yellow());
+        return Ok(());
+    }
+    if dry_run {
+        return cleanup_dry_run(&s3_client, &bucket_name, prefix, cutoff, args.show_keys).await;
+    }
+
+    let age_condition = args.older_than.map(|age| format!(" older than {0}", format_duration(age))).unwrap_or_default();
+    let confirm_message = match (&file_keys, &prefix) {
+        (Some(file_keys), _) => format!("Delete the {0} objects listed in the keys file from bucket {1}?", file_keys.len(), bucket_name),
+        (None, Some(prefix)) => format!("Delete all objects{0} with prefix {1} in bucket {2}?", age_condition, prefix, bucket_name),
+        (None, None) => format!("Delete all objects{0} in bucket {1}?", age_condition, bucket_name),
+    };
+    if !confirm_destructive(&confirm_message, assume_yes)? {
+        return Ok(());
+    }
+
+    // Resuming implies checkpointing, so a run that's interrupted again can still be resumed
+    // Without --resume, a checkpoint left by an interrupted run with the same prefix is offered when running
+    // interactively. Checkpoints are kept per bucket, so one saved for another bucket never applies here.
+    let saved_checkpoint = match args.resume {
+        true => checkpoint::load(&bucket_name)?,
+        false if std::io::stdin().is_terminal() && !assume_yes => match checkpoint::load(&bucket_name)? {
+            Some(saved_checkpoint) if saved_checkpoint.prefix == prefix => {
+                let resume = inquire::Confirm::new(&format!("An interrupted cleanup of {0} had processed {1} objects. Carry on from there?", bucket_name, saved_checkpoint.processed_count))
+                    .with_default(true)
+                    .prompt()?;
+                resume.then_some(saved_checkpoint)
+            }
+            _ => None,
+        },
+        false => None,
+    };
+    if let Some(saved_checkpoint) = &saved_checkpoint {
+        if saved_checkpoint.prefix != prefix {
+            bail!("The saved checkpoint for bucket {0} was made with a different prefix", bucket_name);
+        }
+        status!("Resuming cleanup, {0} objects already processed", saved_checkpoint.processed_count);
+    }
+    else if args.resume {
+        status!("{0}", "No saved checkpoint for this bucket, starting from the beginning".yellow());
+    }
+    let resume_from = saved_checkpoint.as_ref().map(|checkpoint| checkpoint.position.clone()).unwrap_or_default();
+    let previously_processed = saved_checkpoint.as_ref().map(|checkpoint| checkpoint.processed_count);
+    let checkpoint = match args.checkpoint || args.resume || saved_checkpoint.is_some() {
+        true => {
+            let initial_checkpoint = saved_checkpoint.unwrap_or(CleanupCheckpoint { prefix: prefix.clone(), ..Default::default() });
+            Some(CheckpointTracker::new(&bucket_name, initial_checkpoint))
+        }
+        false => None,
+    };
+
+    let worker_count = match args.concurrency {
+        Some(concurrency) => concurrency,
+        None => inquire::Text::new("Number of concurrent delete workers")
+            .with_default(&DEFAULT_DELETE_WORKERS.to_string())
+            .with_validator(validate_worker_count)
+            .prompt()?.parse()?,
+    };
+
+    let operation = interrupt::start_operation();
+    let context = Arc::new(CleanupContext {
+        bucket_name,
+        prefix,
+        cutoff,
+        too_new_count: AtomicU64::new(0),
+        page_size: args.page_size.unwrap_or(MAX_PAGE_SIZE),
+        checkpoint,
+        failed_deletes: Mutex::new(vec![]),
+        missing_keys: Mutex::new(BTreeSet::new()),
+        cancel: operation.token(),
+        delete_limit: retry::AdaptiveLimit::new(worker_count),
+        rate_limiter: args.rate.filter(|rps| *rps > 0).map(RateLimiter::new),
+        max_retries: args.retries.unwrap_or(DEFAULT_DELETE_RETRIES),
+        // The total isn't known up front, so the bar grows as each page of objects is discovered
+        progress: new_progress_bar(0),
+        run_stats: RunStats::default(),
+    });
+    let start_time = Instant::now();
+
+    let versioned = match &file_keys {
+        Some(_) => false,
+        None => is_bucket_versioned(&s3_client, &context.bucket_name).await?,
+    };
+    if versioned {
+        status!("{0}", "Bucket versioning is enabled, deleting all object versions and delete markers".blue());
+    }
+
+    // Writers may still be adding objects while cleanup runs, and failed delete batches leave objects behind,
+    // so the bucket is listed again after each pass and cleanup repeated until nothing is left. Keys from a
+    // keys file aren't listed, so they only ever get one pass.
+    let max_passes = match file_keys {
+        Some(_) => 1,
+        None => args.max_passes.unwrap_or(DEFAULT_CLEANUP_PASSES),
+    };
+    let mut passes = 0;
+    let mut queued_count = 0;
+    let mut failed_tasks = 0;
+    let mut remaining_count = 0;
+    while passes < max_passes {
+        if passes > 0 {
+            context.progress.println(format!("Listing found {0} objects left behind, running cleanup again (pass {1} of {2})", remaining_count, passes + 1, max_passes).yellow());
+        }
+        // Only what the last pass couldn't delete, or found too new, is reported at the end
+        context.failed_deletes.lock().unwrap().clear();
+        context.too_new_count.store(0, Ordering::Relaxed);
+        // A resumed cleanup starts its first pass from the checkpoint, and every later pass from the beginning
+        let start = match passes {
+            0 => resume_from.clone(),
+            _ => ListPosition::default(),
+        };
+        let (pass_queued_count, pass_failed_tasks) = run_cleanup_pass(&s3_client, &context, file_keys.as_deref(), versioned, &start, worker_count).await?;
+        passes += 1;
+        queued_count += pass_queued_count;
+        failed_tasks += pass_failed_tasks;
+        if file_keys.is_some() || context.cancel.is_cancelled() {
+            break;
+        }
+
+        remaining_count = count_remaining_objects(&s3_client, &context, versioned).await?;
+        // Another pass would only fail on the same keys again when this one couldn't delete any of them
+        let nothing_deleted = pass_queued_count > 0 && context.failed_deletes.lock().unwrap().len() as u64 == pass_queued_count;
+        if remaining_count == 0 || nothing_deleted {
+            break;
+        }
+    }
+
+    // Once everything has been listed and processed there's nothing left to resume
+    if context.checkpoint.is_some() && !context.cancel.is_cancelled() && failed_tasks == 0 {
+        checkpoint::clear(&context.bucket_name)?;
+    }
+
+    // Deleting by age or from a keys file only removes objects, leaving any uploads in progress to finish
+    let incomplete_uploads = match context.cancel.is_cancelled() || context.cutoff.is_some() || file_keys.is_some() {
+        true => IncompleteUploads::default(),
+        false => abort_incomplete_uploads(&s3_client, &context.bucket_name, &context.prefix, false).await?,
+    };
+    let too_new_count = context.too_new_count.load(Ordering::Relaxed);
+
+    if queued_count == 0 && remaining_count == 0 {
+        context.progress.finish_and_clear();
+        match (&context.prefix, incomplete_uploads.count) {
+            _ if file_keys.is_some() => status!("{0}", "None of the keys in the keys file exist".blue()),
+            _ if context.cutoff.is_some() => status!("{0}", format!("No objects old enough to delete, {0} were too new", too_new_count).blue()),
+            (Some(_), 0) => status!("{0}", "No objects found matching prefix".blue()),
+            (None, 0) => status!("{0}", "Bucket already empty".blue()),
+            (_, count) => status!("No objects to delete, aborted {0} incomplete multipart uploads holding {1} bytes",
+                (count - incomplete_uploads.failed_count).to_string().green(), incomplete_uploads.part_bytes),
+        }
+        return Ok(());
+    }
+    context.progress.finish_and_clear();
+    interrupt::report_if_interrupted(&context.cancel);
+    let mut summary = context.run_stats.summarize(start_time.elapsed());
+    if let Some(previously_processed) = previously_processed {
+        summary = summary.with_setting("Resumed after", format!("{0} objects", previously_processed));
+    }
+    if passes > 1 {
+        summary = summary.with_setting("Passes", passes);
+    }
+    if let Some(age) = args.older_than {
+        summary = summary
+            .with_setting("Older than", format_duration(age))
+            .with_setting("Too new", too_new_count);
+    }
+    if let Some(keys_file) = &args.keys_file {
+        summary = summary
+            .with_setting("Keys file", keys_file.display())
+            .with_setting("Missing", context.missing_keys.lock().unwrap().len());
+    }
+    if context.delete_limit.throttled_count() > 0 {
+        summary = summary
+            .with_setting("Throttled", format!("{0} batches", context.delete_limit.throttled_count()))
+            .with_setting("Final workers", context.delete_limit.current());
+    }
+    if incomplete_uploads.count > 0 {
+        summary = summary
+            .with_setting("Aborted uploads", incomplete_uploads.count - incomplete_uploads.failed_count)
+            .with_setting("Upload bytes", incomplete_uploads.part_bytes);
+    }
+    summary.print("Cleanup bucket", &context.bucket_name);
+    cost::print_cleanup_estimate(queued_count.div_ceil(context.page_size as u64).max(1));
+
+    report_missing_keys(&context.missing_keys.lock().unwrap());
+    let failed_deletes = context.failed_deletes.lock().unwrap();
+    if !failed_deletes.is_empty() {
+        status!("{0}", format!("{0} objects could not be deleted", failed_deletes.len()).red());
+        for failed_delete in failed_deletes.iter() {
+            let version = failed_delete.version_id.as_ref().map(|version_id| format!(" (version {0})", version_id)).unwrap_or_default();
+            status!("  {0}{1}  {2} {3}", failed_delete.key, version, failed_delete.code.clone().red(), failed_delete.message);
+        }
+    }
+
+    if failed_tasks > 0 {
+        status!("{0} delete task(s) did not complete; some objects may remain", failed_tasks.to_string().red());
+    }
+    else if failed_deletes.is_empty() && incomplete_uploads.failed_count == 0 && remaining_count == 0 {
+        status!("{0}", "Finished cleaning up bucket".green());
+    }
+    // An interrupted cleanup stops early on purpose, so what it left behind isn't a failure
+    if remaining_count > 0 && !context.cancel.is_cancelled() {
+        bail!("{0} objects are still in bucket {1} after {2} cleanup passes", remaining_count, context.bucket_name, passes);
+    }
+    Ok(())
+}
+
+// Lists the bucket once, or queues the keys from a keys file, handing every key to a fresh pool of delete
+// workers. Returns the number of keys queued and the number of delete tasks that panicked.
+async fn run_cleanup_pass(s3_client: &Client, context: &Arc<CleanupContext>, file_keys: Option<&[String]>, versioned: bool, start: &ListPosition, worker_count: u32) -> anyhow::Result<(u64, u32)> {
+    // The lister streams keys to a fixed pool of delete workers, which group them into DeleteObjects
+    // batches. The channel holds at most one batch worth of keys per worker, and each worker holds one
+    // more batch while deleting it, so memory use stays flat however many objects the bucket contains.
+    let (key_sender, key_receiver) = mpsc::channel(worker_count as usize * DELETE_BATCH_SIZE);
+    let key_receiver = Arc::new(tokio::sync::Mutex::new(key_receiver));
+    let delete_tasks: Vec<JoinHandle<()>> = (0..worker_count)
+        .map(|_| tokio::spawn(delete_worker(s3_client.clone(), context.clone(), key_receiver.clone())))
+        .collect();
+
+    let list_result = match (file_keys, versioned) {
+        (Some(file_keys), _) => send_file_keys(s3_client, context, file_keys, &key_sender).await,
+        (None, true) => send_version_keys(s3_client, context, start, &key_sender).await,
+        (None, false) => send_object_keys(s3_client, context, start, &key_sender).await,
+    };
+    // Closing the channel lets the workers exit once they've drained what's left
+    drop(key_sender);
+
+    // Wait for every delete worker to finish, reporting any task that panicked
+    let mut failed_tasks = 0;
+    for result in join_all(delete_tasks).await {
+        if let Err(join_err) = result {
+            context.progress.println(format!("Delete task failed: {0}", join_err.to_string().red()));
+            failed_tasks += 1;
+        }
+    }
+    Ok((list_result?, failed_tasks))
+}
+
+// Pages through the whole listing again after a pass, counting the objects a cleanup would still delete.
+// Versioned buckets are counted by object version and delete marker.
+async fn count_remaining_objects(s3_client: &Client, context: &CleanupContext, versioned: bool) -> anyhow::Result<u64> {
+    let mut remaining_count = 0;
+    let old_enough = |last_modified: Option<&DateTime>| is_old_enough(context.cutoff.as_ref(), last_modified);
+
+    if versioned {
+        let mut key_marker = None;
+        let mut version_id_marker = None;
+        loop {
+            let version_list = s3_client.list_object_versions()
+                .bucket(&context.bucket_name)
+                .set_prefix(context.prefix.clone())
+                .set_key_marker(key_marker)
+                .set_version_id_marker(version_id_marker)
+                .send().await
+                .sdk_context("Unable to list object versions")?;
+
+            key_marker = version_list.next_key_marker.clone();
+            version_id_marker = version_list.next_version_id_marker.clone();
+            remaining_count += version_list.versions().iter().filter(|version| old_enough(version.last_modified.as_ref())).count() as u64;
+            remaining_count += version_list.delete_markers().iter().filter(|marker| old_enough(marker.last_modified.as_ref())).count() as u64;
+            if !version_list.is_truncated.unwrap_or_default() { break; }
+        }
+    }
+    else {
+        let mut page_token = None;
+        loop {
+            let object_list = s3_client.list_objects_v2()
+                .bucket(&context.bucket_name)
+                .set_prefix(context.prefix.clone())
+                .set_continuation_token(page_token)
+                .send().await
+                .sdk_context("Unable to list objects")?;
+
+            remaining_count += object_list.contents().iter().filter(|object| old_enough(object.last_modified.as_ref())).count() as u64;
+            page_token = object_list.next_continuation_token;
+            if page_token.is_none() { break; }
+        }
+    }
+    Ok(remaining_count)
+}
+
+// Walks the same listing a real cleanup would, totalling what would be deleted without deleting anything
+async fn cleanup_dry_run(s3_client: &Client, bucket_name: &String, prefix: Option<String>, cutoff: Option<DateTime>, show_keys: bool) -> anyhow::Result<()> {
+    let versioned = is_bucket_versioned(s3_client, bucket_name).await?;
+    let mut object_count: u64 = 0;
+    let mut total_bytes: u64 = 0;
+    let mut too_new_count: u64 = 0;
+
+    let mut record = |key: Option<String>, size: Option<i64>, last_modified: Option<DateTime>| {
+        if !is_old_enough(cutoff.as_ref(), last_modified.as_ref()) {
+            too_new_count += 1;
+            return;
+        }
+        if show_keys {
+            status!("  {0}", key.unwrap_or_default());
+        }
+        object_count += 1;
+        total_bytes += size.unwrap_or_default() as u64;
+    };
+
+    if versioned {
+        let mut key_marker = None;
+        let mut version_id_marker = None;
+        loop {
+            let version_list = s3_client.list_object_versions()
+                .bucket(bucket_name)
+                .set_prefix(prefix.clone())
+                .set_key_marker(key_marker)
+                .set_version_id_marker(version_id_marker)
+                .send().await
+                .sdk_context("Unable to list object versions")?;
+
+            key_marker = version_list.next_key_marker;
+            version_id_marker = version_list.next_version_id_marker;
+            for version in version_list.versions.unwrap_or_default() {
+                record(version.key, version.size, version.last_modified);
+            }
+            for marker in version_list.delete_markers.unwrap_or_default() {
+                record(marker.key, None, marker.last_modified);
+            }
+            if !version_list.is_truncated.unwrap_or_default() { break; }
+        }
+    }
+    else {
+        let mut page_token = None;
+        loop {
+            let object_list = s3_client.list_objects_v2()
+                .bucket(bucket_name)
+                .set_prefix(prefix.clone())
+                .set_continuation_token(page_token)
+                .send().await
+                .sdk_context("Unable to list objects")?;
+
+            page_token = object_list.next_continuation_token;
+            for object in object_list.contents.unwrap_or_default() {
+                record(object.key, object.size, object.last_modified);
+            }
+            if page_token.is_none() { break; }
+        }
+    }
+
+    let incomplete_uploads = match cutoff {
+        Some(_) => IncompleteUploads::default(),
+        None => abort_incomplete_uploads(s3_client, bucket_name, &prefix, true).await?,
+    };
+
+    if output::is_json() {
+        let report = serde_json::json!({
+            "operation": "Cleanup bucket (dry run)",
+            "bucket": bucket_name,
+            "prefix": prefix,
+            "objects": object_count,
+            "too_new": too_new_count,
+            "total_bytes": total_bytes,
+            "incomplete_uploads": incomplete_uploads.count,
+            "incomplete_upload_bytes": incomplete_uploads.part_bytes,
+        });
+        println!("{0}", report);
+        return Ok(());
+    }
+
+    let object_label = if versioned { "object versions" } else { "objects" };
+    println!("{0}", "Cleanup bucket dry run".bold());
+    println!("  {0:<14} {1}", "Bucket", bucket_name);
+    if let Some(prefix) = &prefix {
+        println!("  {0:<14} {1}", "Prefix", prefix);
+    }
+    println!("  {0:<14} {1} {2}", "Would delete", object_count.to_string().yellow(), object_label);
+    println!("  {0:<14} {1}", "Total bytes", total_bytes.to_string().yellow());
+    if cutoff.is_some() {
+        println!("  {0:<14} {1} {2}", "Too new", too_new_count.to_string().blue(), object_label);
+    }
+    if incomplete_uploads.count > 0 {
+        println!("  {0:<14} {1} incomplete multipart uploads holding {2} bytes", "Would abort",
+            incomplete_uploads.count.to_string().yellow(), incomplete_uploads.part_bytes.to_string().yellow());
+    }
+    Ok(())
+}
+
+// Incomplete multipart uploads found by a cleanup, and what happened to them
+#[derive(Default)]
+struct IncompleteUploads {
+    count: u64,
+    // Total size of the parts already uploaded, which is what the uploads are billed for
+    part_bytes: u64,
+    // Uploads that couldn't be aborted, still holding storage
+    failed_count: u64,
+}
+
+// Multipart uploads that were never completed or aborted don't appear in object listings, but their parts
+// are stored and billed until the upload is aborted. Finds every pending upload under the prefix, totals its
+// parts, and aborts it unless this is a dry run.
+async fn abort_incomplete_uploads(s3_client: &Client, bucket_name: &String, prefix: &Option<String>, dry_run: bool) -> anyhow::Result<IncompleteUploads> {
+    let mut incomplete_uploads = IncompleteUploads::default();
+
+    let mut key_marker = None;
+    let mut upload_id_marker = None;
+    loop {
+        let upload_list = s3_client.list_multipart_uploads()
+            .bucket(bucket_name)
+            .set_prefix(prefix.clone())
+            .set_key_marker(key_marker)
+            .set_upload_id_marker(upload_id_marker)
+            .send().await
+            .sdk_context("Unable to list multipart uploads")?;
+
+        key_marker = upload_list.next_key_marker;
+        upload_id_marker = upload_list.next_upload_id_marker;
+        for upload in upload_list.uploads.unwrap_or_default() {
+            let (Some(key), Some(upload_id)) = (upload.key, upload.upload_id) else {
+                continue;
+            };
+            incomplete_uploads.count += 1;
+            incomplete_uploads.part_bytes += uploaded_part_bytes(s3_client, bucket_name, &key, &upload_id).await;
+            if dry_run {
+                continue;
+            }
+
+            let abort_result = s3_client.abort_multipart_upload()
+                .bucket(bucket_name)
+                .key(&key)
+                .upload_id(&upload_id)
+                .send().await;
+            // An upload completed or aborted since it was listed has nothing left to free
+            if let Err(err) = abort_result {
+                if !err.as_service_error().is_some_and(|service_err| service_err.is_no_such_upload()) {
+                    status!("{0}", format!("Unable to abort the upload of {0}: {1}", key, retry::error_reason(&err)).red());
+                    incomplete_uploads.failed_count += 1;
+                }
+            }
+        }
+        if !upload_list.is_truncated.unwrap_or_default() { break; }
+    }
+    Ok(incomplete_uploads)
+}
+
+// Size is only informational, so an upload whose parts can't be listed counts as holding nothing
+async fn uploaded_part_bytes(s3_client: &Client, bucket_name: &String, key: &str, upload_id: &str) -> u64 {
+    let mut part_bytes = 0;
+    let mut part_number_marker = None;
+    loop {
+        let Ok(part_list) = s3_client.list_parts()
+            .bucket(bucket_name)
+            .key(key)
+            .upload_id(upload_id)
+            .set_part_number_marker(part_number_marker)
+            .send().await else {
+            return part_bytes;
+        };
+        part_bytes += part_list.parts.unwrap_or_default().iter().map(|part| part.size.unwrap_or_default() as u64).sum::<u64>();
+        part_number_marker = part_list.next_part_number_marker;
+        if !part_list.is_truncated.unwrap_or_default() { return part_bytes; }
+    }
+}
+
+// State shared by the lister and every delete task of a cleanup run
+struct CleanupContext {
+    bucket_name: String,
+    // Limits deletion to keys starting with this prefix
+    prefix: Option<String>,
+    // Limits deletion to objects last modified before this time, when deleting by age
+    cutoff: Option<DateTime>,
+    // Objects listed but left alone for being modified after the cutoff
+    too_new_count: AtomicU64,
+    // Keys requested per listing page, up to the S3 maximum of 1000
+    page_size: i32,
+    // Set when checkpointing, to record which listed pages have been fully processed
+    checkpoint: Option<CheckpointTracker>,
+    // Objects that couldn't be deleted, listed once the run finishes
+    failed_deletes: Mutex<Vec<FailedDelete>>,
+    // Keys from --keys-file that didn't exist, which DeleteObjects would otherwise report as deleted
+    missing_keys: Mutex<BTreeSet<String>>,
+    // Cancelled on Ctrl-C, after which no further pages are listed or batches deleted
+    cancel: CancellationToken,
+    // Starts at the worker count and is lowered while S3 responds with SlowDown, so workers wait
+    // for a permit rather than adding to the throttling
+    delete_limit: retry::AdaptiveLimit,
+    // Paces DeleteObjects requests when --rate is given, on top of the adaptive limit
+    rate_limiter: Option<RateLimiter>,
+    // Retries for each DeleteObjects request, and for the keys it reports as failed for a retryable reason
+    max_retries: u32,
+    progress: ProgressBar,
+    run_stats: RunStats,
+}
+
+// Most missing keys printed at the end of a run, so a file that's mostly stale doesn't flood the terminal
+const MAX_LISTED_MISSING_KEYS: usize = 20;
+
+// Requests in flight while checking that the keys from a keys file exist
+const KEY_CHECK_CONCURRENCY: usize = 32;
+
+// Reads one key per line, skipping blank lines. Keys are otherwise used exactly as written, since
+// leading and trailing spaces are valid in S3 keys.
+fn read_keys_file(path: &Path) -> anyhow::Result<Vec<String>> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Unable to read keys file {0}", path.display()))?;
+    let keys: Vec<String> = contents.lines()
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect();
+    if keys.is_empty() {
+        bail!("Keys file {0} doesn't contain any keys", path.display());
+    }
+    status!("Read {0} keys from {1}", keys.len(), path.display());
+    Ok(keys)
+}
+
+fn report_missing_keys(missing_keys: &BTreeSet<String>) {
+    if missing_keys.is_empty() {
+        return;
+    }
+    status!("{0}", format!("{0} keys from the keys file were not found", missing_keys.len()).yellow());
+    for key in missing_keys.iter().take(MAX_LISTED_MISSING_KEYS) {
+        status!("  {0}", key);
+    }
+    if missing_keys.len() > MAX_LISTED_MISSING_KEYS {
+        status!("  ... and {0} more", missing_keys.len() - MAX_LISTED_MISSING_KEYS);
+    }
+}
+
+// Lists the key of every object in the bucket
+async fn list_object_keys(s3_client: &Client, bucket_name: &String) -> anyhow::Result<Vec<String>> {
+    let mut keys = vec![];
+
+    let mut page_token = None;
+    loop {
+        let object_list = s3_client.list_objects_v2()
+            .bucket(bucket_name)
+            .set_continuation_token(page_token)
+            .send().await
+            .sdk_context("Unable to list objects")?;
+
+        page_token = object_list.next_continuation_token;
+        keys.extend(object_list.contents.unwrap_or_default().into_iter().filter_map(|object| object.key));
+        if page_token.is_none() { break; }
+    }
+    Ok(keys)
+}
+
+// Versioning can't be disabled once enabled, only suspended, and a suspended bucket still holds older versions
+async fn is_bucket_versioned(s3_client: &Client, bucket_name: &String) -> anyhow::Result<bool> {
+    let versioning = s3_client.get_bucket_versioning()
+        .bucket(bucket_name)
+        .send().await
+        .sdk_context("Unable to read bucket versioning status")?;
+    Ok(versioning.status.is_some())
+}
+
+// Lists the current version of every object, queueing each one for deletion. Returns the number of objects queued.
+async fn send_object_keys(s3_client: &Client, context: &CleanupContext, start: &ListPosition, key_sender: &mpsc::Sender<QueuedKey>) -> anyhow::Result<u64> {
+    let mut queued_count = 0;
+
+    let mut page_token = start.continuation_token.clone();
+    while !context.cancel.is_cancelled() {
+        let object_list = s3_client.list_objects_v2()
+            .bucket(&context.bucket_name)
+            .set_prefix(context.prefix.clone())
+            .max_keys(context.page_size)
+            .set_continuation_token(page_token)
+            .send().await
+            .sdk_context("Unable to list objects")?;
+
+        page_token = object_list.next_continuation_token;
+        let object_ids = object_list.contents.unwrap_or_default().into_iter()
+            .filter(|object| old_enough_to_delete(context, object.last_modified.as_ref()))
+            .map(|object| ObjectIdentifier::builder().set_key(object.key).build())
+            .collect::<Result<Vec<ObjectIdentifier>, _>>()?;
+
+        let next_position = ListPosition { continuation_token: page_token.clone(), ..Default::default() };
+        queued_count += queue_keys(context, key_sender, next_position, object_ids).await?;
+        if page_token.is_none() { break; }
+    }
+    Ok(queued_count)
+}
+
+// Queues the keys from a keys file for deletion, a page at a time. DeleteObjects reports a key that
+// doesn't exist as deleted, so each key is checked with HEAD first to find the missing ones. Returns
+// the number of keys queued.
+async fn send_file_keys(s3_client: &Client, context: &CleanupContext, file_keys: &[String], key_sender: &mpsc::Sender<QueuedKey>) -> anyhow::Result<u64> {
+    let mut queued_count = 0;
+
+    for page_keys in file_keys.chunks(context.page_size as usize) {
+        if context.cancel.is_cancelled() {
+            break;
+        }
+        let found_keys: Vec<&String> = stream::iter(page_keys)
+            .map(|key| async move {
+                let head_result = s3_client.head_object().bucket(&context.bucket_name).key(key).send().await;
+                // Any other failure, such as AccessDenied, leaves it to DeleteObjects to report
+                let missing = head_result.is_err_and(|err| err.as_service_error().is_some_and(|service_err| service_err.is_not_found()));
+                (key, missing)
+            })
+            .buffered(KEY_CHECK_CONCURRENCY)
+            .filter_map(|(key, missing)| async move {
+                if missing {
+                    context.missing_keys.lock().unwrap().insert(key.clone());
+                }
+                (!missing).then_some(key)
+            })
+            .collect().await;
+
+        let object_ids = found_keys.into_iter()
+            .map(|key| ObjectIdentifier::builder().key(key).build())
+            .collect::<Result<Vec<ObjectIdentifier>, _>>()?;
+        queued_count += queue_keys(context, key_sender, ListPosition::default(), object_ids).await?;
+    }
+    Ok(queued_count)
+}
+
+// Lists every object version and delete marker, queueing each one for deletion. Returns the number of versions queued.
+async fn send_version_keys(s3_client: &Client, context: &CleanupContext, start: &ListPosition, key_sender: &mpsc::Sender<QueuedKey>) -> anyhow::Result<u64> {
+    let mut queued_count = 0;
+
+    let mut key_marker = start.key_marker.clone();
+    let mut version_id_marker = start.version_id_marker.clone();
+    while !context.cancel.is_cancelled() {
+        let version_list = s3_client.list_object_versions()
+            .bucket(&context.bucket_name)
+            .set_prefix(context.prefix.clone())
+            .max_keys(context.page_size)
+            .set_key_marker(key_marker)
+            .set_version_id_marker(version_id_marker)
+            .send().await
+            .sdk_context("Unable to list object versions")?;
+
+        key_marker = version_list.next_key_marker.clone();
+        version_id_marker = version_list.next_version_id_marker.clone();
+
+        let versions = version_list.versions.unwrap_or_default().into_iter()
+            .filter(|version| old_enough_to_delete(context, version.last_modified.as_ref()))
+            .map(|version| (version.key, version.version_id));
+        let delete_markers = version_list.delete_markers.unwrap_or_default().into_iter()
+            .filter(|marker| old_enough_to_delete(context, marker.last_modified.as_ref()))
+            .map(|marker| (marker.key, marker.version_id));
+        let object_ids = versions.chain(delete_markers)
+            .map(|(key, version_id)| ObjectIdentifier::builder().set_key(key).set_version_id(version_id).build())
+            .collect::<Result<Vec<ObjectIdentifier>, _>>()?;
+
+        let next_position = ListPosition { key_marker: key_marker.clone(), version_id_marker: version_id_marker.clone(), ..Default::default() };
+        queued_count += queue_keys(context, key_sender, next_position, object_ids).await?;
+        if !version_list.is_truncated.unwrap_or_default() { break; }
+    }
+    Ok(queued_count)
+}
+
+// Objects without a last modified time are kept, since there's no telling whether they're old enough
+fn is_old_enough(cutoff: Option<&DateTime>, last_modified: Option<&DateTime>) -> bool {
+    match (cutoff, last_modified) {
+        (None, _) => true,
+        (Some(cutoff), Some(last_modified)) => last_modified < cutoff,
+        (Some(_), None) => false,
+    }
+}
+
+// Counts the objects a cleanup by age leaves behind
+fn old_enough_to_delete(context: &CleanupContext, last_modified: Option<&DateTime>) -> bool {
+    let old_enough = is_old_enough(context.cutoff.as_ref(), last_modified);
+    if !old_enough {
+        context.too_new_count.fetch_add(1, Ordering::Relaxed);
+    }
+    old_enough
+}
+
+// A key waiting to be deleted, tagged with the listing page it came from for checkpointing
+struct QueuedKey {
+    page: u64,
+    object_id: ObjectIdentifier,
+}
+
+// Hands a page of objects to the delete workers, waiting whenever the channel is full. The position
+// is where listing continues after this page, saved once the page has been fully processed.
+async fn queue_keys(context: &CleanupContext, key_sender: &mpsc::Sender<QueuedKey>, next_position: ListPosition, object_ids: Vec<ObjectIdentifier>) -> anyhow::Result<u64> {
+    let object_count = object_ids.len() as u64;
+    let page = match &context.checkpoint {
+        Some(checkpoint) => checkpoint.start_page(next_position, object_count)?,
+        None => 0,
+    };
+    context.progress.inc_length(object_count);
+    for object_id in object_ids {
+        key_sender.send(QueuedKey { page, object_id }).await.context("Delete workers stopped unexpectedly")?;
+    }
+    Ok(object_count)
+}
+
+// Takes up to a full batch of keys off the shared channel at a time and deletes them, until the lister closes it
+async fn delete_worker(s3_client: Client, context: Arc<CleanupContext>, key_receiver: Arc<tokio::sync::Mutex<mpsc::Receiver<QueuedKey>>>) {
+    let mut queued_keys = Vec::with_capacity(DELETE_BATCH_SIZE);
+    loop {
+        // The lock is only held while waiting for keys, not while deleting them
+        let received_count = key_receiver.lock().await.recv_many(&mut queued_keys, DELETE_BATCH_SIZE).await;
+        if received_count == 0 {
+            break;
+        }
+
+        let mut page_counts: HashMap<u64, u64> = HashMap::new();
+        let object_ids = queued_keys.drain(..)
+            .map(|queued_key| {
+                *page_counts.entry(queued_key.page).or_default() += 1;
+                queued_key.object_id
+            })
+            .collect();
+        let failed_deletes = delete_objects(&s3_client, &context, object_ids).await;
+        context.failed_deletes.lock().unwrap().extend(failed_deletes);
+
+        // A batch skipped because of Ctrl-C wasn't processed, so it must stay ahead of the saved position
+        if let Some(checkpoint) = context.checkpoint.as_ref().filter(|_| !context.cancel.is_cancelled()) {
+            if let Err(err) = checkpoint.complete(&page_counts) {
+                context.progress.println(format!("Unable to save cleanup checkpoint: {0:#}", err).red());
+            }
+        }
+    }
+}
+
+const DEFAULT_DELETE_WORKERS: u32 = 8;
+
+// Cleanup passes to attempt before giving up on emptying a bucket that keeps gaining objects
+const DEFAULT_CLEANUP_PASSES: u32 = 10;
+
+// Largest page S3 will return from a listing request
+const MAX_PAGE_SIZE: i32 = 1000;
+
+// Maximum number of keys accepted by a single DeleteObjects request
+const DELETE_BATCH_SIZE: usize = 1000;
+
+// Number of times a DeleteObjects request, or the keys it reports as throttled, are retried unless --retries is given
+const DEFAULT_DELETE_RETRIES: u32 = 3;
+
+// An object left behind after its retries ran out, with the error S3 gave for it
+struct FailedDelete {
+    key: String,
+    version_id: Option<String>,
+    code: String,
+    message: String,
+}
+
+// Deletes the specified objects (or object versions) from an Amazon S3 bucket, recording the outcome in the run stats.
+// DeleteObjects can succeed overall while failing individual keys, so keys that failed for a retryable reason
+// are sent again in a smaller batch. Returns the objects that still couldn't be deleted.
+async fn delete_objects(s3_client: &Client, context: &CleanupContext, object_ids: Vec<ObjectIdentifier>) -> Vec<FailedDelete> {
+    let mut failed_deletes = vec![];
+
+    for batch in object_ids.chunks(DELETE_BATCH_SIZE) {
+        let mut pending_ids = batch.to_vec();
+        let mut attempt = 0;
+        while !pending_ids.is_empty() && !context.cancel.is_cancelled() {
+            let delete = Delete::builder()
+                .set_objects(Some(pending_ids.clone()))
+                .quiet(true)
+                .build().expect("delete request always has objects set");
+
+            // Each attempt waits for its own permit, so a throttled attempt lowers the limit before the next one
+            let request_start = Instant::now();
+            let delete = &delete;
+            let delete_result = retry::with_retries(context.max_retries, &context.run_stats, move || async move {
+                let permit = context.delete_limit.acquire().await;
+                if let Some(rate_limiter) = &context.rate_limiter {
+                    rate_limiter.acquire().await;
+                }
+                let delete_result = s3_client.delete_objects()
+                    .bucket(&context.bucket_name)
+                    .delete(delete.clone())
+                    .customize()
+                    .config_override(retry::without_sdk_retries())
+                    .send().await;
+
+                let throttled = match &delete_result {
+                    Ok(output) => output.errors().iter().any(|key_error| retry::is_throttling_code(key_error.code().unwrap_or_default())),
+                    Err(err) => retry::is_throttling(err),
+                };
+                match throttled {
+                    true => context.delete_limit.throttled(permit),
+                    false => context.delete_limit.succeeded(permit),
+                }
+                delete_result
+            })
+            .instrument(tracing::debug_span!("delete_objects", bucket = %context.bucket_name, keys = pending_ids.len(), attempt))
+            .await;
+
+            let delete_output = match delete_result {
+                Ok(output) => output,
+                Err(err) => {
+                    let reason = retry::error_reason(&err);
+                    context.progress.println(format!("Failed to delete batch of {0} objects: {1}", pending_ids.len(), reason.clone().red()));
+                    for object_id in &pending_ids {
+                        context.run_stats.record_failure(reason.clone());
+                        failed_deletes.push(FailedDelete {
+                            key: object_id.key().to_string(),
+                            version_id: object_id.version_id().map(str::to_string),
+                            code: reason.clone(),
+                            message: String::new(),
+                        });
+                    }
+                    context.progress.inc(pending_ids.len() as u64);
+                    break;
+                }
+            };
+            let latency = request_start.elapsed();
+
+            // With quiet mode enabled, only the keys that could not be deleted are returned
+            let key_errors = delete_output.errors.unwrap_or_default();
+            context.run_stats.record_batch(latency, (pending_ids.len() - key_errors.len()) as u64);
+
+            let mut retry_ids = vec![];
+            for key_error in key_errors {
+                let code = key_error.code().unwrap_or("Unknown error").to_string();
+                if attempt < context.max_retries && retry::is_retryable_code(&code) {
+                    retry_ids.push(ObjectIdentifier::builder()
+                        .set_key(key_error.key)
+                        .set_version_id(key_error.version_id)
+                        .build().expect("failed delete always has a key"));
+                    continue;
+                }
+                context.run_stats.record_failure(code.clone());
+                failed_deletes.push(FailedDelete {
+                    key: key_error.key.unwrap_or_default(),
+                    version_id: key_error.version_id,
+                    code,
+                    message: key_error.message.unwrap_or_default(),
+                });
+            }
+            context.progress.inc((pending_ids.len() - retry_ids.len()) as u64);
+
+            pending_ids = retry_ids;
+            if !pending_ids.is_empty() {
+                attempt += 1;
+                context.run_stats.record_retry();
+                tokio::time::sleep(retry::backoff_delay(attempt)).await;
+            }
+        }
+    }
+    failed_deletes
+}
+
+// Partitions the total object count across workers so the grand total matches exactly what was requested.
+// The remainder is spread one object at a time over the first workers, and workers with nothing to do are dropped.
+fn split_object_count(object_count: u32, worker_count: u32) -> Vec<u32> {
+    let objects_per_worker = object_count / worker_count;
+    let remainder = object_count % worker_count;
+
+    (0..worker_count)
+        .map(|worker| objects_per_worker + u32::from(worker < remainder))
+        .filter(|count| *count > 0)
+        .collect()
+}
+
+// Determines how the body of each created object is filled
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+enum PayloadMode {
+    // Zero-filled bytes, which compress and dedup extremely well
+    Zeros,
+    // A random buffer generated once per worker and reused for each of its objects
+    Random,
+    // Fresh random bytes for every object, so neither compression nor dedup can help
+    Incompressible,
+}
+
+impl PayloadMode {
+    fn all() -> Vec<PayloadMode> {
+        vec![PayloadMode::Zeros, PayloadMode::Random, PayloadMode::Incompressible]
+    }
+}
+
+impl std::fmt::Display for PayloadMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            PayloadMode::Zeros => "Zeros",
+            PayloadMode::Random => "Random",
+            PayloadMode::Incompressible => "Incompressible",
+        };
+        write!(f, "{0}", name)
+    }
+}
+
+// Server-side encryption applied to created objects
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+enum Encryption {
+    // Whatever the bucket's default encryption is
+    None,
+    // S3 managed keys (AES256)
+    SseS3,
+    // AWS KMS keys, which adds a KMS request to every PUT
+    SseKms,
+}
+
+impl Encryption {
+    fn all() -> Vec<Encryption> {
+        vec![Encryption::None, Encryption::SseS3, Encryption::SseKms]
+    }
+
+    fn server_side_encryption(&self) -> Option<ServerSideEncryption> {
+        match self {
+            Encryption::None => None,
+            Encryption::SseS3 => Some(ServerSideEncryption::Aes256),
+            Encryption::SseKms => Some(ServerSideEncryption::AwsKms),
+        }
+    }
+}
+
+impl std::fmt::Display for Encryption {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Encryption::None => "None",
+            Encryption::SseS3 => "SSE-S3",
+            Encryption::SseKms => "SSE-KMS",
+        };
+        write!(f, "{0}", name)
+    }
+}
+
+// S3 limits user metadata to 2KB per object, counting the bytes of every key and value, and each
+// generated pair takes 39 bytes
+const MAX_METADATA_SIZE: usize = 2048;
+const MAX_METADATA_COUNT: u32 = 50;
+
+// Builds metadata_count headers with 32 character values, eg. meta-01 = a1b2...
+fn generate_metadata(metadata_count: u32) -> HashMap<String, String> {
+    (1..=metadata_count)
+        .map(|index| (format!("meta-{0:02}", index), uuid::Uuid::new_v4().simple().to_string()))
+        .collect()
+}
+
+// Metadata is sent as HTTP headers, so keys are limited to characters that are safe in a header name and
+// values to printable ASCII
+fn parse_metadata_pair(input: &str) -> Option<(String, String)> {
+    let (key, value) = input.split_once('=')?;
+    let valid_key = !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.');
+    let valid_value = value.chars().all(|c| c.is_ascii_graphic() || c == ' ');
+    (valid_key && valid_value).then(|| (key.to_lowercase(), value.to_string()))
+}
+
+// S3 allows at most 10 tags per object, with keys of up to 128 characters and values of up to 256
+const MAX_TAG_COUNT: usize = 10;
+const MAX_TAG_KEY_LENGTH: usize = 128;
+const MAX_TAG_VALUE_LENGTH: usize = 256;
+
+// Checks a tag set of the form k1=v1&k2=v2, where every tag needs a non-empty key
+fn is_valid_tagging(input: &str) -> bool {
+    if input.is_empty() {
+        return true;
+    }
+    let tags: Vec<&str> = input.split('&').collect();
+    tags.len() <= MAX_TAG_COUNT && tags.iter().all(|tag| matches!(tag.split_once('='),
+        Some((key, value)) if !key.is_empty() && key.chars().count() <= MAX_TAG_KEY_LENGTH && value.chars().count() <= MAX_TAG_VALUE_LENGTH))
+}
+
+fn validate_tags(input: &str) -> Result<Validation, CustomUserError> {
+    if is_valid_tagging(input) {
+        return Ok(Validation::Valid);
+    }
+    Ok(Validation::Invalid("Invalid tags specified. Use up to 10 key=value pairs separated by &, with keys up to 128 and values up to 256 characters".into()))
+}
+
+// The tagging header is a URL query string, so keys and values are percent-encoded. Tags are entered
+// as plain text, which lets values contain spaces, slashes and other characters S3 allows in tags.
+fn encode_tagging(input: &str) -> String {
+    input.split('&')
+        .map(|tag| {
+            let (key, value) = tag.split_once('=').unwrap_or((tag, ""));
+            format!("{0}={1}", percent_encode(key), percent_encode(value))
+        })
+        .collect::<Vec<String>>()
+        .join("&")
+}
+
+fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{0:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+fn validate_percentage(input: &str) -> Result<Validation, CustomUserError> {
+    match input.parse::<u32>() {
+        Ok(percentage) if percentage <= 100 => Ok(Validation::Valid),
+        _ => Ok(Validation::Invalid("Invalid percentage specified. Please use a value from 0 - 100".into())),
+    }
+}
+
+fn validate_metadata_count(input: &str) -> Result<Validation, CustomUserError> {
+    match input.parse::<u32>() {
+        Ok(count) if count <= MAX_METADATA_COUNT => Ok(Validation::Valid),
+        _ => Ok(Validation::Invalid("Invalid metadata count specified. Please use a value from 0 - 50".into())),
+    }
+}
+
+// Determines how the keys of created objects are named
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+enum KeyNaming {
+    // Random UUIDs, which spread keys evenly across the keyspace
+    Uuid,
+    // Zero-padded counters such as obj-000001, for testing listing and prefix behavior
+    Sequential,
+    // Milliseconds since the Unix epoch followed by a UUID, so keys list in the order they were written
+    Timestamp,
+}
+
+impl KeyNaming {
+    fn all() -> Vec<KeyNaming> {
+        vec![KeyNaming::Uuid, KeyNaming::Sequential, KeyNaming::Timestamp]
+    }
+}
+
+impl std::fmt::Display for KeyNaming {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            KeyNaming::Uuid => "UUID",
+            KeyNaming::Sequential => "Sequential",
+            KeyNaming::Timestamp => "Timestamp",
+        };
+        write!(f, "{0}", name)
+    }
+}
+
+// Determines whether created objects share a single prefix or are spread across several
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+enum Keyspace {
+    // Every key under the one prefix, which S3 serves from a single partition until it splits
+    Hot,
+    // Keys shared out in turn between numbered prefixes such as 0000/ and 0001/
+    Spread,
+}
+
+impl Keyspace {
+    fn all() -> Vec<Keyspace> {
+        vec![Keyspace::Hot, Keyspace::Spread]
+    }
+}
+
+impl std::fmt::Display for Keyspace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Keyspace::Hot => "Hot (one prefix)",
+            Keyspace::Spread => "Spread (several prefixes)",
+        };
+        write!(f, "{0}", name)
+    }
+}
+
+// Spread prefixes are numbered with four digits
+const MAX_SPREAD_PREFIXES: u32 = 10_000;
+
+fn validate_spread_prefixes(input: &str) -> Result<Validation, CustomUserError> {
+    match input.parse::<u32>() {
+        Ok(count) if (2..=MAX_SPREAD_PREFIXES).contains(&count) => Ok(Validation::Valid),
+        _ => Ok(Validation::Invalid(format!("Invalid prefix count specified. Please use a value from 2 - {0}", MAX_SPREAD_PREFIXES).into())),
+    }
+}
+
+// Builds the next object key, drawing from the shared counter for sequential naming so concurrent
+// workers never produce the same key
+fn next_object_key(context: &CreateObjectsContext) -> String {
+    // The run length is the number of keys in the file, so every index handed out is within it
+    if let Some(file_keys) = &context.file_keys {
+        let index = context.next_sequence.fetch_add(1, Ordering::Relaxed) as usize;
+        return file_keys[index].clone();
+    }
+    let key_prefix = match context.spread_prefixes {
+        1 => context.key_prefix.clone(),
+        spread_prefixes => {
+            let spread_prefix = context.next_spread_prefix.fetch_add(1, Ordering::Relaxed) % spread_prefixes as u64;
+            format!("{0}{1:04}/", context.key_prefix, spread_prefix)
+        }
+    };
+    match context.key_naming {
+        KeyNaming::Uuid => format!("{0}{1}", key_prefix, uuid::Uuid::new_v4()),
+        KeyNaming::Sequential => {
+            let sequence = context.next_sequence.fetch_add(1, Ordering::Relaxed) + 1;
+            format!("{0}obj-{1:06}", key_prefix, sequence)
+        }
+        KeyNaming::Timestamp => {
+            let timestamp = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_millis();
+            format!("{0}{1:013}-{2}", key_prefix, timestamp, uuid::Uuid::new_v4())
+        }
+    }
+}
+
+// Builds an object body of exactly object_size bytes
+fn generate_payload(rng: &mut SmallRng, payload_mode: PayloadMode, object_size: u64) -> Bytes {
+    let mut buffer = vec![0u8; object_size as usize];
+    if payload_mode != PayloadMode::Zeros {
+        rng.fill_bytes(&mut buffer);
+    }
+    Bytes::from(buffer)
+}
+
+// Uploads objects until this worker's count is used up, the deadline of a timed run passes, or the run is cancelled.
+// An upload in flight at the deadline is allowed to finish and is counted.
+async fn create_object(s3_client: Client, context: Arc<CreateObjectsContext>, object_count: u32, deadline: Option<Instant>) {
+    let mut rng = SmallRng::from_entropy();
+
+    // Allocate the payloads once per task; cloning or slicing Bytes only bumps a reference count. Single
+    // PUTs send a slice of a buffer as large as the biggest object sent that way, and multipart uploads
+    // send the same part-sized buffer for every part rather than holding the whole object.
+    let put_buffer_size = match context.sizes.min() <= context.multipart_threshold {
+        true => context.sizes.max().min(context.multipart_threshold),
+        false => 0,
+    };
+    let part_buffer_size = match context.sizes.max() > context.multipart_threshold {
+        true => context.part_size,
+        false => 0,
+    };
+    let put_buffer = generate_payload(&mut rng, context.payload_mode, put_buffer_size);
+    let part_buffer = generate_payload(&mut rng, context.payload_mode, part_buffer_size);
+
+    for _ in 1..=object_count {
+        if context.cancel.is_cancelled() || deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            break;
+        }
+        let key = next_object_key(&context);
+        let object_size = context.sizes.sample(&mut rng);
+        let multipart = object_size > context.multipart_threshold;
+
+        let payload = match (context.payload_mode, multipart) {
+            (PayloadMode::Incompressible, true) => generate_payload(&mut rng, context.payload_mode, context.part_size),
+            (PayloadMode::Incompressible, false) => generate_payload(&mut rng, context.payload_mode, object_size),
+            (_, true) => part_buffer.clone(),
+            (_, false) => put_buffer.slice(0..object_size as usize),
+        };
+
+        if let Some(rate_limiter) = &context.rate_limiter {
+            rate_limiter.acquire().await;
+        }
+
+        // A fresh client per request is built before the timer starts, so only connection setup is measured, not client setup
+        let s3_client = match context.connection_strategy {
+            ConnectionStrategy::PerRequest => connection::fresh_client(&s3_client),
+            _ => s3_client.clone(),
+        };
+
+        let request_start = Instant::now();
+        let put_result = if multipart {
+            multipart::upload_multipart(&s3_client, &context, &key, object_size, &payload).await
+        }
+        else {
+            let expected_checksum = context.checksum.compute(&payload);
+            let (checksum_crc32c, checksum_sha256) = context.checksum.header_values(expected_checksum.clone());
+            // The body stream is consumed by each attempt, so it's rebuilt from the shared payload
+            let put_span = tracing::debug_span!("put_object", bucket = %context.bucket_name, key = %key);
+            retry::with_retries(context.max_retries, &context.run_stats, || {
+                s3_client.put_object()
+                    .bucket(&context.bucket_name)
+                    .key(&key)
+                    .storage_class(context.storage_class.clone())
+                    .set_acl(context.acl.clone())
+                    .set_server_side_encryption(context.encryption.server_side_encryption())
+                    .set_ssekms_key_id(context.kms_key_id.clone())
+                    .set_metadata(Some(context.metadata.clone()))
+                    .set_content_type(context.content_type.clone())
+                    .set_tagging(context.tagging.clone())
+                    .set_checksum_algorithm(context.checksum.algorithm())
+                    .set_checksum_crc32_c(checksum_crc32c.clone())
+                    .set_checksum_sha256(checksum_sha256.clone())
+                    .body(ByteStream::new(SdkBody::from(payload.clone())))
+                    .customize()
+                    .config_override(retry::without_sdk_retries())
+                    .send()
+            }).instrument(put_span).await.map(|_| expected_checksum).map_err(|err| retry::error_reason(&err))
+        };
+
+        let latency = request_start.elapsed();
+        latency_log::record("put", &key, object_size, latency, put_result.as_ref().map(|_| ()).map_err(String::as_str));
+        match put_result {
+            Ok(expected_checksum) => {
+                context.run_stats.record(latency, object_size);
+                context.size_histogram.record(&context.sizes, object_size);
+                if rng.gen_range(0..100) < context.verify_sample {
+                    verify_download(&s3_client, &context, &key, object_size, &payload).await;
+                }
+                else if context.verify_checksums {
+                    verify_checksum(&s3_client, &context, &key, expected_checksum).await;
+                }
+            }
+            Err(reason) => context.run_stats.record_failure(reason),
+        }
+        context.completed_count.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+// Reads back the checksum S3 stored for an object and compares it with the one computed before upload.
+// The verification request isn't timed, so it doesn't skew the upload latencies.
+async fn verify_checksum(s3_client: &Client, context: &CreateObjectsContext, key: &str, expected_checksum: Option<String>) {
+    let head_result = s3_client.head_object()
+        .bucket(&context.bucket_name)
+        .key(key)
+        .checksum_mode(ChecksumMode::Enabled)
+        .send().await;
+
+    match head_result {
+        Ok(output) => {
+            let stored_checksum = context.checksum.returned_value(output.checksum_crc32_c, output.checksum_sha256);
+            context.run_stats.record_verification(stored_checksum.is_some() && stored_checksum == expected_checksum);
+        }
+        Err(err) => context.run_stats.record_failure(format!("Checksum verification failed: {0}", retry::error_reason(&err))),
+    }
+}
+
+// Downloads an object again and compares it with the payload it was uploaded from. Multipart objects
+// repeat the part buffer, so the body is compared a part at a time. Like the checksum check, the
+// download isn't timed or counted towards the transferred bytes.
+async fn verify_download(s3_client: &Client, context: &CreateObjectsContext, key: &str, object_size: u64, payload: &Bytes) {
+    let get_result = s3_client.get_object()
+        .bucket(&context.bucket_name)
+        .key(key)
+        .send().await;
+
+    let body = match get_result {
+        Ok(output) => output.body.collect().await.map(|body| body.into_bytes()).map_err(|err| format!("Body read failed: {0}", err)),
+        Err(err) => Err(retry::error_reason(&err)),
+    };
+    match body {
+        Ok(body) => {
+            let matched = body.len() as u64 == object_size
+                && body.chunks(payload.len().max(1)).all(|chunk| chunk == &payload[..chunk.len()]);
+            if !matched {
+                tracing::warn!(key, "downloaded object doesn't match the uploaded payload");
+            }
+            context.run_stats.record_verification(matched);
+        }
+        Err(reason) => context.run_stats.record_failure(format!("Download verification failed: {0}", reason)),
+    }
+}
+
+// Checks a new bucket name against the S3 general purpose bucket naming rules, returning the first rule broken
+fn bucket_name_error(name: &str) -> Option<&'static str> {
+    if name.len() < 3 || name.len() > 63 {
+        return Some("must be between 3 and 63 characters long");
+    }
+    if !name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '.') {
+        return Some("can only contain lowercase letters, numbers, hyphens and periods");
+    }
+    if !name.starts_with(|c: char| c.is_ascii_alphanumeric()) || !name.ends_with(|c: char| c.is_ascii_alphanumeric()) {
+        return Some("must begin and end with a letter or number");
+    }
+    if name.contains("..") {
+        return Some("cannot contain two adjacent periods");
+    }
+    if name.parse::<std::net::Ipv4Addr>().is_ok() {
+        return Some("cannot be formatted as an IP address");
+    }
+    if name.starts_with("xn--") || name.starts_with("sthree-") || name.ends_with("-s3alias") || name.ends_with("--ol-s3") {
+        return Some("uses a prefix or suffix reserved by S3");
+    }
+    None
+}
+
+fn validate_bucket_name(input: &str) -> Result<Validation, CustomUserError> {
+    match bucket_name_error(input) {
+        Some(reason) => Ok(Validation::Invalid(format!("Invalid bucket name, it {0}", reason).into())),
+        None => Ok(Validation::Valid),
+    }
+}
+
+// Largest count accepted for objects, requests and other quantities
+const MAX_COUNT: u32 = 999_999_999;
+
+// Parses a count such as "500", "10k" or "2m", where k is a thousand and m a million
+fn parse_count(input: &str) -> Option<u32> {
+    let regex = regex::Regex::new(r"(?i)^\s*(\d{1,9})\s*(k|m)?\s*$").unwrap();
+    let captures = regex.captures(input)?;
+    let value: u32 = captures[1].parse().ok()?;
+    let multiplier = match captures.get(2).map(|m| m.as_str().to_lowercase()).as_deref() {
+        Some("k") => 1_000,
+        Some("m") => 1_000_000,
+        _ => 1,
+    };
+    value.checked_mul(multiplier).filter(|count| *count <= MAX_COUNT)
+}
+
+fn validate_number(input: &str) -> Result<Validation, CustomUserError> {
+    match parse_count(input) {
+        Some(_) => Ok(Validation::Valid),
+        None => Ok(Validation::Invalid(format!("Invalid quantity specified. Please use a value from 0 - {0}, optionally with a k or m suffix, eg. 500k", MAX_COUNT).into())),
+    }
+}
+
+fn validate_optional_number(input: &str) -> Result<Validation, CustomUserError> {
+    if input.is_empty() {
+        return Ok(Validation::Valid);
+    }
+    validate_number(input)
+}
+
+fn validate_pass_count(input: &str) -> Result<Validation, CustomUserError> {
+    match input.parse::<u32>() {
+        Ok(passes) if passes > 0 => Ok(Validation::Valid),
+        _ => Ok(Validation::Invalid("Invalid number of passes specified. Please use a value of at least 1".into())),
+    }
+}
+
+fn validate_retry_count(input: &str) -> Result<Validation, CustomUserError> {
+    match input.parse::<u32>() {
+        Ok(retries) if retries <= retry::MAX_RETRIES => Ok(Validation::Valid),
+        _ => Ok(Validation::Invalid(format!("Invalid retry count specified. Please use a value from 0 - {0}", retry::MAX_RETRIES).into())),
+    }
+}
+
+// Offers the worker count chosen last time as the default, remembering whichever is entered
+fn prompt_worker_count() -> anyhow::Result<u32> {
+    let worker_count = inquire::Text::new("Number of concurrent workers")
+        .with_default(&recent::get(Setting::Concurrency).unwrap_or("16".into()))
+        .with_validator(validate_worker_count)
+        .prompt()?;
+    recent::remember(Setting::Concurrency, &worker_count);
+    Ok(worker_count.parse()?)
+}
+
+fn validate_worker_count(input: &str) -> Result<Validation, CustomUserError> {
+    let regex = regex::Regex::new(r"^\d{1,4}$").unwrap();
+    if regex.is_match(input) && input.parse::<u32>().unwrap() > 0 {
+        return Ok(Validation::Valid);
+    }
+    Ok(Validation::Invalid("Invalid worker count specified. Please use a value from 1 - 9999".into()))
+}
+
+// Parses a storage class name such as STANDARD_IA, rejecting anything S3 doesn't know about
+fn parse_storage_class(input: &str) -> Option<StorageClass> {
+    let name = input.trim().to_uppercase();
+    StorageClass::values().contains(&name.as_str()).then(|| StorageClass::from(name.as_str()))
+}
+
+// Largest object that can be uploaded with a single PutObject request (5GB)
+const MAX_SINGLE_PUT_SIZE: u64 = 5 * 1024 * 1024 * 1024;
+
+// Largest object S3 will store, which requires a multipart upload (5TB)
+const MAX_OBJECT_SIZE: u64 = 5 * 1024 * 1024 * 1024 * 1024;
+
+// Runs that would upload more than this in total (100GB) get a warning before starting
+const LARGE_UPLOAD_WARNING: u64 = 100 * 1024 * 1024 * 1024;
+
+// Parses a human-readable size such as "512", "4KB", "1MB", "2GB" or "1TB" into a byte count
+fn parse_size(input: &str) -> Option<u64> {
+    let regex = regex::Regex::new(r"(?i)^\s*(\d+)\s*(B|KB|MB|GB|TB)?\s*$").unwrap();
+    let captures = regex.captures(input)?;
+    let value: u64 = captures[1].parse().ok()?;
+    let multiplier = match captures.get(2).map(|m| m.as_str().to_uppercase()).as_deref() {
+        Some("KB") => 1024,
+        Some("MB") => 1024 * 1024,
+        Some("GB") => 1024 * 1024 * 1024,
+        Some("TB") => 1024 * 1024 * 1024 * 1024,
+        _ => 1,
+    };
+    value.checked_mul(multiplier)
+}
+
+// Parses a run length such as "90", "30s", "5m" or "1h". A plain number is taken as seconds.
+fn parse_duration(input: &str) -> Option<Duration> {
+    let regex = regex::Regex::new(r"(?i)^\s*(\d+)\s*(s|m|h|d)?\s*$").unwrap();
+    let captures = regex.captures(input)?;
+    let value: u64 = captures[1].parse().ok()?;
+    let multiplier = match captures.get(2).map(|m| m.as_str().to_lowercase()).as_deref() {
+        Some("m") => 60,
+        Some("h") => 60 * 60,
+        Some("d") => 24 * 60 * 60,
+        _ => 1,
+    };
+    value.checked_mul(multiplier).filter(|seconds| *seconds > 0).map(Duration::from_secs)
+}
+
+// A plain number is a request count, while a number with a time suffix such as 30s or 2m is a duration
+fn parse_warmup(input: &str) -> Option<RunLength> {
+    match input.trim().ends_with(|c: char| c.is_ascii_alphabetic()) {
+        true => parse_duration(input).map(RunLength::Duration).or_else(|| parse_count(input).map(RunLength::Count)),
+        false => parse_count(input).map(RunLength::Count),
+    }
+}
+
+fn validate_warmup(input: &str) -> Result<Validation, CustomUserError> {
+    match parse_warmup(input) {
+        Some(_) => Ok(Validation::Valid),
+        None => Ok(Validation::Invalid("Invalid warm-up specified. Use a request count such as 100, or a duration such as 30s or 2m".into())),
+    }
+}
+
+fn validate_duration(input: &str) -> Result<Validation, CustomUserError> {
+    match parse_duration(input) {
+        Some(_) => Ok(Validation::Valid),
+        None => Ok(Validation::Invalid("Invalid duration specified. Use a number with an optional s, m, h or d suffix, eg. 30s, 5m, 1h, 7d".into())),
+    }
+}
+
+// Formats a whole number of seconds the way it would be typed, eg. 1h30m or 45s
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    let (days, hours, minutes, seconds) = (seconds / 86400, seconds % 86400 / 3600, seconds % 3600 / 60, seconds % 60);
+    let mut formatted = String::new();
+    if days > 0 {
+        formatted.push_str(&format!("{0}d", days));
+    }
+    if hours > 0 {
+        formatted.push_str(&format!("{0}h", hours));
+    }
+    if minutes > 0 {
+        formatted.push_str(&format!("{0}m", minutes));
+    }
+    if seconds > 0 || formatted.is_empty() {
+        formatted.push_str(&format!("{0}s", seconds));
+    }
+    formatted
+}
+
+fn validate_size_distribution(input: &str) -> Result<Validation, CustomUserError> {
+    match SizeDistribution::parse(input) {
+        Ok(_) => Ok(Validation::Valid),
+        Err(reason) => Ok(Validation::Invalid(format!("Invalid size, {0}", reason).into())),
+    }
+}
+
+fn validate_size(input: &str) -> Result<Validation, CustomUserError> {
+    match parse_size(input) {
+        Some(size) if size > MAX_OBJECT_SIZE => {
+            Ok(Validation::Invalid("Object size cannot exceed 5TB, the S3 object size limit".into()))
+        }
+        Some(_) => Ok(Validation::Valid),
+        None => Ok(Validation::Invalid("Invalid size specified. Use a number with an optional suffix, eg. 4KB, 1MB, 10MB".into())),
+    }
+}
+
+async fn select_authentication(profile_files: &EnvConfigFiles) -> anyhow::Result<(SdkConfig, Option<String>)> {
+    let auth_options = vec!["Default", "Environment Variables", "Profile", "SSO", "Assume Role"];
+    let auth_selection = inquire::Select::new("Select AWS authentication option", auth_options).prompt()?;
+
+    if auth_selection == "Profile" {
+        let profile_name = select_profile(profile_files).await?;
+        let aws_cfg = aws_config::from_env().profile_files(profile_files.clone()).profile_name(&profile_name).load().await;
+        return Ok((aws_cfg, Some(profile_name)));
+    }
+    else if auth_selection == "SSO" {
+        let sso_profile = select_sso_profile(profile_files).await?;
+        let aws_cfg = aws_config::from_env().profile_files(profile_files.clone()).profile_name(&sso_profile).load().await;
+        return Ok((aws_cfg, Some(sso_profile)));
+    }
+    else if auth_selection == "Assume Role" {
+        return Ok((assume_role_config(profile_files).await?, None));
+    }
+    else if auth_selection == "Environment Variables" {
+        dotenvy::dotenv().context("Unable to load environment variables from .env")?;
+    }
+    Ok((aws_config::from_env().profile_files(profile_files.clone()).load().await, None))
+}
+
+// Uses the default credential chain to assume an IAM role, typically one in another account.
+// Credentials are refreshed from STS automatically as they expire during long runs.
+async fn assume_role_config(profile_files: &EnvConfigFiles) -> anyhow::Result<SdkConfig> {
+    let role_arn = inquire::Text::new("Role ARN to assume")
+        .with_validator(validate_role_arn)
+        .prompt()?;
+    let session_name = inquire::Text::new("Session name")
+        .with_default("s3-stress")
+        .prompt()?;
+    let external_id = inquire::Text::new("External ID (leave blank for none)").prompt()?;
+
+    let base_cfg = aws_config::from_env().profile_files(profile_files.clone()).load().await;
+    let mut provider = AssumeRoleProvider::builder(role_arn)
+        .session_name(session_name)
+        .configure(&base_cfg);
+    if !external_id.is_empty() {
+        provider = provider.external_id(external_id);
+    }
+    let provider = provider.build().await;
+
+    Ok(base_cfg.into_builder()
+        .credentials_provider(SharedCredentialsProvider::new(provider))
+        .build())
+}
+
+fn validate_role_arn(input: &str) -> Result<Validation, CustomUserError> {
+    let regex = regex::Regex::new(r"^arn:aws[\w-]*:iam::\d{12}:role/.+$").unwrap();
+    if regex.is_match(input) {
+        return Ok(Validation::Valid);
+    }
+    Ok(Validation::Invalid("Invalid role ARN specified, eg. arn:aws:iam::123456789012:role/StressTest".into()))
+}
+
+async fn select_sso_profile(profile_files: &EnvConfigFiles) -> anyhow::Result<String> {
+    let loaded_profiles = get_aws_env_config_sections(profile_files).await?;
+    let prompt = "Select an SSO profile";
+    let profile_names = loaded_profiles.sso_sessions().map(|x| x.to_string()).collect();
+
+    Ok(inquire::Select::new(prompt, profile_names).prompt()?)
+}
+
+async fn get_aws_env_config_sections(profile_files: &EnvConfigFiles) -> anyhow::Result<aws_config::profile::ProfileSet> {
+    let fs = aws_types::os_shim_internal::Fs::real();
+    let env = aws_types::os_shim_internal::Env::real();
+
+    aws_config::profile::load(&fs, &env, profile_files, None).await.context("Unable to load AWS profiles")
+}
+
+async fn select_profile(profile_files: &EnvConfigFiles) -> anyhow::Result<String> {
+    let loaded_profiles = get_aws_env_config_sections(profile_files).await?;
+    let profile_names: Vec<&str> = loaded_profiles.profiles().collect();
+    let prompt = "Select an AWS profile";
+    Ok(inquire::Select::new(prompt, profile_names).prompt()?.to_string())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_sdk_s3::operation::{delete_objects::DeleteObjectsOutput, get_bucket_versioning::GetBucketVersioningOutput, list_multipart_uploads::ListMultipartUploadsOutput, list_objects_v2::ListObjectsV2Output, put_object::PutObjectOutput};
+    use aws_sdk_s3::types::Object;
+    use aws_smithy_mocks_experimental::{mock, MockResponseInterceptor, Rule, RuleMode};
+    use aws_smithy_runtime::client::http::test_util::infallible_client_fn;
+
+    // A client that answers every request from the given rules instead of calling S3. The HTTP responses
+    // are empty, since the interceptor replaces them with the output of the matching rule.
+    fn mock_client(rules: &[&Rule]) -> Client {
+        let interceptor = rules.iter().fold(MockResponseInterceptor::new().rule_mode(RuleMode::MatchAny), |interceptor, rule| interceptor.with_rule(rule));
+        Client::from_conf(s3::Config::builder()
+            .behavior_version(aws_config::BehaviorVersion::latest())
+            .region(Region::new("us-east-1"))
+            .credentials_provider(s3::config::Credentials::new("test", "test", None, None, "test"))
+            .http_client(infallible_client_fn(|_request| hyper::Response::new(SdkBody::empty())))
+            .interceptor(interceptor)
+            .build())
+    }
+
+    // Cleans up the whole bucket without prompting for anything
+    fn cleanup_args() -> CleanupArgs {
+        CleanupArgs { prefix: Some(String::new()), concurrency: Some(2), ..Default::default() }
+    }
+
+    // Records the keys sent in every DeleteObjects request. Rules only see the request when matching it,
+    // so the keys are collected by the matcher.
+    fn delete_objects_rule(deleted_keys: &Arc<Mutex<Vec<String>>>) -> Rule {
+        let deleted_keys = deleted_keys.clone();
+        mock!(Client::delete_objects)
+            .match_requests(move |request| {
+                let objects = request.delete().map(|delete| delete.objects()).unwrap_or_default();
+                deleted_keys.lock().unwrap().extend(objects.iter().map(|object| object.key().to_string()));
+                true
+            })
+            .then_output(|| DeleteObjectsOutput::builder().build())
+    }
+
+    // Objects in a mocked listing, leaving out the ones deleted so far as S3 would
+    fn listed_objects(deleted_keys: &Mutex<Vec<String>>, keys: &[&str]) -> Vec<Object> {
+        let deleted_keys = deleted_keys.lock().unwrap();
+        keys.iter()
+            .filter(|key| !deleted_keys.iter().any(|deleted_key| deleted_key == *key))
+            .map(|key| Object::builder().key(*key).build())
+            .collect()
+    }
+
+    fn unversioned_rules() -> [Rule; 2] {
+        [
+            mock!(Client::get_bucket_versioning).then_output(|| GetBucketVersioningOutput::builder().build()),
+            mock!(Client::list_multipart_uploads).then_output(|| ListMultipartUploadsOutput::builder().build()),
+        ]
+    }
+
+    #[tokio::test]
+    async fn cleanup_deletes_every_listed_page() {
+        let deleted_keys = Arc::new(Mutex::new(vec![]));
+        let first_page_keys = deleted_keys.clone();
+        let first_page = mock!(Client::list_objects_v2)
+            .match_requests(|request| request.continuation_token().is_none())
+            .then_output(move || ListObjectsV2Output::builder()
+                .set_contents(Some(listed_objects(&first_page_keys, &["a", "b"])))
+                .is_truncated(true)
+                .next_continuation_token("page-2")
+                .build());
+        let second_page_keys = deleted_keys.clone();
+        let second_page = mock!(Client::list_objects_v2)
+            .match_requests(|request| request.continuation_token() == Some("page-2"))
+            .then_output(move || ListObjectsV2Output::builder()
+                .set_contents(Some(listed_objects(&second_page_keys, &["c"])))
+                .build());
+        let [versioning, uploads] = unversioned_rules();
+        let delete_objects = delete_objects_rule(&deleted_keys);
+        let s3_client = mock_client(&[&versioning, &first_page, &second_page, &uploads, &delete_objects]);
+
+        operation_cleanup_bucket(s3_client, "test-bucket".to_string(), cleanup_args(), true, false).await.unwrap();
+
+        // Each page is listed once to delete its objects and once more to check none are left
+        assert_eq!(first_page.num_calls(), 2);
+        assert_eq!(second_page.num_calls(), 2);
+        let mut deleted_keys = deleted_keys.lock().unwrap().clone();
+        deleted_keys.sort();
+        assert_eq!(deleted_keys, vec!["a", "b", "c"]);
+    }
+
+    #[tokio::test]
+    async fn cleanup_of_an_empty_bucket_deletes_nothing() {
+        let list_objects = mock!(Client::list_objects_v2).then_output(|| ListObjectsV2Output::builder().build());
+        let [versioning, uploads] = unversioned_rules();
+        let deleted_keys = Arc::new(Mutex::new(vec![]));
+        let delete_objects = delete_objects_rule(&deleted_keys);
+        let s3_client = mock_client(&[&versioning, &list_objects, &uploads, &delete_objects]);
+
+        operation_cleanup_bucket(s3_client, "test-bucket".to_string(), cleanup_args(), true, false).await.unwrap();
+
+        assert_eq!(list_objects.num_calls(), 2);
+        assert_eq!(delete_objects.num_calls(), 0);
+        assert!(deleted_keys.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn cleanup_runs_again_for_objects_written_during_a_pass() {
+        let deleted_keys = Arc::new(Mutex::new(vec![]));
+        let listed_keys = deleted_keys.clone();
+        // The second object only shows up once the first has been deleted, as if a writer added it mid-cleanup
+        let list_objects = mock!(Client::list_objects_v2).then_output(move || {
+            let keys = match listed_keys.lock().unwrap().is_empty() {
+                true => vec!["a"],
+                false => vec!["a", "late"],
+            };
+            ListObjectsV2Output::builder()
+                .set_contents(Some(listed_objects(&listed_keys, &keys)))
+                .build()
+        });
+        let [versioning, uploads] = unversioned_rules();
+        let delete_objects = delete_objects_rule(&deleted_keys);
+        let s3_client = mock_client(&[&versioning, &list_objects, &uploads, &delete_objects]);
+
+        operation_cleanup_bucket(s3_client, "test-bucket".to_string(), cleanup_args(), true, false).await.unwrap();
+
+        assert_eq!(delete_objects.num_calls(), 2);
+        assert_eq!(*deleted_keys.lock().unwrap(), vec!["a", "late"]);
+    }
+
+    #[tokio::test]
+    async fn cleanup_reports_what_remains_after_the_last_pass() {
+        // The object is listed again however many times it's deleted
+        let list_objects = mock!(Client::list_objects_v2).then_output(|| ListObjectsV2Output::builder()
+            .contents(Object::builder().key("a").build())
+            .build());
+        let [versioning, uploads] = unversioned_rules();
+        let deleted_keys = Arc::new(Mutex::new(vec![]));
+        let delete_objects = delete_objects_rule(&deleted_keys);
+        let s3_client = mock_client(&[&versioning, &list_objects, &uploads, &delete_objects]);
+
+        let args = CleanupArgs { max_passes: Some(3), ..cleanup_args() };
+        let err = operation_cleanup_bucket(s3_client, "test-bucket".to_string(), args, true, false).await.unwrap_err();
+
+        assert_eq!(err.to_string(), "1 objects are still in bucket test-bucket after 3 cleanup passes");
+        assert_eq!(delete_objects.num_calls(), 3);
+    }
+
+    // Creates count small objects without prompting for anything
+    fn create_objects_args(count: u32, concurrency: u32) -> CreateObjectsArgs {
+        CreateObjectsArgs {
+            count: Some(count),
+            size: Some(SizeDistribution::Fixed(1024)),
+            payload: Some(PayloadMode::Zeros),
+            storage_class: Some(StorageClass::Standard),
+            acl: Some(ObjectCannedAcl::BucketOwnerFullControl),
+            encryption: Some(Encryption::None),
+            checksum: Some(Checksum::None),
+            verify_sample: Some(0),
+            metadata_count: Some(0),
+            content_type: Some(String::new()),
+            tags: Some(String::new()),
+            prefix: Some(String::new()),
+            naming: Some(KeyNaming::Sequential),
+            keyspace: Some(Keyspace::Hot),
+            concurrency: Some(concurrency),
+            rate: Some(0),
+            retries: Some(0),
+            connection_strategy: Some(ConnectionStrategy::Shared),
+            warmup: Some(RunLength::Count(0)),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn create_objects_puts_exactly_the_requested_count() {
+        let put_object = mock!(Client::put_object).then_output(|| PutObjectOutput::builder().build());
+        let s3_client = mock_client(&[&put_object]);
+
+        operation_create_objects(s3_client, "test-bucket".to_string(), create_objects_args(10, 4), false).await.unwrap();
+
+        assert_eq!(put_object.num_calls(), 10);
+    }
+
+    #[test]
+    fn split_object_count_divides_evenly() {
+        let counts = split_object_count(100, 4);
+        assert_eq!(counts, vec![25, 25, 25, 25]);
+        assert_eq!(counts.iter().sum::<u32>(), 100);
+    }
+
+    #[test]
+    fn split_object_count_spreads_the_remainder() {
+        let counts = split_object_count(10, 4);
+        assert_eq!(counts, vec![3, 3, 2, 2]);
+        assert_eq!(counts.iter().sum::<u32>(), 10);
+    }
+
+    #[test]
+    fn split_object_count_drops_idle_workers() {
+        let counts = split_object_count(3, 8);
+        assert_eq!(counts, vec![1, 1, 1]);
+        assert_eq!(counts.iter().sum::<u32>(), 3);
+    }
+
+    #[test]
+    fn split_object_count_of_zero_starts_no_workers() {
+        let counts = split_object_count(0, 4);
+        assert!(counts.is_empty());
+        assert_eq!(counts.iter().sum::<u32>(), 0);
+    }
+
+    #[test]
+    fn parse_count_accepts_suffixes_and_spaces() {
+        assert_eq!(parse_count("500k"), Some(500_000));
+        assert_eq!(parse_count("2M"), Some(2_000_000));
+        assert_eq!(parse_count(" 7 "), Some(7));
+    }
+
+    #[test]
+    fn parse_count_rejects_counts_over_the_limit() {
+        assert_eq!(parse_count("1234567890"), None);
+        assert_eq!(parse_count("1000m"), None);
+        assert_eq!(parse_count(&MAX_COUNT.to_string()), Some(MAX_COUNT));
+    }
+
+    #[test]
+    fn parse_count_rejects_overflow() {
+        assert_eq!(parse_count("5000m"), None);
+    }
+
+    #[test]
+    fn bucket_region_without_a_constraint_is_us_east_1() {
+        assert_eq!(bucket_region(None), "us-east-1");
+        assert_eq!(bucket_region(Some(&BucketLocationConstraint::from(""))), "us-east-1");
+    }
+
+    #[test]
+    fn bucket_region_maps_legacy_eu_to_eu_west_1() {
+        assert_eq!(bucket_region(Some(&BucketLocationConstraint::Eu)), "eu-west-1");
+    }
+
+    #[test]
+    fn bucket_region_passes_other_regions_through() {
+        assert_eq!(bucket_region(Some(&BucketLocationConstraint::EuWest2)), "eu-west-2");
+    }
+
+    #[test]
+    fn create_bucket_configuration_leaves_out_us_east_1() {
+        assert_eq!(create_bucket_configuration("us-east-1"), None);
+    }
+
+    #[test]
+    fn create_bucket_configuration_sets_other_regions() {
+        let configuration = create_bucket_configuration("eu-west-2").unwrap();
+        assert_eq!(configuration.location_constraint(), Some(&BucketLocationConstraint::EuWest2));
+    }
+
+    #[test]
+    fn parse_duration_accepts_suffixes() {
+        assert_eq!(parse_duration("30s"), Some(Duration::from_secs(30)));
+        assert_eq!(parse_duration("5m"), Some(Duration::from_secs(5 * 60)));
+        assert_eq!(parse_duration("1h"), Some(Duration::from_secs(60 * 60)));
+        assert_eq!(parse_duration("7D"), Some(Duration::from_secs(7 * 24 * 60 * 60)));
+        assert_eq!(parse_duration(" 90 "), Some(Duration::from_secs(90)));
+    }
+
+    #[test]
+    fn parse_duration_rejects_zero_and_unknown_suffixes() {
+        assert_eq!(parse_duration("0s"), None);
+        assert_eq!(parse_duration("0"), None);
+        assert_eq!(parse_duration("10x"), None);
+        assert_eq!(parse_duration(""), None);
+        assert_eq!(parse_duration("1h30m"), None);
+    }
+
+    #[test]
+    fn format_duration_round_trips_through_parse_duration() {
+        for input in ["30s", "5m", "1h", "7d"] {
+            assert_eq!(format_duration(parse_duration(input).unwrap()), input);
+        }
+        assert_eq!(format_duration(Duration::from_secs(5400)), "1h30m");
+        assert_eq!(format_duration(Duration::from_secs(90061)), "1d1h1m1s");
+        assert_eq!(format_duration(Duration::ZERO), "0s");
+    }
+
+    #[test]
+    fn encode_tagging_percent_encodes_keys_and_values() {
+        assert_eq!(encode_tagging("team=load test&env=dev"), "team=load%20test&env=dev");
+        assert_eq!(encode_tagging("formula=a=b"), "formula=a%3Db");
+        assert_eq!(encode_tagging("path=a/b&note=x+y"), "path=a%2Fb&note=x%2By");
+        assert_eq!(encode_tagging("flag"), "flag=");
+        assert_eq!(percent_encode("a&b=c d"), "a%26b%3Dc%20d");
+        assert_eq!(percent_encode("Az09-_.~"), "Az09-_.~");
+    }
+
+    #[test]
+    fn is_valid_tagging_accepts_up_to_the_limits() {
+        assert!(is_valid_tagging(""));
+        assert!(is_valid_tagging("team=load test&env="));
+        let tags = (1..=MAX_TAG_COUNT).map(|index| format!("k{0}=v", index)).collect::<Vec<String>>().join("&");
+        assert!(is_valid_tagging(&tags));
+        assert!(is_valid_tagging(&format!("{0}={1}", "k".repeat(MAX_TAG_KEY_LENGTH), "v".repeat(MAX_TAG_VALUE_LENGTH))));
+    }
+
+    #[test]
+    fn is_valid_tagging_rejects_tags_over_the_limits() {
+        let tags = (1..=MAX_TAG_COUNT + 1).map(|index| format!("k{0}=v", index)).collect::<Vec<String>>().join("&");
+        assert!(!is_valid_tagging(&tags));
+        assert!(!is_valid_tagging(&format!("{0}=v", "k".repeat(MAX_TAG_KEY_LENGTH + 1))));
+        assert!(!is_valid_tagging(&format!("k={0}", "v".repeat(MAX_TAG_VALUE_LENGTH + 1))));
+        assert!(!is_valid_tagging("=v"));
+        assert!(!is_valid_tagging("team"));
+        assert!(!is_valid_tagging("team=a&&env=b"));
+    }
+
+    #[test]
+    fn parse_metadata_pair_splits_on_the_first_equals_sign() {
+        assert_eq!(parse_metadata_pair("Owner=load test"), Some(("owner".to_string(), "load test".to_string())));
+        assert_eq!(parse_metadata_pair("query=a=b"), Some(("query".to_string(), "a=b".to_string())));
+        assert_eq!(parse_metadata_pair("empty="), Some(("empty".to_string(), String::new())));
+    }
+
+    #[test]
+    fn parse_metadata_pair_rejects_invalid_pairs() {
+        assert_eq!(parse_metadata_pair("owner"), None);
+        assert_eq!(parse_metadata_pair("=value"), None);
+        assert_eq!(parse_metadata_pair("my key=value"), None);
+        assert_eq!(parse_metadata_pair("owner=caf\u{e9}"), None);
+        assert_eq!(parse_metadata_pair("owner=line\nbreak"), None);
+    }
+
+    #[test]
+    fn bucket_name_error_accepts_valid_names() {
+        let sixty_three = "a".repeat(63);
+        for name in ["abc", "my-bucket.logs", "bucket-2024", "1.2.3", sixty_three.as_str()] {
+            assert_eq!(bucket_name_error(name), None, "{name}");
+        }
+    }
+
+    #[test]
+    fn bucket_name_error_reports_the_broken_rule() {
+        let sixty_four = "a".repeat(64);
+        let cases = [
+            ("ab", "must be between 3 and 63 characters long"),
+            (sixty_four.as_str(), "must be between 3 and 63 characters long"),
+            ("My-Bucket", "can only contain lowercase letters, numbers, hyphens and periods"),
+            ("my_bucket", "can only contain lowercase letters, numbers, hyphens and periods"),
+            ("-bucket", "must begin and end with a letter or number"),
+            ("bucket.", "must begin and end with a letter or number"),
+            ("my..bucket", "cannot contain two adjacent periods"),
+            ("192.168.1.1", "cannot be formatted as an IP address"),
+            ("xn--bucket", "uses a prefix or suffix reserved by S3"),
+            ("sthree-bucket", "uses a prefix or suffix reserved by S3"),
+            ("bucket-s3alias", "uses a prefix or suffix reserved by S3"),
+            ("bucket--ol-s3", "uses a prefix or suffix reserved by S3"),
+        ];
+        for (name, error) in cases {
+            assert_eq!(bucket_name_error(name), Some(error), "{name}");
+        }
+    }
+
+    #[test]
+    fn parse_warmup_reads_plain_numbers_as_request_counts() {
+        assert!(matches!(parse_warmup("100"), Some(RunLength::Count(100))));
+        assert!(matches!(parse_warmup("0"), Some(RunLength::Count(0))));
+        assert!(matches!(parse_warmup("2k"), Some(RunLength::Count(2000))));
+    }
+
+    #[test]
+    fn parse_warmup_reads_time_suffixes_as_durations() {
+        assert!(matches!(parse_warmup("30s"), Some(RunLength::Duration(duration)) if duration == Duration::from_secs(30)));
+        assert!(matches!(parse_warmup(" 2m "), Some(RunLength::Duration(duration)) if duration == Duration::from_secs(120)));
+    }
+
+    #[test]
+    fn parse_warmup_rejects_invalid_input() {
+        for input in ["", "abc", "10x", "0s", "-5", "1.5m"] {
+            assert!(parse_warmup(input).is_none(), "{input}");
+        }
+    }
+
+    #[test]
+    fn is_region_name_accepts_region_names() {
+        for name in ["us-east-1", "ap-southeast-2", "us-gov-west-1", "eu-central-2"] {
+            assert!(is_region_name(name), "{name}");
+        }
+    }
+
+    #[test]
+    fn is_region_name_rejects_other_strings() {
+        for name in ["", "us_east_1", "US-EAST-1", "us-east", "useast1", "us-east-1 "] {
+            assert!(!is_region_name(name), "{name:?}");
+        }
+    }
+}
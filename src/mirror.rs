@@ -0,0 +1,130 @@
+use aws_sdk_s3::Client;
+use colorize::AnsiColor;
+use futures::future::join_all;
+use std::{sync::{atomic::{AtomicU64, Ordering}, Arc}, time::Instant};
+use tokio_util::sync::CancellationToken;
+
+use crate::cli::MirrorBucketArgs;
+use crate::copy::{copy_multipart, copy_source, list_source_objects, SourceObject};
+use crate::interrupt;
+use crate::output::status;
+use crate::stats::RunStats;
+use crate::{new_progress_bar, prompt_worker_count, retry, track_progress, MAX_SINGLE_PUT_SIZE};
+
+// Settings and shared counters for every worker taking part in a bucket mirror
+struct MirrorBucketContext {
+    source_bucket: String,
+    destination_bucket: String,
+    // Source prefix swapped for the destination prefix in each copied key
+    prefix: String,
+    destination_prefix: String,
+    sources: Vec<SourceObject>,
+    // Position of the next object to copy, shared so workers pick up whichever object is next
+    next_index: AtomicU64,
+    cancel: CancellationToken,
+    completed_count: Arc<AtomicU64>,
+    run_stats: RunStats,
+}
+
+// Copies every object under the prefix to the destination bucket, keeping the key unless a destination
+// prefix replaces the source prefix. The source is listed
+// through a client for its own region, while the copies are sent to the destination's region, since S3
+// performs the copy from the destination side and fetches the source itself, even across regions.
+#[tracing::instrument(skip_all, fields(source = %source_bucket, destination = %destination_bucket))]
+pub async fn operation_mirror_bucket(source_client: Client, source_bucket: String, destination_client: Client, destination_bucket: String, args: MirrorBucketArgs) -> anyhow::Result<()> {
+    let prefix = match args.prefix {
+        Some(prefix) => prefix,
+        None => inquire::Text::new("Mirror objects under which prefix? (leave blank for the whole bucket)").prompt()?,
+    };
+
+    let destination_prefix = match args.destination_prefix {
+        Some(destination_prefix) => destination_prefix,
+        None => inquire::Text::new("Write the copies under which prefix? (leave blank to keep the same keys)")
+            .with_initial_value(&prefix)
+            .prompt()?,
+    };
+    // Within one bucket every object would be copied onto itself, so the copies need a prefix of their own
+    if source_bucket == destination_bucket && destination_prefix == prefix {
+        anyhow::bail!("When mirroring within the same bucket, the destination prefix must differ from the source prefix");
+    }
+
+    let sources = list_source_objects(&source_client, &source_bucket, &prefix, "").await?;
+    if sources.is_empty() {
+        status!("{0}", "No objects found to mirror".blue());
+        return Ok(());
+    }
+    let source_size: u64 = sources.iter().map(|source| source.size).sum();
+    status!("Found {0} objects totalling {1} bytes to mirror", sources.len(), source_size);
+
+    let worker_count = match args.concurrency {
+        Some(concurrency) => concurrency,
+        None => prompt_worker_count()?,
+    };
+
+    let operation = interrupt::start_operation();
+    let object_count = sources.len() as u64;
+    let context = Arc::new(MirrorBucketContext {
+        source_bucket,
+        destination_bucket,
+        prefix,
+        destination_prefix,
+        sources,
+        next_index: AtomicU64::new(0),
+        cancel: operation.token(),
+        completed_count: Arc::new(AtomicU64::new(0)),
+        run_stats: RunStats::default(),
+    });
+
+    let progress = new_progress_bar(object_count);
+    let progress_task = tokio::spawn(track_progress(progress.clone(), context.completed_count.clone(), vec![context.run_stats.byte_counter()]));
+
+    let start_time = Instant::now();
+
+    let mut join_handle_list = vec![];
+    for _ in 0..worker_count.min(object_count as u32) {
+        join_handle_list.push(tokio::spawn(mirror_worker(destination_client.clone(), context.clone())));
+    }
+    join_all(join_handle_list).await;
+
+    progress_task.abort();
+    progress.finish_and_clear();
+    interrupt::report_if_interrupted(&context.cancel);
+    context.run_stats.summarize(start_time.elapsed())
+        .with_setting("Source", &context.source_bucket)
+        .with_setting("Destination", &context.destination_bucket)
+        .with_setting("Dest prefix", &context.destination_prefix)
+        .print("Mirror bucket", &context.destination_bucket);
+    Ok(())
+}
+
+async fn mirror_worker(s3_client: Client, context: Arc<MirrorBucketContext>) {
+    loop {
+        if context.cancel.is_cancelled() {
+            break;
+        }
+        let index = context.next_index.fetch_add(1, Ordering::Relaxed) as usize;
+        let Some(source) = context.sources.get(index) else {
+            break;
+        };
+        let copy_source = copy_source(&context.source_bucket, &source.key);
+        let destination_key = format!("{0}{1}", context.destination_prefix, source.key.strip_prefix(&context.prefix).unwrap_or(&source.key));
+
+        let request_start = Instant::now();
+        let copy_result = match source.size > MAX_SINGLE_PUT_SIZE {
+            true => copy_multipart(&s3_client, &context.destination_bucket, &context.cancel, &copy_source, &destination_key, source.size).await,
+            false => s3_client.copy_object()
+                .bucket(&context.destination_bucket)
+                .key(&destination_key)
+                .copy_source(&copy_source)
+                .send().await
+                .map(|_| ())
+                .map_err(|err| retry::error_reason(&err)),
+        };
+
+        match copy_result {
+            Ok(()) => context.run_stats.record(request_start.elapsed(), source.size),
+            Err(reason) => context.run_stats.record_failure(reason),
+        }
+        context.completed_count.fetch_add(1, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,222 @@
+use anyhow::Context;
+use aws_sdk_s3::{primitives::{ByteStream, SdkBody}, Client};
+use futures::future::join_all;
+use inquire::{validator::Validation, CustomUserError};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+use std::{sync::{atomic::{AtomicU64, Ordering}, Arc, Mutex}, time::Instant};
+
+use tokio_util::sync::CancellationToken;
+
+use crate::cli::MixedWorkloadArgs;
+use crate::interrupt;
+use crate::stats::RunStats;
+use crate::{format_duration, generate_payload, new_duration_progress_bar, new_progress_bar, parse_count, parse_duration, parse_size, prompt_worker_count, retry, split_object_count, track_progress, validate_duration, validate_number, validate_size, PayloadMode, RunLength};
+
+// Settings and shared state for every worker taking part in a mixed workload run
+struct MixedWorkloadContext {
+    bucket_name: String,
+    object_size: u64,
+    // Percentage of operations that should be reads, from 0 to 100
+    read_percent: u32,
+    // Keys written so far in this run, which is the pool reads are drawn from
+    written_keys: Mutex<Vec<String>>,
+    // End of a timed run, when workers stop instead of counting down their share of operations
+    deadline: Option<Instant>,
+    cancel: CancellationToken,
+    completed_count: Arc<AtomicU64>,
+    read_stats: RunStats,
+    write_stats: RunStats,
+}
+
+#[tracing::instrument(skip_all, fields(bucket = %bucket_name))]
+pub async fn operation_mixed_workload(s3_client: Client, bucket_name: String, args: MixedWorkloadArgs) -> anyhow::Result<()> {
+    let read_percent = match args.ratio {
+        Some(ratio) => ratio,
+        None => {
+            let ratio = inquire::Text::new("Read:write ratio")
+                .with_default("70:30")
+                .with_validator(validate_ratio)
+                .prompt()?;
+            parse_ratio(&ratio).context("Invalid read:write ratio")?
+        }
+    };
+
+    let run_length = match (args.count, args.duration) {
+        (Some(count), _) => RunLength::Count(count),
+        (None, Some(duration)) => RunLength::Duration(duration),
+        (None, None) => match inquire::Select::new("Run for a number of operations or a length of time?", vec!["Operation count", "Duration"]).prompt()? {
+            "Duration" => {
+                let duration = inquire::Text::new("How long should I keep running operations? (eg. 30s, 5m, 1h)")
+                    .with_default("5m")
+                    .with_validator(validate_duration)
+                    .prompt()?;
+                RunLength::Duration(parse_duration(&duration).context("Invalid duration")?)
+            }
+            _ => {
+                let operation_count = inquire::Text::new("How many operations should I run in total?")
+                    .with_validator(validate_number)
+                    .prompt()?;
+                RunLength::Count(parse_count(&operation_count).context("Invalid operation count")?)
+            }
+        },
+    };
+
+    let object_size = match args.size {
+        Some(size) => size,
+        None => {
+            let object_size = inquire::Text::new("What size should each written object be? (eg. 4KB, 1MB, 10MB)")
+                .with_default("1KB")
+                .with_validator(validate_size)
+                .prompt()?;
+            parse_size(&object_size).context("Invalid object size")?
+        }
+    };
+
+    let worker_count = match args.concurrency {
+        Some(concurrency) => concurrency,
+        None => prompt_worker_count()?,
+    };
+
+    // A timed run gives every worker an unlimited count and stops them at the deadline instead
+    let worker_operation_counts = match run_length {
+        RunLength::Count(operation_count) => split_object_count(operation_count, worker_count),
+        RunLength::Duration(_) => vec![u32::MAX; worker_count as usize],
+    };
+
+    let operation = interrupt::start_operation();
+    let context = Arc::new(MixedWorkloadContext {
+        bucket_name,
+        object_size,
+        read_percent,
+        written_keys: Mutex::new(vec![]),
+        deadline: match run_length {
+            RunLength::Count(_) => None,
+            RunLength::Duration(duration) => Some(Instant::now() + duration),
+        },
+        cancel: operation.token(),
+        completed_count: Arc::new(AtomicU64::new(0)),
+        read_stats: RunStats::default(),
+        write_stats: RunStats::default(),
+    });
+
+    let progress = match run_length {
+        RunLength::Count(operation_count) => new_progress_bar(operation_count as u64),
+        RunLength::Duration(duration) => new_duration_progress_bar(duration),
+    };
+    let progress_task = tokio::spawn(track_progress(progress.clone(), context.completed_count.clone(), vec![context.read_stats.byte_counter(), context.write_stats.byte_counter()]));
+
+    let start_time = Instant::now();
+
+    let mut join_handle_list = vec![];
+    for worker_operation_count in worker_operation_counts {
+        join_handle_list.push(tokio::spawn(mixed_worker(s3_client.clone(), context.clone(), worker_operation_count)));
+    }
+    join_all(join_handle_list).await;
+
+    progress_task.abort();
+    progress.finish_and_clear();
+    interrupt::report_if_interrupted(&context.cancel);
+
+    let elapsed = start_time.elapsed();
+    let mut read_summary = context.read_stats.summarize(elapsed);
+    let mut write_summary = context.write_stats.summarize(elapsed);
+    if let RunLength::Duration(duration) = run_length {
+        read_summary = read_summary.with_setting("Duration", format_duration(duration));
+        write_summary = write_summary.with_setting("Duration", format_duration(duration));
+    }
+    read_summary.print("Mixed workload reads", &context.bucket_name);
+    write_summary.print("Mixed workload writes", &context.bucket_name);
+    Ok(())
+}
+
+async fn mixed_worker(s3_client: Client, context: Arc<MixedWorkloadContext>, operation_count: u32) {
+    let mut rng = SmallRng::from_entropy();
+    let payload = generate_payload(&mut rng, PayloadMode::Random, context.object_size);
+
+    for _ in 1..=operation_count {
+        if context.cancel.is_cancelled() || context.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            break;
+        }
+        // Until something has been written there's nothing to read, so the first operations are always writes
+        let read_key = if rng.gen_range(0..100) < context.read_percent {
+            let written_keys = context.written_keys.lock().unwrap();
+            (!written_keys.is_empty()).then(|| written_keys[rng.gen_range(0..written_keys.len())].clone())
+        }
+        else {
+            None
+        };
+
+        let request_start = Instant::now();
+        match read_key {
+            Some(key) => {
+                let get_result = s3_client.get_object()
+                    .bucket(&context.bucket_name)
+                    .key(key)
+                    .send().await;
+
+                match get_result {
+                    Ok(output) => match output.body.collect().await {
+                        Ok(body) => context.read_stats.record(request_start.elapsed(), body.into_bytes().len() as u64),
+                        Err(err) => context.read_stats.record_failure(format!("Body read failed: {0}", err)),
+                    },
+                    Err(err) => context.read_stats.record_failure(retry::error_reason(&err)),
+                }
+            }
+            None => {
+                let key = uuid::Uuid::new_v4().to_string();
+                let put_result = s3_client.put_object()
+                    .bucket(&context.bucket_name)
+                    .key(&key)
+                    .body(ByteStream::new(SdkBody::from(payload.clone())))
+                    .send().await;
+
+                match put_result {
+                    Ok(_) => {
+                        context.write_stats.record(request_start.elapsed(), context.object_size);
+                        context.written_keys.lock().unwrap().push(key);
+                    }
+                    Err(err) => context.write_stats.record_failure(retry::error_reason(&err)),
+                }
+            }
+        }
+        context.completed_count.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+// Parses a read:write ratio such as "70:30" into the percentage of operations that are reads
+pub fn parse_ratio(input: &str) -> Option<u32> {
+    let (reads, writes) = input.trim().split_once(':')?;
+    let reads: u32 = reads.trim().parse().ok()?;
+    let writes: u32 = writes.trim().parse().ok()?;
+    let total = reads.checked_add(writes).filter(|total| *total > 0)?;
+    Some((reads as f64 * 100.0 / total as f64).round() as u32)
+}
+
+fn validate_ratio(input: &str) -> Result<Validation, CustomUserError> {
+    if parse_ratio(input).is_some() {
+        return Ok(Validation::Valid);
+    }
+    Ok(Validation::Invalid("Invalid ratio specified. Use reads:writes, eg. 70:30".into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_ratio_gives_the_read_percentage() {
+        assert_eq!(parse_ratio("70:30"), Some(70));
+        assert_eq!(parse_ratio(" 1 : 2 "), Some(33));
+        assert_eq!(parse_ratio("2:1"), Some(67));
+    }
+
+    #[test]
+    fn parse_ratio_rejects_an_empty_ratio() {
+        assert_eq!(parse_ratio("0:0"), None);
+    }
+
+    #[test]
+    fn parse_ratio_rejects_totals_that_overflow() {
+        assert_eq!(parse_ratio("4000000000:4000000000"), None);
+    }
+}
@@ -0,0 +1,136 @@
+use aws_sdk_s3::{primitives::{ByteStream, SdkBody}, types::{CompletedMultipartUpload, CompletedPart}, Client};
+use bytes::Bytes;
+use futures::{stream, StreamExt};
+use std::time::Instant;
+
+use crate::{retry, CreateObjectsContext};
+
+// S3 requires every part except the last to be at least 5MB
+pub const MIN_PART_SIZE: u64 = 5 * 1024 * 1024;
+pub const DEFAULT_PART_SIZE: u64 = 8 * 1024 * 1024;
+const MAX_PART_COUNT: u64 = 10_000;
+
+// Number of parts of a single object uploaded at the same time
+const PART_CONCURRENCY: usize = 4;
+
+// Picks a part size for an object, growing beyond the preferred size when needed to stay within the 10,000
+// part limit or to meet the 5MB minimum
+pub fn part_size(object_size: u64, preferred: u64) -> u64 {
+    preferred.max(MIN_PART_SIZE).max(object_size.div_ceil(MAX_PART_COUNT))
+}
+
+// Uploads a single object in parts, reusing the part buffer as the body of every part so memory use
+// is bounded by the part size rather than the object size. If any part fails, the upload is aborted
+// so the parts already stored don't linger and incur storage charges. Returns the checksum S3
+// should report for the finished object, when checksums are enabled.
+pub async fn upload_multipart(s3_client: &Client, context: &CreateObjectsContext, key: &str, object_size: u64, part_payload: &Bytes) -> Result<Option<String>, String> {
+    let create_output = retry::with_retries(context.max_retries, &context.run_stats, || {
+        s3_client.create_multipart_upload()
+            .bucket(&context.bucket_name)
+            .key(key)
+            .storage_class(context.storage_class.clone())
+            .set_acl(context.acl.clone())
+            .set_server_side_encryption(context.encryption.server_side_encryption())
+            .set_ssekms_key_id(context.kms_key_id.clone())
+            .set_metadata(Some(context.metadata.clone()))
+            .set_content_type(context.content_type.clone())
+            .set_tagging(context.tagging.clone())
+            .set_checksum_algorithm(context.checksum.algorithm())
+            .customize()
+            .config_override(retry::without_sdk_retries())
+            .send()
+    }).await.map_err(|err| retry::error_reason(&err))?;
+    let upload_id = create_output.upload_id.unwrap_or_default();
+
+    let part_size = part_payload.len() as u64;
+    let part_count = object_size.div_ceil(part_size);
+
+    let part_results: Vec<Result<CompletedPart, String>> = stream::iter(1..=part_count)
+        .map(|part_number| {
+            let upload_id = &upload_id;
+            async move {
+                if context.cancel.is_cancelled() {
+                    return Err("Interrupted".to_string());
+                }
+                // Only the final part can be smaller than the others
+                let length = part_size.min(object_size - (part_number - 1) * part_size);
+                let body = part_payload.slice(0..length as usize);
+                let part_checksum = context.checksum.compute(&body);
+                let (checksum_crc32c, checksum_sha256) = context.checksum.header_values(part_checksum);
+
+                let part_start = Instant::now();
+                let upload_result = retry::with_retries(context.max_retries, &context.run_stats, || {
+                    s3_client.upload_part()
+                        .bucket(&context.bucket_name)
+                        .key(key)
+                        .upload_id(upload_id)
+                        .part_number(part_number as i32)
+                        .set_checksum_algorithm(context.checksum.algorithm())
+                        .set_checksum_crc32_c(checksum_crc32c.clone())
+                        .set_checksum_sha256(checksum_sha256.clone())
+                        .body(ByteStream::new(SdkBody::from(body.clone())))
+                        .customize()
+                        .config_override(retry::without_sdk_retries())
+                        .send()
+                }).await;
+                let upload_output = match upload_result {
+                    Ok(output) => {
+                        context.part_stats.record(part_start.elapsed(), length);
+                        output
+                    }
+                    Err(err) => {
+                        let reason = retry::error_reason(&err);
+                        context.part_stats.record_failure(reason.clone());
+                        return Err(reason);
+                    }
+                };
+
+                Ok(CompletedPart::builder()
+                    .part_number(part_number as i32)
+                    .set_e_tag(upload_output.e_tag)
+                    .set_checksum_crc32_c(checksum_crc32c)
+                    .set_checksum_sha256(checksum_sha256)
+                    .build())
+            }
+        })
+        .buffer_unordered(PART_CONCURRENCY)
+        .collect().await;
+
+    let mut completed_parts = match part_results.into_iter().collect::<Result<Vec<CompletedPart>, String>>() {
+        Ok(parts) => parts,
+        Err(reason) => {
+            abort_upload(s3_client, context, key, &upload_id).await;
+            return Err(reason);
+        }
+    };
+    completed_parts.sort_by_key(|part| part.part_number);
+
+    let complete_result = retry::with_retries(context.max_retries, &context.run_stats, || {
+        s3_client.complete_multipart_upload()
+            .bucket(&context.bucket_name)
+            .key(key)
+            .upload_id(&upload_id)
+            .multipart_upload(CompletedMultipartUpload::builder().set_parts(Some(completed_parts.clone())).build())
+            .customize()
+            .config_override(retry::without_sdk_retries())
+            .send()
+    }).await;
+
+    if let Err(err) = complete_result {
+        abort_upload(s3_client, context, key, &upload_id).await;
+        return Err(retry::error_reason(&err));
+    }
+
+    let part_checksums: Vec<String> = completed_parts.iter()
+        .filter_map(|part| context.checksum.returned_value(part.checksum_crc32_c.clone(), part.checksum_sha256.clone()))
+        .collect();
+    Ok(context.checksum.compute_composite(&part_checksums))
+}
+
+async fn abort_upload(s3_client: &Client, context: &CreateObjectsContext, key: &str, upload_id: &str) {
+    let _abort_result = s3_client.abort_multipart_upload()
+        .bucket(&context.bucket_name)
+        .key(key)
+        .upload_id(upload_id)
+        .send().await;
+}
@@ -0,0 +1,33 @@
+use std::sync::OnceLock;
+
+// How results are written to stdout
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    // Colorized summaries and progress bars for people
+    #[default]
+    Text,
+    // A single JSON report per run for scripts and dashboards
+    Json,
+}
+
+static FORMAT: OnceLock<OutputFormat> = OnceLock::new();
+
+// Sets the output format for the rest of the process. Called once from main after parsing arguments.
+pub fn init(format: OutputFormat) {
+    FORMAT.set(format).expect("output format already initialized");
+}
+
+pub fn is_json() -> bool {
+    FORMAT.get().copied().unwrap_or_default() == OutputFormat::Json
+}
+
+// Prints decorative status output, which is suppressed in JSON mode so stdout stays parseable
+macro_rules! status {
+    ($($arg:tt)*) => {
+        if !$crate::output::is_json() {
+            println!($($arg)*);
+        }
+    };
+}
+
+pub(crate) use status;
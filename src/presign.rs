@@ -0,0 +1,313 @@
+use anyhow::Context;
+use aws_sdk_s3::{presigning::PresigningConfig, Client};
+use bytes::Bytes;
+use colorize::AnsiColor;
+use futures::future::join_all;
+use hyper::{client::HttpConnector, Body, Request};
+use hyper_rustls::HttpsConnector;
+use rand::{rngs::SmallRng, SeedableRng};
+use std::{sync::{atomic::{AtomicU64, Ordering}, Arc}, time::{Duration, Instant}};
+use tokio_util::sync::CancellationToken;
+
+use crate::cli::PresignArgs;
+use crate::interrupt;
+use crate::latency_log;
+use crate::output::status;
+use crate::retry::SdkContext;
+use crate::stats::RunStats;
+use crate::{format_duration, generate_payload, list_object_keys, new_progress_bar, parse_count, parse_duration, parse_size, prompt_worker_count, track_progress, validate_duration, validate_number, validate_size, PayloadMode};
+
+// Presigned URLs can't be valid for longer than a week
+const MAX_EXPIRY: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+// Error codes S3 returns when the signature in the URL is rejected rather than the request itself
+const SIGNATURE_CODES: [&str; 3] = ["SignatureDoesNotMatch", "AuthorizationQueryParametersError", "InvalidAccessKeyId"];
+
+// Request sent with each presigned URL
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum PresignMethod {
+    // Uploads a new object to each URL
+    Put,
+    // Downloads existing objects, wrapping around when more requests than objects are asked for
+    Get,
+}
+
+impl PresignMethod {
+    fn all() -> Vec<PresignMethod> {
+        vec![PresignMethod::Put, PresignMethod::Get]
+    }
+}
+
+impl std::fmt::Display for PresignMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            PresignMethod::Put => "PUT",
+            PresignMethod::Get => "GET",
+        };
+        write!(f, "{0}", name)
+    }
+}
+
+// A presigned request, with the headers that were signed alongside the URL
+struct PresignedUrl {
+    key: String,
+    uri: String,
+    headers: Vec<(String, String)>,
+}
+
+// How a request sent to a presigned URL ended, when it didn't succeed
+enum PresignedFailure {
+    Signature(String),
+    Expired,
+    Request(String),
+}
+
+// Settings and shared counters for every worker taking part in a presigned URL run
+struct PresignContext {
+    bucket_name: String,
+    method: PresignMethod,
+    urls: Vec<PresignedUrl>,
+    payload: Bytes,
+    http_client: hyper::Client<HttpsConnector<HttpConnector>>,
+    // Position of the next request, shared so workers pick up whichever URL is next
+    next_index: AtomicU64,
+    request_count: u64,
+    // Rejected signatures and expired URLs are counted apart from the run stats' request failures, since they
+    // point at the signing path rather than the service being overloaded
+    signature_errors: AtomicU64,
+    expired_count: AtomicU64,
+    cancel: CancellationToken,
+    completed_count: Arc<AtomicU64>,
+    run_stats: RunStats,
+}
+
+#[tracing::instrument(skip_all, fields(bucket = %bucket_name))]
+pub async fn operation_presigned_load(s3_client: Client, bucket_name: String, args: PresignArgs) -> anyhow::Result<()> {
+    let method = match args.method {
+        Some(method) => method,
+        None => inquire::Select::new("Send which requests to the presigned URLs?", PresignMethod::all()).prompt()?,
+    };
+
+    let request_count = match args.count {
+        Some(count) => count,
+        None => {
+            let request_count = inquire::Text::new("How many requests should I send?")
+                .with_default("1000")
+                .with_validator(validate_number)
+                .prompt()?;
+            parse_count(&request_count).context("Invalid request count")?
+        }
+    };
+
+    let object_size = match method {
+        PresignMethod::Get => 0,
+        PresignMethod::Put => match args.size {
+            Some(size) => size,
+            None => {
+                let object_size = inquire::Text::new("What size should each uploaded object be? (eg. 4KB, 1MB, 10MB)")
+                    .with_default("1KB")
+                    .with_validator(validate_size)
+                    .prompt()?;
+                parse_size(&object_size).context("Invalid object size")?
+            }
+        },
+    };
+
+    let expires_in = match args.expires_in {
+        Some(expires_in) => expires_in,
+        None => {
+            let expires_in = inquire::Text::new("How long should the presigned URLs stay valid? (eg. 15m, 1h)")
+                .with_default("15m")
+                .with_validator(validate_duration)
+                .prompt()?;
+            parse_duration(&expires_in).context("Invalid duration")?
+        }
+    };
+    if expires_in > MAX_EXPIRY {
+        anyhow::bail!("Presigned URLs can be valid for at most 7 days");
+    }
+
+    let worker_count = match args.concurrency {
+        Some(concurrency) => concurrency,
+        None => prompt_worker_count()?,
+    };
+
+    // Every URL is signed before the load starts, so the measured latency is only the HTTP request
+    let keys = match method {
+        PresignMethod::Put => (0..request_count).map(|_| format!("presigned/{0}", uuid::Uuid::new_v4())).collect(),
+        PresignMethod::Get => {
+            let mut keys = list_object_keys(&s3_client, &bucket_name).await?;
+            if keys.is_empty() {
+                status!("{0}", "No objects found in this bucket".blue());
+                return Ok(());
+            }
+            keys.truncate(request_count as usize);
+            keys
+        }
+    };
+    let urls = presign_urls(&s3_client, &bucket_name, method, keys, expires_in).await?;
+    status!("Signed {0} presigned {1} URLs, valid for {2}", urls.len(), method, format_duration(expires_in));
+
+    let https = hyper_rustls::HttpsConnectorBuilder::new()
+        .with_native_roots()
+        .https_or_http()
+        .enable_http1()
+        .build();
+
+    let operation = interrupt::start_operation();
+    let context = Arc::new(PresignContext {
+        bucket_name,
+        method,
+        urls,
+        payload: generate_payload(&mut SmallRng::from_entropy(), PayloadMode::Random, object_size),
+        http_client: hyper::Client::builder().build(https),
+        next_index: AtomicU64::new(0),
+        request_count: request_count as u64,
+        signature_errors: AtomicU64::new(0),
+        expired_count: AtomicU64::new(0),
+        cancel: operation.token(),
+        completed_count: Arc::new(AtomicU64::new(0)),
+        run_stats: RunStats::default(),
+    });
+
+    let progress = new_progress_bar(request_count as u64);
+    let progress_task = tokio::spawn(track_progress(progress.clone(), context.completed_count.clone(), vec![context.run_stats.byte_counter()]));
+
+    let start_time = Instant::now();
+
+    let mut join_handle_list = vec![];
+    for _ in 0..worker_count.min(request_count) {
+        join_handle_list.push(tokio::spawn(presigned_worker(context.clone())));
+    }
+    join_all(join_handle_list).await;
+    latency_log::flush();
+
+    progress_task.abort();
+    progress.finish_and_clear();
+    interrupt::report_if_interrupted(&context.cancel);
+    context.run_stats.summarize(start_time.elapsed())
+        .with_setting("Method", context.method)
+        .with_setting("URLs", context.urls.len())
+        .with_setting("Signature errs", context.signature_errors.load(Ordering::Relaxed))
+        .with_setting("Expired", context.expired_count.load(Ordering::Relaxed))
+        .print("Presigned URLs", &context.bucket_name);
+    Ok(())
+}
+
+async fn presign_urls(s3_client: &Client, bucket_name: &String, method: PresignMethod, keys: Vec<String>, expires_in: Duration) -> anyhow::Result<Vec<PresignedUrl>> {
+    let presigning_config = PresigningConfig::expires_in(expires_in)?;
+    let mut urls = Vec::with_capacity(keys.len());
+    for key in keys {
+        let error_context = format!("Unable to presign a URL for {0}", key);
+        let presigned = match method {
+            PresignMethod::Put => s3_client.put_object().bucket(bucket_name).key(&key)
+                .presigned(presigning_config.clone()).await.sdk_context(&error_context)?,
+            PresignMethod::Get => s3_client.get_object().bucket(bucket_name).key(&key)
+                .presigned(presigning_config.clone()).await.sdk_context(&error_context)?,
+        };
+
+        urls.push(PresignedUrl {
+            uri: presigned.uri().to_string(),
+            headers: presigned.headers().map(|(name, value)| (name.to_string(), value.to_string())).collect(),
+            key,
+        });
+    }
+    Ok(urls)
+}
+
+async fn presigned_worker(context: Arc<PresignContext>) {
+    loop {
+        if context.cancel.is_cancelled() {
+            break;
+        }
+        let index = context.next_index.fetch_add(1, Ordering::Relaxed);
+        if index >= context.request_count {
+            break;
+        }
+        let url = &context.urls[index as usize % context.urls.len()];
+
+        let request_start = Instant::now();
+        let result = send_presigned(&context, url).await;
+        let latency = request_start.elapsed();
+        match result {
+            Ok(bytes) => {
+                latency_log::record("presigned", &url.key, bytes, latency, Ok(()));
+                context.run_stats.record(latency, bytes);
+            }
+            Err(failure) => {
+                let reason = match failure {
+                    PresignedFailure::Signature(code) => {
+                        context.signature_errors.fetch_add(1, Ordering::Relaxed);
+                        code
+                    }
+                    PresignedFailure::Expired => {
+                        context.expired_count.fetch_add(1, Ordering::Relaxed);
+                        "Expired".to_string()
+                    }
+                    PresignedFailure::Request(reason) => {
+                        context.run_stats.record_failure(reason.clone());
+                        reason
+                    }
+                };
+                latency_log::record("presigned", &url.key, 0, latency, Err(&reason));
+            }
+        }
+        context.completed_count.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+// Sends a single request to a presigned URL, reading the whole response body. Returns the number of
+// bytes transferred.
+async fn send_presigned(context: &PresignContext, url: &PresignedUrl) -> Result<u64, PresignedFailure> {
+    let mut request = Request::builder().uri(&url.uri);
+    for (name, value) in &url.headers {
+        request = request.header(name, value);
+    }
+    let request = match context.method {
+        PresignMethod::Put => request.method("PUT").body(Body::from(context.payload.clone())),
+        PresignMethod::Get => request.method("GET").body(Body::empty()),
+    }.map_err(|err| PresignedFailure::Request(format!("Invalid request: {0}", err)))?;
+
+    let response = context.http_client.request(request).await
+        .map_err(|err| PresignedFailure::Request(match err.is_connect() {
+            true => "Connection failure".to_string(),
+            false => format!("HTTP error: {0}", err),
+        }))?;
+    let status = response.status();
+    let body = hyper::body::to_bytes(response.into_body()).await
+        .map_err(|err| PresignedFailure::Request(format!("Body read failed: {0}", err)))?;
+
+    if status.is_success() {
+        return Ok(match context.method {
+            PresignMethod::Put => context.payload.len() as u64,
+            PresignMethod::Get => body.len() as u64,
+        });
+    }
+    Err(classify_failure(status.as_u16(), &String::from_utf8_lossy(&body)))
+}
+
+// S3 reports an expired URL as AccessDenied with a "Request has expired" message, so the message is
+// checked as well as the error code
+fn classify_failure(status: u16, body: &str) -> PresignedFailure {
+    let code = xml_element(body, "Code").unwrap_or_default();
+    let message = xml_element(body, "Message").unwrap_or_default();
+    if message.contains("expired") {
+        return PresignedFailure::Expired;
+    }
+    if SIGNATURE_CODES.contains(&code) {
+        return PresignedFailure::Signature(code.to_string());
+    }
+    match (code.is_empty(), message.is_empty()) {
+        (true, _) => PresignedFailure::Request(format!("HTTP {0}", status)),
+        (false, true) => PresignedFailure::Request(code.to_string()),
+        (false, false) => PresignedFailure::Request(format!("{0}: {1}", code, message)),
+    }
+}
+
+// Error bodies are small, flat XML documents, so the element text is found without a full parser
+fn xml_element<'a>(body: &'a str, name: &str) -> Option<&'a str> {
+    let start_tag = format!("<{0}>", name);
+    let start = body.find(&start_tag)? + start_tag.len();
+    let end = body[start..].find(&format!("</{0}>", name))? + start;
+    Some(&body[start..end])
+}
@@ -0,0 +1,21 @@
+use std::time::Duration;
+use tokio::{sync::Mutex, time::{Interval, MissedTickBehavior}};
+
+// Paces requests from any number of workers so their combined rate approximates a target.
+// Each call to acquire() waits for the next tick of a single shared interval.
+pub struct RateLimiter {
+    interval: Mutex<Interval>,
+}
+
+impl RateLimiter {
+    pub fn new(requests_per_second: u32) -> RateLimiter {
+        let mut interval = tokio::time::interval(Duration::from_secs_f64(1.0 / requests_per_second as f64));
+        // Don't burst to catch up after a stall, otherwise the offered load would spike above the target
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        RateLimiter { interval: Mutex::new(interval) }
+    }
+
+    pub async fn acquire(&self) {
+        self.interval.lock().await.tick().await;
+    }
+}
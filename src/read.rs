@@ -0,0 +1,202 @@
+use anyhow::Context;
+use aws_sdk_s3::Client;
+use colorize::AnsiColor;
+use futures::future::join_all;
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+use std::{collections::BTreeSet, sync::{atomic::{AtomicU64, Ordering}, Arc, Mutex}, time::Instant};
+
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
+
+use crate::cli::ReadObjectsArgs;
+use crate::interrupt;
+use crate::latency_log;
+use crate::output::status;
+use crate::rate_limit::RateLimiter;
+use crate::stats::RunStats;
+use crate::{list_object_keys, new_progress_bar, parse_count, prompt_worker_count, read_keys_file, report_missing_keys, retry, split_object_count, track_progress, validate_number, validate_optional_number};
+
+// Order in which read workers walk through the bucket's keys
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum ReadOrder {
+    // Each key in listing order, wrapping around when more reads than keys are requested
+    Sequential,
+    // Keys sampled uniformly at random, so popular keys may be read more than once
+    Random,
+    // Every read goes to the first key, to measure how a single hot object holds up under load
+    Repeat,
+}
+
+impl ReadOrder {
+    fn all() -> Vec<ReadOrder> {
+        vec![ReadOrder::Sequential, ReadOrder::Random, ReadOrder::Repeat]
+    }
+}
+
+impl std::fmt::Display for ReadOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ReadOrder::Sequential => "Sequential",
+            ReadOrder::Random => "Random",
+            ReadOrder::Repeat => "Repeat a single key",
+        };
+        write!(f, "{0}", name)
+    }
+}
+
+// Settings and shared counters for every worker taking part in a read objects run
+struct ReadObjectsContext {
+    bucket_name: String,
+    keys: Vec<String>,
+    read_order: ReadOrder,
+    // Position of the next key to read in sequential mode
+    next_index: AtomicU64,
+    rate_limiter: Option<RateLimiter>,
+    // Keys that returned NoSuchKey, which for keys from a file usually means the file is out of date
+    missing_keys: Mutex<BTreeSet<String>>,
+    cancel: CancellationToken,
+    completed_count: Arc<AtomicU64>,
+    run_stats: RunStats,
+}
+
+#[tracing::instrument(skip_all, fields(bucket = %bucket_name))]
+pub async fn operation_read_objects(s3_client: Client, bucket_name: String, args: ReadObjectsArgs) -> anyhow::Result<()> {
+    let keys = match &args.keys_file {
+        Some(keys_file) => read_keys_file(keys_file)?,
+        None => list_object_keys(&s3_client, &bucket_name).await?,
+    };
+    if keys.is_empty() {
+        status!("{0}", "No objects found in this bucket".blue());
+        return Ok(());
+    }
+    status!("Found {0} objects to read", keys.len());
+
+    let read_count = match args.count {
+        Some(count) => count,
+        None => {
+            let read_count = inquire::Text::new("How many objects should I read?")
+                .with_default(&keys.len().to_string())
+                .with_validator(validate_number)
+                .prompt()?;
+            parse_count(&read_count).context("Invalid read count")?
+        }
+    };
+
+    let worker_count = match args.concurrency {
+        Some(concurrency) => concurrency,
+        None => prompt_worker_count()?,
+    };
+
+    let read_order = match args.order {
+        Some(order) => order,
+        None => inquire::Select::new("Read keys in which order?", ReadOrder::all()).prompt()?,
+    };
+
+    let requests_per_second = match args.rate {
+        Some(rate) => rate,
+        None => {
+            let requests_per_second = inquire::Text::new("Target requests per second (leave blank for unlimited)")
+                .with_validator(validate_optional_number)
+                .prompt()?;
+            parse_count(&requests_per_second).unwrap_or_default()
+        }
+    };
+
+    let operation = interrupt::start_operation();
+    let context = Arc::new(ReadObjectsContext {
+        bucket_name,
+        keys,
+        read_order,
+        rate_limiter: Some(requests_per_second).filter(|rps| *rps > 0).map(RateLimiter::new),
+        next_index: AtomicU64::new(0),
+        missing_keys: Mutex::new(BTreeSet::new()),
+        cancel: operation.token(),
+        completed_count: Arc::new(AtomicU64::new(0)),
+        run_stats: RunStats::default(),
+    });
+
+    let progress = new_progress_bar(read_count as u64);
+    let progress_task = tokio::spawn(track_progress(progress.clone(), context.completed_count.clone(), vec![context.run_stats.byte_counter()]));
+
+    let start_time = Instant::now();
+
+    let mut join_handle_list = vec![];
+    for worker_read_count in split_object_count(read_count, worker_count) {
+        join_handle_list.push(tokio::spawn(read_object(s3_client.clone(), context.clone(), worker_read_count)));
+    }
+    join_all(join_handle_list).await;
+    latency_log::flush();
+
+    progress_task.abort();
+    progress.finish_and_clear();
+    interrupt::report_if_interrupted(&context.cancel);
+    let mut summary = context.run_stats.summarize(start_time.elapsed())
+        .with_setting("Order", context.read_order);
+    if requests_per_second > 0 {
+        summary = summary.with_setting("Target rate", format!("{0} requests/sec", requests_per_second));
+    }
+    if context.read_order == ReadOrder::Repeat {
+        summary = summary.with_setting("Key", &context.keys[0]);
+    }
+    if let Some(keys_file) = &args.keys_file {
+        summary = summary.with_setting("Keys file", keys_file.display());
+    }
+    summary.print("Read objects", &context.bucket_name);
+    if args.keys_file.is_some() {
+        report_missing_keys(&context.missing_keys.lock().unwrap());
+    }
+    Ok(())
+}
+
+async fn read_object(s3_client: Client, context: Arc<ReadObjectsContext>, read_count: u32) {
+    let mut rng = SmallRng::from_entropy();
+
+    for _ in 1..=read_count {
+        if context.cancel.is_cancelled() {
+            break;
+        }
+        let index = match context.read_order {
+            ReadOrder::Sequential => context.next_index.fetch_add(1, Ordering::Relaxed) as usize % context.keys.len(),
+            ReadOrder::Random => rng.gen_range(0..context.keys.len()),
+            ReadOrder::Repeat => 0,
+        };
+
+        if let Some(rate_limiter) = &context.rate_limiter {
+            rate_limiter.acquire().await;
+        }
+
+        let key = &context.keys[index];
+        let request_start = Instant::now();
+        let get_result = s3_client.get_object()
+            .bucket(&context.bucket_name)
+            .key(key)
+            .send()
+            .instrument(tracing::debug_span!("get_object", bucket = %context.bucket_name, key = %key))
+            .await;
+
+        // The download isn't finished until the whole body has been read off the wire
+        let read_result = match get_result {
+            Ok(output) => output.body.collect().await
+                .map(|body| body.into_bytes().len() as u64)
+                .map_err(|err| format!("Body read failed: {0}", err)),
+            Err(err) => {
+                if err.as_service_error().is_some_and(|service_err| service_err.is_no_such_key()) {
+                    context.missing_keys.lock().unwrap().insert(key.clone());
+                }
+                Err(retry::error_reason(&err))
+            }
+        };
+        let latency = request_start.elapsed();
+        match read_result {
+            Ok(size) => {
+                latency_log::record("get", key, size, latency, Ok(()));
+                context.run_stats.record(latency, size);
+            }
+            Err(reason) => {
+                latency_log::record("get", key, 0, latency, Err(&reason));
+                context.run_stats.record_failure(reason);
+            }
+        }
+        context.completed_count.fetch_add(1, Ordering::Relaxed);
+    }
+}
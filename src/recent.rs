@@ -0,0 +1,114 @@
+use colorize::AnsiColor;
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, sync::{Mutex, OnceLock}};
+
+// Buckets kept in the recent list, most recently used first
+const MAX_RECENT_BUCKETS: usize = 10;
+
+// Values typed at the prompts in earlier runs, offered as the prompt defaults next time. Kept in
+// s3-stress/recent.json under the user's config directory.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct RecentSettings {
+    buckets: Vec<String>,
+    region: Option<String>,
+    object_count: Option<String>,
+    object_size: Option<String>,
+    concurrency: Option<String>,
+}
+
+// A prompt whose last answer is remembered
+#[derive(Clone, Copy, Debug)]
+pub enum Setting {
+    Region,
+    ObjectCount,
+    ObjectSize,
+    Concurrency,
+}
+
+impl RecentSettings {
+    fn value(&mut self, setting: Setting) -> &mut Option<String> {
+        match setting {
+            Setting::Region => &mut self.region,
+            Setting::ObjectCount => &mut self.object_count,
+            Setting::ObjectSize => &mut self.object_size,
+            Setting::Concurrency => &mut self.concurrency,
+        }
+    }
+}
+
+struct RecentState {
+    settings: RecentSettings,
+    // Cleared by --no-save, so CI runs read the defaults without leaving state behind
+    save: bool,
+}
+
+static RECENT: OnceLock<Mutex<RecentState>> = OnceLock::new();
+
+// $XDG_CONFIG_HOME or ~/.config, and %APPDATA% on Windows. None when no home directory can be found.
+fn state_path() -> Option<PathBuf> {
+    let config_dir = match cfg!(windows) {
+        true => std::env::var_os("APPDATA").map(PathBuf::from),
+        false => std::env::var_os("XDG_CONFIG_HOME").map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config"))),
+    };
+    config_dir.map(|config_dir| config_dir.join("s3-stress").join("recent.json"))
+}
+
+// Loads the values saved by earlier runs. A missing file is normal, and one that can't be read only
+// loses the defaults, so it's warned about rather than stopping the run.
+pub fn init(save: bool) {
+    let settings = match state_path().filter(|path| path.exists()) {
+        None => RecentSettings::default(),
+        Some(path) => match std::fs::read_to_string(&path).map_err(anyhow::Error::from)
+            .and_then(|contents| Ok(serde_json::from_str(&contents)?)) {
+            Ok(settings) => settings,
+            Err(err) => {
+                eprintln!("{0}", format!("Ignoring recent settings file {0}: {1}", path.display(), err).yellow());
+                RecentSettings::default()
+            }
+        },
+    };
+    RECENT.set(Mutex::new(RecentState { settings, save })).ok();
+}
+
+fn with_settings<T>(f: impl FnOnce(&mut RecentSettings) -> T) -> Option<T> {
+    RECENT.get().map(|recent| f(&mut recent.lock().unwrap().settings))
+}
+
+pub fn get(setting: Setting) -> Option<String> {
+    with_settings(|settings| settings.value(setting).clone()).flatten()
+}
+
+pub fn remember(setting: Setting, value: &str) {
+    with_settings(|settings| *settings.value(setting) = Some(value.to_string()));
+}
+
+pub fn last_bucket() -> Option<String> {
+    with_settings(|settings| settings.buckets.first().cloned()).flatten()
+}
+
+pub fn remember_bucket(bucket_name: &str) {
+    with_settings(|settings| {
+        settings.buckets.retain(|bucket| bucket != bucket_name);
+        settings.buckets.insert(0, bucket_name.to_string());
+        settings.buckets.truncate(MAX_RECENT_BUCKETS);
+    });
+}
+
+// Writes the remembered values back, called after each operation. Failing to save is only warned about,
+// since the operation itself has already finished.
+pub fn save() {
+    let Some(recent) = RECENT.get() else {
+        return;
+    };
+    let recent = recent.lock().unwrap();
+    let Some(path) = state_path().filter(|_| recent.save) else {
+        return;
+    };
+    let write_result = path.parent().map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| std::fs::write(&path, serde_json::to_string_pretty(&recent.settings).unwrap()));
+    if let Err(err) = write_result {
+        eprintln!("{0}", format!("Unable to save recent settings to {0}: {1}", path.display(), err).yellow());
+    }
+}
@@ -0,0 +1,62 @@
+use aws_config::Region;
+use aws_sdk_s3::Client;
+use aws_types::SdkConfig;
+use colorize::AnsiColor;
+use futures::future::join_all;
+use std::{collections::HashMap, sync::Arc};
+
+use crate::output::status;
+use crate::stats;
+use crate::{client_region, lookup_bucket_location, new_s3_client, with_acceleration, ConnectionOptions};
+
+// Clients for each region buckets have been found in, so buckets that share a region also share a client
+pub struct RegionClients {
+    // Used for the location lookups, and for any bucket in the active region
+    shared_client: Arc<Client>,
+    aws_cfg: Arc<SdkConfig>,
+    connection: Arc<ConnectionOptions>,
+    clients: HashMap<String, Client>,
+}
+
+impl RegionClients {
+    pub fn new(shared_client: Arc<Client>, aws_cfg: Arc<SdkConfig>, connection: Arc<ConnectionOptions>) -> RegionClients {
+        RegionClients { shared_client, aws_cfg, connection, clients: HashMap::new() }
+    }
+
+    // Returns the client for a region, building it the first time the region is asked for
+    pub fn client_for_region(&mut self, region: &str) -> Client {
+        if let Some(client) = self.clients.get(region) {
+            return client.clone();
+        }
+        let region_cfg = self.aws_cfg.as_ref().clone().into_builder()
+            .region(Region::new(region.to_string())).build();
+        let client = new_s3_client(&region_cfg, &self.connection);
+        self.clients.insert(region.to_string(), client.clone());
+        client
+    }
+
+    // Looks up every bucket's location at once, then returns a client for each bucket in the same order.
+    // Region switches are confirmed one bucket at a time afterwards, so prompts aren't interleaved.
+    pub async fn clients_for_buckets(&mut self, bucket_names: &[String]) -> anyhow::Result<Vec<Client>> {
+        let locations = join_all(bucket_names.iter()
+            .map(|bucket_name| lookup_bucket_location(&self.shared_client, &self.connection, bucket_name))).await;
+
+        let mut clients = Vec::with_capacity(bucket_names.len());
+        for (bucket_name, location) in bucket_names.iter().zip(locations) {
+            let Some(location) = location? else {
+                stats::set_bucket_region(bucket_name, &self.shared_client);
+                clients.push(with_acceleration(self.shared_client.as_ref().clone(), &self.connection, bucket_name).await);
+                continue;
+            };
+            status!("Bucket {0} location: {1}", bucket_name, location.clone().green());
+            let client = match client_region(&self.aws_cfg, &self.connection, bucket_name, location)? {
+                Some(region) => self.client_for_region(&region),
+                None => self.shared_client.as_ref().clone(),
+            };
+            stats::set_bucket_region(bucket_name, &client);
+            // Acceleration is enabled per bucket, so it's applied after the regional client is shared
+            clients.push(with_acceleration(client, &self.connection, bucket_name).await);
+        }
+        Ok(clients)
+    }
+}
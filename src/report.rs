@@ -0,0 +1,68 @@
+use anyhow::Context;
+use colorize::AnsiColor;
+use std::{fs::File, io::{BufWriter, Write}, path::Path, sync::{Mutex, OnceLock}};
+
+use crate::stats::RunReport;
+
+// Layout of the file given with --report-out
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ReportFormat {
+    // One JSON object per line, in the same form as --output json
+    #[default]
+    Json,
+    // A header row followed by one row per run, for spreadsheets
+    Csv,
+}
+
+struct ReportFile {
+    format: ReportFormat,
+    writer: BufWriter<File>,
+}
+
+// Report file given with --report-out, which every run summary is appended to. Unset unless the option was given.
+static REPORT: OnceLock<Mutex<ReportFile>> = OnceLock::new();
+
+const CSV_HEADER: &str = "operation,bucket,region,succeeded,failed,success_rate_percent,requests,retries,total_bytes,elapsed_seconds,objects_per_second,megabytes_per_second,min_ms,mean_ms,p50_ms,p90_ms,p95_ms,p99_ms,max_ms";
+
+pub fn init(path: &Path, format: ReportFormat) -> anyhow::Result<()> {
+    let file = File::create(path).with_context(|| format!("Unable to create report file {0}", path.display()))?;
+    let mut writer = BufWriter::new(file);
+    if format == ReportFormat::Csv {
+        writeln!(writer, "{0}", CSV_HEADER)?;
+        writer.flush()?;
+    }
+    REPORT.set(Mutex::new(ReportFile { format, writer })).map_err(|_| anyhow::anyhow!("report file already initialized"))
+}
+
+// Appends a finished run to the report file, flushing straight away so the file stays complete if a
+// later run is cut short. A failed write is only warned about, since the run itself has already succeeded.
+pub fn write(report: &RunReport) {
+    let Some(report_file) = REPORT.get() else {
+        return;
+    };
+    let mut report_file = report_file.lock().unwrap();
+    let line = match report_file.format {
+        ReportFormat::Json => serde_json::to_string(report).unwrap(),
+        ReportFormat::Csv => csv_row(report),
+    };
+    if let Err(err) = writeln!(report_file.writer, "{0}", line).and_then(|_| report_file.writer.flush()) {
+        eprintln!("{0}", format!("Unable to write to the report file: {0}", err).yellow());
+    }
+}
+
+fn csv_row(report: &RunReport) -> String {
+    let latency = &report.latency_ms;
+    format!("{0},{1},{2},{3},{4},{5:.2},{6},{7},{8},{9:.3},{10:.2},{11:.2},{12:.3},{13:.3},{14:.3},{15:.3},{16:.3},{17:.3},{18:.3}",
+        csv_field(&report.operation), csv_field(&report.bucket), csv_field(report.region.as_deref().unwrap_or_default()),
+        report.succeeded, report.failed, report.success_rate_percent, report.requests, report.retries, report.total_bytes,
+        report.elapsed_seconds, report.objects_per_second, report.megabytes_per_second,
+        latency.min, latency.mean, latency.p50, latency.p90, latency.p95, latency.p99, latency.max)
+}
+
+// Operation and bucket names don't normally need quoting, but S3-compatible backends allow more in bucket names
+fn csv_field(value: &str) -> String {
+    match value.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{0}\"", value.replace('"', "\"\"")),
+        false => value.to_string(),
+    }
+}
@@ -0,0 +1,364 @@
+use aws_sdk_s3::{config::{http::HttpResponse, retry::RetryConfig}, error::{ProvideErrorMetadata, SdkError}};
+use rand::Rng;
+use std::{future::Future, sync::atomic::{AtomicU32, AtomicU64, Ordering}, time::Duration};
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+use crate::failure::{ErrorKind, Failure};
+use crate::stats::RunStats;
+
+const BASE_DELAY: Duration = Duration::from_millis(100);
+const MAX_DELAY: Duration = Duration::from_secs(20);
+
+// Most retries --retries accepts. With the backoff capped at MAX_DELAY, that's already minutes per request.
+pub const MAX_RETRIES: u32 = 20;
+
+// Error codes S3 uses to ask clients to slow down or try again later
+const RETRYABLE_CODES: [&str; 6] = ["SlowDown", "Throttling", "ThrottlingException", "RequestTimeout", "InternalError", "ServiceUnavailable"];
+
+// The subset of those codes that mean the request rate itself is too high
+const THROTTLING_CODES: [&str; 3] = ["SlowDown", "Throttling", "ThrottlingException"];
+
+// Error codes for credentials S3 doesn't accept, as opposed to valid credentials without permission
+const AUTH_CODES: [&str; 5] = ["ExpiredToken", "InvalidAccessKeyId", "InvalidToken", "SignatureDoesNotMatch", "TokenRefreshRequired"];
+
+// Successful requests in a row needed before an adaptive limit allows one more request in flight
+const RECOVER_AFTER: u32 = 20;
+
+// Throttling, server-side (5xx) errors, timeouts and connection failures are worth retrying.
+// Anything else, such as AccessDenied or NoSuchBucket, will fail the same way on every attempt.
+pub fn is_retryable<E: ProvideErrorMetadata>(err: &SdkError<E, HttpResponse>) -> bool {
+    match err {
+        SdkError::TimeoutError(_) | SdkError::DispatchFailure(_) | SdkError::ResponseError(_) => true,
+        SdkError::ServiceError(context) => {
+            let status = context.raw().status().as_u16();
+            status >= 500 || status == 429 || RETRYABLE_CODES.contains(&context.err().code().unwrap_or_default())
+        }
+        _ => false,
+    }
+}
+
+// For errors reported without an HTTP status, such as the per-key errors in a DeleteObjects response
+pub fn is_retryable_code(code: &str) -> bool {
+    RETRYABLE_CODES.contains(&code)
+}
+
+// Throttling responses, as opposed to other retryable failures, which say nothing about the request rate
+pub fn is_throttling<E: ProvideErrorMetadata>(err: &SdkError<E, HttpResponse>) -> bool {
+    match err {
+        SdkError::ServiceError(context) => {
+            let status = context.raw().status().as_u16();
+            status == 503 || status == 429 || is_throttling_code(context.err().code().unwrap_or_default())
+        }
+        _ => false,
+    }
+}
+
+pub fn is_throttling_code(code: &str) -> bool {
+    THROTTLING_CODES.contains(&code)
+}
+
+// Sorts a failed request into the cause reported by the exit code. The SDK reports credentials it
+// couldn't load as a dispatch failure, which is told apart from a connection failure by not being an IO
+// error or a timeout.
+pub fn error_kind<E: ProvideErrorMetadata>(err: &SdkError<E, HttpResponse>) -> ErrorKind {
+    match err {
+        SdkError::TimeoutError(_) | SdkError::ResponseError(_) => ErrorKind::Network,
+        SdkError::DispatchFailure(failure) if failure.is_io() || failure.is_timeout() => ErrorKind::Network,
+        SdkError::DispatchFailure(_) => ErrorKind::Auth,
+        SdkError::ServiceError(context) => {
+            let code = context.err().code().unwrap_or_default();
+            match context.raw().status().as_u16() {
+                _ if AUTH_CODES.contains(&code) => ErrorKind::Auth,
+                _ if is_throttling(err) => ErrorKind::Throttling,
+                404 => ErrorKind::NotFound,
+                403 => ErrorKind::Permission,
+                _ => ErrorKind::Other,
+            }
+        }
+        _ => ErrorKind::Other,
+    }
+}
+
+// Caps the number of requests in flight, halving the cap whenever S3 throttles and raising it by one
+// again after a run of successful requests, down to a minimum of one
+pub struct AdaptiveLimit {
+    permits: Semaphore,
+    max_permits: u32,
+    current_permits: AtomicU32,
+    success_streak: AtomicU32,
+    throttled_count: AtomicU64,
+}
+
+impl AdaptiveLimit {
+    pub fn new(max_permits: u32) -> AdaptiveLimit {
+        AdaptiveLimit {
+            permits: Semaphore::new(max_permits as usize),
+            max_permits,
+            current_permits: AtomicU32::new(max_permits),
+            success_streak: AtomicU32::new(0),
+            throttled_count: AtomicU64::new(0),
+        }
+    }
+
+    pub async fn acquire(&self) -> SemaphorePermit<'_> {
+        self.permits.acquire().await.expect("adaptive limit semaphore is never closed")
+    }
+
+    // Releases the permit of a request that went through without being throttled
+    pub fn succeeded(&self, permit: SemaphorePermit<'_>) {
+        drop(permit);
+        if self.success_streak.fetch_add(1, Ordering::Relaxed) + 1 < RECOVER_AFTER {
+            return;
+        }
+        self.success_streak.store(0, Ordering::Relaxed);
+        let raised = self.current_permits.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |current| (current < self.max_permits).then_some(current + 1));
+        if raised.is_ok() {
+            self.permits.add_permits(1);
+        }
+    }
+
+    // Releases the permit of a throttled request and lowers the cap. The held permit is forgotten rather
+    // than returned, and any others that are free are forgotten too, so the cap takes effect immediately.
+    pub fn throttled(&self, permit: SemaphorePermit<'_>) {
+        self.throttled_count.fetch_add(1, Ordering::Relaxed);
+        self.success_streak.store(0, Ordering::Relaxed);
+        let Ok(previous) = self.current_permits.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |current| (current > 1).then_some((current / 2).max(1))) else {
+            return;
+        };
+        let reduction = previous - (previous / 2).max(1);
+        permit.forget();
+        let forgotten = 1 + self.permits.forget_permits(reduction as usize - 1) as u32;
+        // Permits still held by other requests can't be taken back, so the rest are returned to the count
+        // and the cap is reached once the next requests are throttled too
+        self.current_permits.fetch_add(reduction - forgotten, Ordering::Relaxed);
+    }
+
+    pub fn current(&self) -> u32 {
+        self.current_permits.load(Ordering::Relaxed)
+    }
+
+    pub fn throttled_count(&self) -> u64 {
+        self.throttled_count.load(Ordering::Relaxed)
+    }
+}
+
+// Exponential backoff with full jitter, so retrying workers don't hammer the endpoint in lockstep
+pub fn backoff_delay(attempt: u32) -> Duration {
+    let ceiling = BASE_DELAY.saturating_mul(2u32.saturating_pow(attempt)).min(MAX_DELAY);
+    ceiling.mul_f64(rand::thread_rng().gen_range(0.0..=1.0))
+}
+
+// Short, human-readable reason for a failed request, used to group failures in the run summary
+pub fn error_reason<E: ProvideErrorMetadata>(err: &SdkError<E, HttpResponse>) -> String {
+    match err {
+        SdkError::TimeoutError(_) => "Timeout".to_string(),
+        SdkError::DispatchFailure(_) => "Connection failure".to_string(),
+        SdkError::ResponseError(_) => "Invalid response".to_string(),
+        SdkError::ServiceError(context) => {
+            let service_err = context.err();
+            match (service_err.code(), service_err.message()) {
+                (Some(code), Some(message)) => format!("{0}: {1}", code, message),
+                (Some(code), None) => code.to_string(),
+                (None, _) => format!("HTTP {0}", context.raw().status().as_u16()),
+            }
+        }
+        _ => err.to_string(),
+    }
+}
+
+// ID S3 assigns to each request, which AWS support asks for when looking into a failure. Only
+// requests that got a response have one.
+pub fn request_id<E>(err: &SdkError<E, HttpResponse>) -> Option<&str> {
+    let response = match err {
+        SdkError::ServiceError(context) => context.raw(),
+        SdkError::ResponseError(context) => context.raw(),
+        _ => return None,
+    };
+    response.headers().get("x-amz-request-id")
+}
+
+// Config override for requests sent through with_retries. Left on, the SDK's own retries would multiply
+// the attempts and hide them from the run stats, so those requests only get the retries counted here.
+pub fn without_sdk_retries() -> aws_sdk_s3::config::Builder {
+    aws_sdk_s3::config::Builder::default().retry_config(RetryConfig::disabled())
+}
+
+// Runs an S3 request, retrying retryable failures with backoff up to max_retries times.
+// The operation is called again for each attempt since request builders and bodies are consumed by send().
+pub async fn with_retries<T, E, F, Fut>(max_retries: u32, run_stats: &RunStats, mut operation: F) -> Result<T, SdkError<E, HttpResponse>>
+where
+    E: ProvideErrorMetadata,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, SdkError<E, HttpResponse>>>,
+{
+    let mut attempt = 0;
+    loop {
+        match operation().await {
+            Err(err) if attempt < max_retries && is_retryable(&err) => {
+                attempt += 1;
+                tracing::debug!(attempt, reason = %error_reason(&err), request_id = request_id(&err), "retrying request");
+                run_stats.record_retry();
+                tokio::time::sleep(backoff_delay(attempt)).await;
+            }
+            Err(err) => {
+                tracing::debug!(attempts = attempt + 1, reason = %error_reason(&err), request_id = request_id(&err), "request failed");
+                return Err(err);
+            }
+            result => return result,
+        }
+    }
+}
+
+// Attaches a short description of what was being attempted to a failed S3 request, using the
+// service's error code and message rather than the SDK's generic "service error" text
+pub trait SdkContext<T> {
+    fn sdk_context(self, context: &str) -> anyhow::Result<T>;
+}
+
+impl<T, E: ProvideErrorMetadata> SdkContext<T> for Result<T, SdkError<E, HttpResponse>> {
+    fn sdk_context(self, context: &str) -> anyhow::Result<T> {
+        self.map_err(|err| {
+            tracing::error!(reason = %error_reason(&err), request_id = request_id(&err), "{0}", context);
+            Failure::new(error_kind(&err), format!("{0}: {1}", context, error_reason(&err))).into()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_sdk_s3::{error::ErrorMetadata, operation::put_object::PutObjectError};
+    use aws_smithy_runtime_api::http::StatusCode;
+    use aws_smithy_types::body::SdkBody;
+
+    fn service_error(status: u16, code: Option<&str>) -> SdkError<PutObjectError, HttpResponse> {
+        let mut metadata = ErrorMetadata::builder();
+        if let Some(code) = code {
+            metadata = metadata.code(code);
+        }
+        let raw = HttpResponse::new(StatusCode::try_from(status).unwrap(), SdkBody::empty());
+        SdkError::service_error(PutObjectError::generic(metadata.build()), raw)
+    }
+
+    #[test]
+    fn service_errors_are_classified_by_status_and_code() {
+        let cases = [
+            // status, code, retryable, throttling, kind
+            (503, Some("SlowDown"), true, true, ErrorKind::Throttling),
+            (400, Some("SlowDown"), true, true, ErrorKind::Throttling),
+            (503, None, true, true, ErrorKind::Throttling),
+            (429, None, true, true, ErrorKind::Throttling),
+            (500, None, true, false, ErrorKind::Other),
+            (500, Some("InternalError"), true, false, ErrorKind::Other),
+            (400, Some("RequestTimeout"), true, false, ErrorKind::Other),
+            (403, Some("AccessDenied"), false, false, ErrorKind::Permission),
+            (403, Some("InvalidAccessKeyId"), false, false, ErrorKind::Auth),
+            (400, Some("ExpiredToken"), false, false, ErrorKind::Auth),
+            (404, Some("NoSuchBucket"), false, false, ErrorKind::NotFound),
+            (400, Some("InvalidArgument"), false, false, ErrorKind::Other),
+        ];
+        for (status, code, retryable, throttling, kind) in cases {
+            let err = service_error(status, code);
+            assert_eq!(is_retryable(&err), retryable, "is_retryable for {status} {code:?}");
+            assert_eq!(is_throttling(&err), throttling, "is_throttling for {status} {code:?}");
+            assert_eq!(error_kind(&err), kind, "error_kind for {status} {code:?}");
+        }
+    }
+
+    #[test]
+    fn timeouts_are_retryable_network_errors() {
+        let err: SdkError<PutObjectError, HttpResponse> = SdkError::timeout_error("timed out");
+        assert!(is_retryable(&err));
+        assert!(!is_throttling(&err));
+        assert_eq!(error_kind(&err), ErrorKind::Network);
+    }
+
+    #[test]
+    fn codes_are_classified_without_a_status() {
+        let cases = [
+            // code, retryable, throttling
+            ("SlowDown", true, true),
+            ("Throttling", true, true),
+            ("ThrottlingException", true, true),
+            ("ServiceUnavailable", true, false),
+            ("InternalError", true, false),
+            ("AccessDenied", false, false),
+            ("NoSuchBucket", false, false),
+            ("", false, false),
+        ];
+        for (code, retryable, throttling) in cases {
+            assert_eq!(is_retryable_code(code), retryable, "is_retryable_code for {code:?}");
+            assert_eq!(is_throttling_code(code), throttling, "is_throttling_code for {code:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn adaptive_limit_halves_on_throttling_down_to_one() {
+        let limit = AdaptiveLimit::new(8);
+        for expected in [4, 2, 1, 1, 1] {
+            limit.throttled(limit.acquire().await);
+            assert_eq!(limit.current(), expected);
+            assert_eq!(limit.permits.available_permits(), expected as usize);
+        }
+        assert_eq!(limit.throttled_count(), 5);
+    }
+
+    #[tokio::test]
+    async fn adaptive_limit_recovers_after_successes_up_to_the_maximum() {
+        let limit = AdaptiveLimit::new(4);
+        limit.throttled(limit.acquire().await);
+        limit.throttled(limit.acquire().await);
+        assert_eq!(limit.current(), 1);
+        for _ in 0..RECOVER_AFTER * 10 {
+            limit.succeeded(limit.acquire().await);
+            assert!((1..=4).contains(&limit.current()));
+        }
+        assert_eq!(limit.current(), 4);
+        assert_eq!(limit.permits.available_permits(), 4);
+    }
+
+    #[tokio::test]
+    async fn adaptive_limit_throttling_resets_the_success_streak() {
+        let limit = AdaptiveLimit::new(4);
+        limit.throttled(limit.acquire().await);
+        for _ in 0..RECOVER_AFTER - 1 {
+            limit.succeeded(limit.acquire().await);
+        }
+        limit.throttled(limit.acquire().await);
+        assert_eq!(limit.current(), 1);
+        limit.succeeded(limit.acquire().await);
+        assert_eq!(limit.current(), 1);
+    }
+
+    #[tokio::test]
+    async fn adaptive_limit_keeps_permits_held_by_other_requests() {
+        let limit = AdaptiveLimit::new(8);
+        let held: Vec<_> = acquire_many(&limit, 7).await;
+        limit.throttled(limit.acquire().await);
+        // Only the throttled request's permit could be taken back, so the cap drops by one
+        assert_eq!(limit.current(), 7);
+        drop(held);
+        assert_eq!(limit.permits.available_permits(), 7);
+    }
+
+    async fn acquire_many(limit: &AdaptiveLimit, count: usize) -> Vec<SemaphorePermit<'_>> {
+        let mut permits = Vec::new();
+        for _ in 0..count {
+            permits.push(limit.acquire().await);
+        }
+        permits
+    }
+
+    #[test]
+    fn backoff_delay_is_capped() {
+        for attempt in [0, 1, 5, 10, 20, 31, 32, 64, u32::MAX] {
+            for _ in 0..100 {
+                assert!(backoff_delay(attempt) <= MAX_DELAY);
+            }
+        }
+        for _ in 0..100 {
+            assert!(backoff_delay(0) <= BASE_DELAY);
+            assert!(backoff_delay(2) <= BASE_DELAY * 4);
+        }
+    }
+}
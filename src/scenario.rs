@@ -0,0 +1,142 @@
+use anyhow::{bail, Context};
+use clap::{CommandFactory, Parser};
+use colorize::AnsiColor;
+use serde::Deserialize;
+use serde_json::Value;
+use std::path::Path;
+
+use crate::cli::{Command, GlobalArgs};
+use crate::config::RunConfig;
+use crate::interrupt;
+use crate::output::status;
+use crate::session;
+
+// A sequence of operations read from a JSON file passed with --scenario, run one after another
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Scenario {
+    steps: Vec<ScenarioStep>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScenarioStep {
+    // Subcommand name, eg. create-objects
+    operation: String,
+    // The subcommand's options, named as on the command line without the leading dashes, eg.
+    // {"count": "10k", "size": "4KB"}. Flags are set with true.
+    #[serde(default)]
+    args: serde_json::Map<String, Value>,
+    // Whether the steps after this one still run if it fails
+    #[serde(default)]
+    continue_on_error: bool,
+}
+
+// Steps are parsed the same way as a subcommand typed on the command line, so they accept the same
+// options and are validated the same way
+#[derive(Parser, Debug)]
+#[command(no_binary_name = true)]
+struct StepCommand {
+    #[command(subcommand)]
+    command: Command,
+}
+
+// Every step is parsed before the first one runs, so a mistake in a late step doesn't leave the
+// scenario half finished. The session summary at the end covers every step that ran.
+pub async fn run_scenario(global: GlobalArgs, config: RunConfig, path: &Path) -> anyhow::Result<()> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Unable to read scenario file {0}", path.display()))?;
+    let scenario: Scenario = serde_json::from_str(&contents)
+        .with_context(|| format!("Invalid scenario file {0}", path.display()))?;
+    if scenario.steps.is_empty() {
+        bail!("Scenario file {0} doesn't contain any steps", path.display());
+    }
+
+    let mut commands = Vec::with_capacity(scenario.steps.len());
+    for (index, step) in scenario.steps.iter().enumerate() {
+        let step_args = step_arguments(step).with_context(|| format!("Invalid step {0} ({1})", index + 1, step.operation))?;
+        let parsed = StepCommand::try_parse_from(step_args)
+            .map_err(|err| anyhow::anyhow!("Invalid step {0} ({1}): {2}", index + 1, step.operation, err.to_string().trim()))?;
+        commands.push(parsed.command);
+    }
+
+    let step_count = commands.len();
+    for (index, (step, command)) in scenario.steps.iter().zip(commands).enumerate() {
+        status!("{0}", format!("Step {0} of {1}: {2}", index + 1, step_count, step.operation).bold());
+        let start_time = session::begin_operation();
+        interrupt::reset_interrupted();
+
+        let step_result = crate::run_command(global.clone(), &config, command).await;
+        let outcome = match &step_result {
+            Err(_) => session::Outcome::Failed,
+            Ok(()) if interrupt::last_operation_interrupted() => session::Outcome::Interrupted,
+            Ok(()) => session::Outcome::Completed,
+        };
+        session::end_operation(&step.operation, start_time, outcome);
+
+        match step_result {
+            Err(err) if step.continue_on_error => eprintln!("{0}", format!("Error: {0:#}", err).red()),
+            Err(err) => {
+                session::print_report();
+                return Err(err.context(format!("Step {0} ({1}) failed", index + 1, step.operation)));
+            }
+            // Ctrl-C stops the scenario as well as the step, rather than moving on to the next one
+            Ok(()) if outcome == session::Outcome::Interrupted => break,
+            Ok(()) => {}
+        }
+    }
+    session::print_report();
+    Ok(())
+}
+
+// Turns a step into the arguments it would have on the command line. Whether true adds a bare flag or
+// a value depends on the option, so each one is looked up in the subcommand's definition.
+fn step_arguments(step: &ScenarioStep) -> anyhow::Result<Vec<String>> {
+    let command = StepCommand::command();
+    let Some(subcommand) = command.find_subcommand(&step.operation) else {
+        bail!("Unknown operation {0}", step.operation);
+    };
+
+    let mut arguments = vec![step.operation.clone()];
+    for (name, value) in &step.args {
+        let long_name = name.replace('_', "-");
+        let Some(argument) = subcommand.get_arguments().find(|argument| argument.get_long() == Some(long_name.as_str())) else {
+            bail!("Unknown option {0}", name);
+        };
+        let value = match value {
+            Value::String(value) => value.clone(),
+            Value::Number(value) => value.to_string(),
+            Value::Bool(value) if !argument.get_action().takes_values() => {
+                if *value {
+                    arguments.push(format!("--{0}", long_name));
+                }
+                continue;
+            }
+            Value::Bool(value) => value.to_string(),
+            // Repeatable options such as metadata are given once for each entry
+            Value::Array(values) if matches!(argument.get_action(), clap::ArgAction::Append) => {
+                for value in values {
+                    arguments.push(format!("--{0}", long_name));
+                    arguments.push(match value {
+                        Value::String(value) => value.clone(),
+                        other => other.to_string(),
+                    });
+                }
+                continue;
+            }
+            // Other lists are for options that take several comma-separated values, such as page-sizes
+            Value::Array(values) => values.iter()
+                .map(|value| match value {
+                    Value::String(value) => value.clone(),
+                    other => other.to_string(),
+                })
+                .collect::<Vec<String>>()
+                .join(","),
+            Value::Null => continue,
+            Value::Object(_) => bail!("Option {0} can't be an object", name),
+        };
+        arguments.push(format!("--{0}", long_name));
+        arguments.push(value);
+    }
+    Ok(arguments)
+}
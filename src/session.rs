@@ -0,0 +1,89 @@
+use colorize::AnsiColor;
+use std::{sync::Mutex, time::{Duration, Instant}};
+
+// Operations run from the interactive menu so far, reported when the session ends
+static OPERATIONS: Mutex<Vec<SessionEntry>> = Mutex::new(vec![]);
+
+// Object counts from every summary printed by the operation currently running. Each operation
+// prints at least one summary, and the mixed workload prints one for reads and one for writes.
+static SUMMARY_COUNTS: Mutex<(u64, u64)> = Mutex::new((0, 0));
+
+// How an operation run from the menu ended
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Completed,
+    Interrupted,
+    Failed,
+}
+
+impl std::fmt::Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Outcome::Completed => "Completed",
+            Outcome::Interrupted => "Interrupted",
+            Outcome::Failed => "Failed",
+        };
+        write!(f, "{0}", name)
+    }
+}
+
+struct SessionEntry {
+    operation: String,
+    duration: Duration,
+    succeeded: u64,
+    failed: u64,
+    outcome: Outcome,
+}
+
+// Marks the start of an operation, returning the time it started
+pub fn begin_operation() -> Instant {
+    *SUMMARY_COUNTS.lock().unwrap() = (0, 0);
+    Instant::now()
+}
+
+// Adds the counts from a run summary to the operation currently running
+pub fn record_summary(succeeded: u64, failed: u64) {
+    let mut counts = SUMMARY_COUNTS.lock().unwrap();
+    counts.0 += succeeded;
+    counts.1 += failed;
+}
+
+pub fn end_operation(operation: &str, start_time: Instant, outcome: Outcome) {
+    let (succeeded, failed) = *SUMMARY_COUNTS.lock().unwrap();
+    OPERATIONS.lock().unwrap().push(SessionEntry {
+        operation: operation.to_string(),
+        duration: start_time.elapsed(),
+        succeeded,
+        failed,
+        outcome,
+    });
+}
+
+// Prints a table of every operation run this session, if there were any
+pub fn print_report() {
+    let operations = OPERATIONS.lock().unwrap();
+    if operations.is_empty() || crate::output::is_json() {
+        return;
+    }
+
+    println!("{0}", "Session summary".bold());
+    let header = format!("{0:<24} {1:>10} {2:>10} {3:>8}  {4}", "Operation", "Duration", "Succeeded", "Failed", "Outcome");
+    println!("  {0}", header.underlined());
+    for entry in operations.iter() {
+        let outcome = match entry.outcome {
+            Outcome::Completed => entry.outcome.to_string().green(),
+            Outcome::Interrupted => entry.outcome.to_string().yellow(),
+            Outcome::Failed => entry.outcome.to_string().red(),
+        };
+        // Padding is applied before coloring, since the escape codes would otherwise count towards the width
+        println!("  {0:<24} {1} {2} {3}  {4}",
+            entry.operation,
+            format!("{0:>9.2}s", entry.duration.as_secs_f64()).blue(),
+            format!("{0:>10}", entry.succeeded).green(),
+            format!("{0:>8}", entry.failed).red(),
+            outcome);
+    }
+
+    let total_duration: Duration = operations.iter().map(|entry| entry.duration).sum();
+    println!("  {0:<24} {1:>9.2}s", "Total", total_duration.as_secs_f64());
+}
@@ -0,0 +1,240 @@
+use rand::{rngs::SmallRng, Rng};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::info::format_size;
+use crate::{parse_size, MAX_OBJECT_SIZE};
+
+// Number of equal-width bins a uniform range is split into for the size histogram
+const UNIFORM_BINS: u64 = 5;
+
+// Sizes a uniform range is sampled at when averaging over it, which is close enough for estimates
+const UNIFORM_SAMPLES: u64 = 101;
+
+// Sizes of the objects written by a create run, picked afresh for every object
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SizeDistribution {
+    // Every object is the same size, eg. 4KB
+    Fixed(u64),
+    // Any size in the range is equally likely, eg. 4KB-1MB
+    Uniform { min: u64, max: u64 },
+    // Sizes are picked in proportion to their weights, eg. 4KB:70,1MB:25,100MB:5
+    Weighted(Vec<(u64, u64)>),
+}
+
+impl SizeDistribution {
+    pub fn parse(input: &str) -> Result<SizeDistribution, String> {
+        let size = |input: &str| match parse_size(input) {
+            Some(size) if size > MAX_OBJECT_SIZE => Err("object size cannot exceed 5TB, the S3 object size limit".to_string()),
+            Some(size) => Ok(size),
+            None => Err(format!("invalid size {0}, expected a number with an optional suffix, eg. 4KB, 1MB, 10MB", input.trim())),
+        };
+
+        if input.contains(':') {
+            let mut weighted = vec![];
+            // Sampling and the histogram add the weights up, so a total that doesn't fit is rejected here
+            let mut total_weight: u64 = 0;
+            for entry in input.split(',') {
+                let Some((entry_size, weight)) = entry.split_once(':') else {
+                    return Err(format!("expected size:weight, got {0}", entry.trim()));
+                };
+                let weight: u64 = weight.trim().parse().map_err(|_| format!("invalid weight {0}, expected a whole number", weight.trim()))?;
+                if weight == 0 {
+                    return Err("weights must be at least 1".to_string());
+                }
+                total_weight = total_weight.checked_add(weight).ok_or("the weights add up to too large a number")?;
+                weighted.push((size(entry_size)?, weight));
+            }
+            return Ok(SizeDistribution::Weighted(weighted));
+        }
+        match input.split_once('-') {
+            Some((min, max)) => {
+                let (min, max) = (size(min)?, size(max)?);
+                if min >= max {
+                    return Err("the first size of a range must be smaller than the second".to_string());
+                }
+                Ok(SizeDistribution::Uniform { min, max })
+            }
+            None => Ok(SizeDistribution::Fixed(size(input)?)),
+        }
+    }
+
+    pub fn sample(&self, rng: &mut SmallRng) -> u64 {
+        match self {
+            SizeDistribution::Fixed(size) => *size,
+            SizeDistribution::Uniform { min, max } => rng.gen_range(*min..=*max),
+            SizeDistribution::Weighted(weighted) => {
+                let total_weight: u64 = weighted.iter().map(|(_, weight)| weight).sum();
+                let mut pick = rng.gen_range(0..total_weight);
+                for (size, weight) in weighted {
+                    if pick < *weight {
+                        return *size;
+                    }
+                    pick -= weight;
+                }
+                unreachable!("pick is always below the total weight")
+            }
+        }
+    }
+
+    pub fn min(&self) -> u64 {
+        match self {
+            SizeDistribution::Fixed(size) => *size,
+            SizeDistribution::Uniform { min, .. } => *min,
+            SizeDistribution::Weighted(weighted) => weighted.iter().map(|(size, _)| *size).min().unwrap_or_default(),
+        }
+    }
+
+    pub fn max(&self) -> u64 {
+        match self {
+            SizeDistribution::Fixed(size) => *size,
+            SizeDistribution::Uniform { max, .. } => *max,
+            SizeDistribution::Weighted(weighted) => weighted.iter().map(|(size, _)| *size).max().unwrap_or_default(),
+        }
+    }
+
+    // Average of f over the object sizes, used for totals and cost estimates before any object is written
+    pub fn expected(&self, f: impl Fn(u64) -> f64) -> f64 {
+        match self {
+            SizeDistribution::Fixed(size) => f(*size),
+            SizeDistribution::Uniform { min, max } => {
+                let step = (max - min) as f64 / (UNIFORM_SAMPLES - 1) as f64;
+                (0..UNIFORM_SAMPLES).map(|sample| f(min + (sample as f64 * step) as u64)).sum::<f64>() / UNIFORM_SAMPLES as f64
+            }
+            SizeDistribution::Weighted(weighted) => {
+                let total_weight: u64 = weighted.iter().map(|(_, weight)| weight).sum();
+                weighted.iter().map(|(size, weight)| f(*size) * *weight as f64).sum::<f64>() / total_weight as f64
+            }
+        }
+    }
+
+    pub fn mean(&self) -> u64 {
+        self.expected(|size| size as f64).round() as u64
+    }
+
+    fn bin_count(&self) -> usize {
+        match self {
+            SizeDistribution::Fixed(_) => 1,
+            SizeDistribution::Uniform { .. } => UNIFORM_BINS as usize,
+            SizeDistribution::Weighted(weighted) => weighted.len(),
+        }
+    }
+
+    // Histogram bin a size falls in. Each weighted size has its own bin, and a size listed twice is counted in the first.
+    fn bin(&self, size: u64) -> usize {
+        match self {
+            SizeDistribution::Fixed(_) => 0,
+            SizeDistribution::Uniform { min, max } => ((size - min) as u128 * UNIFORM_BINS as u128 / (max - min + 1) as u128) as usize,
+            SizeDistribution::Weighted(weighted) => weighted.iter().position(|(weighted_size, _)| *weighted_size == size).unwrap_or_default(),
+        }
+    }
+
+    fn bin_label(&self, bin: usize) -> String {
+        match self {
+            SizeDistribution::Fixed(size) => format_size(*size),
+            SizeDistribution::Uniform { min, max } => {
+                let width = (max - min + 1) as u128;
+                let start = *min as u128 + width * bin as u128 / UNIFORM_BINS as u128;
+                let end = *min as u128 + width * (bin as u128 + 1) / UNIFORM_BINS as u128 - 1;
+                format!("{0}-{1}", format_size(start as u64), format_size(end as u64))
+            }
+            SizeDistribution::Weighted(weighted) => format_size(weighted[bin].0),
+        }
+    }
+}
+
+impl std::fmt::Display for SizeDistribution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SizeDistribution::Fixed(size) => write!(f, "{0}", format_size(*size)),
+            SizeDistribution::Uniform { min, max } => write!(f, "{0} to {1}", format_size(*min), format_size(*max)),
+            SizeDistribution::Weighted(weighted) => {
+                let total_weight: u64 = weighted.iter().map(|(_, weight)| weight).sum();
+                let entries: Vec<String> = weighted.iter()
+                    .map(|(size, weight)| format!("{0} ({1:.0}%)", format_size(*size), *weight as f64 * 100.0 / total_weight as f64))
+                    .collect();
+                write!(f, "{0}", entries.join(", "))
+            }
+        }
+    }
+}
+
+// Counts of the object sizes actually written, shared between workers
+pub struct SizeHistogram {
+    counts: Vec<AtomicU64>,
+}
+
+impl SizeHistogram {
+    pub fn new(sizes: &SizeDistribution) -> SizeHistogram {
+        SizeHistogram { counts: (0..sizes.bin_count()).map(|_| AtomicU64::new(0)).collect() }
+    }
+
+    pub fn record(&self, sizes: &SizeDistribution, size: u64) {
+        self.counts[sizes.bin(size)].fetch_add(1, Ordering::Relaxed);
+    }
+
+    // One entry per bin, eg. "4.00 KB: 702 (70.2%), 1.00 MB: 298 (29.8%)"
+    pub fn describe(&self, sizes: &SizeDistribution) -> String {
+        let counts: Vec<u64> = self.counts.iter().map(|count| count.load(Ordering::Relaxed)).collect();
+        let total = counts.iter().sum::<u64>().max(1);
+        counts.iter().enumerate()
+            .map(|(bin, count)| format!("{0}: {1} ({2:.1}%)", sizes.bin_label(bin), count, *count as f64 * 100.0 / total as f64))
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    #[test]
+    fn parse_fixed_size() {
+        assert_eq!(SizeDistribution::parse("4KB"), Ok(SizeDistribution::Fixed(4096)));
+    }
+
+    #[test]
+    fn parse_uniform_range() {
+        assert_eq!(SizeDistribution::parse("4KB-1MB"), Ok(SizeDistribution::Uniform { min: 4096, max: 1024 * 1024 }));
+    }
+
+    #[test]
+    fn parse_rejects_a_range_that_isnt_increasing() {
+        assert!(SizeDistribution::parse("1MB-4KB").is_err());
+        assert!(SizeDistribution::parse("1MB-1MB").is_err());
+    }
+
+    #[test]
+    fn parse_weighted_sizes() {
+        assert_eq!(SizeDistribution::parse("4KB:70, 1MB:30"), Ok(SizeDistribution::Weighted(vec![(4096, 70), (1024 * 1024, 30)])));
+    }
+
+    #[test]
+    fn parse_rejects_bad_weights() {
+        assert!(SizeDistribution::parse("4KB:0,1MB:1").is_err());
+        assert!(SizeDistribution::parse("4KB:many").is_err());
+        assert!(SizeDistribution::parse("4KB:70,1MB").is_err());
+    }
+
+    #[test]
+    fn parse_rejects_weights_that_overflow() {
+        assert!(SizeDistribution::parse("4KB:18446744073709551615,1MB:1").is_err());
+    }
+
+    #[test]
+    fn parse_rejects_sizes_over_the_s3_limit() {
+        assert!(SizeDistribution::parse("6TB").is_err());
+    }
+
+    #[test]
+    fn sample_stays_within_the_distribution() {
+        let mut rng = SmallRng::seed_from_u64(1);
+        assert_eq!(SizeDistribution::Fixed(4096).sample(&mut rng), 4096);
+
+        let uniform = SizeDistribution::Uniform { min: 10, max: 20 };
+        assert!((0..1000).all(|_| (10..=20).contains(&uniform.sample(&mut rng))));
+
+        let weighted = SizeDistribution::Weighted(vec![(4096, 1), (8192, u64::MAX - 1)]);
+        assert!((0..1000).all(|_| [4096, 8192].contains(&weighted.sample(&mut rng))));
+    }
+}
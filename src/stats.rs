@@ -0,0 +1,357 @@
+use colorize::AnsiColor;
+use serde::Serialize;
+use aws_sdk_s3::Client;
+use std::{cmp::Reverse, collections::{BTreeMap, HashMap}, sync::{atomic::{AtomicU64, Ordering}, Arc, Mutex}, time::Duration};
+
+// Whether each bucket's requests go through Transfer Acceleration, only recorded when --accelerate is given
+static ACCELERATION: Mutex<BTreeMap<String, bool>> = Mutex::new(BTreeMap::new());
+
+pub fn set_acceleration(bucket_name: &str, accelerated: bool) {
+    ACCELERATION.lock().unwrap().insert(bucket_name.to_string(), accelerated);
+}
+
+// Region each bucket's requests were sent to, recorded as its client is chosen so reports can include it
+static BUCKET_REGIONS: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
+
+pub fn set_bucket_region(bucket_name: &str, s3_client: &Client) {
+    if let Some(region) = s3_client.config().region() {
+        BUCKET_REGIONS.lock().unwrap().insert(bucket_name.to_string(), region.to_string());
+    }
+}
+
+// Collects per-request measurements from every worker taking part in a run
+#[derive(Default)]
+pub struct RunStats {
+    latencies: Mutex<Vec<Duration>>,
+    object_count: AtomicU64,
+    // Shared with the progress display, which samples it for live throughput
+    total_bytes: Arc<AtomicU64>,
+    retry_count: AtomicU64,
+    failures: Mutex<HashMap<String, u64>>,
+    verified_count: AtomicU64,
+    mismatch_count: AtomicU64,
+}
+
+impl RunStats {
+    // Records a single successful request for one object and the number of bytes it transferred
+    pub fn record(&self, latency: Duration, bytes: u64) {
+        self.latencies.lock().unwrap().push(latency);
+        self.object_count.fetch_add(1, Ordering::Relaxed);
+        self.total_bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    // Records a single successful request that acted on several objects at once, such as DeleteObjects
+    pub fn record_batch(&self, latency: Duration, object_count: u64) {
+        self.latencies.lock().unwrap().push(latency);
+        self.object_count.fetch_add(object_count, Ordering::Relaxed);
+    }
+
+    pub fn byte_counter(&self) -> Arc<AtomicU64> {
+        self.total_bytes.clone()
+    }
+
+    pub fn record_retry(&self) {
+        self.retry_count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    // Records an object that failed for good, grouped by the reason it failed
+    pub fn record_failure(&self, reason: String) {
+        *self.failures.lock().unwrap().entry(reason).or_default() += 1;
+    }
+
+    // Records an object whose stored checksum was read back and compared with the one sent
+    pub fn record_verification(&self, matched: bool) {
+        match matched {
+            true => self.verified_count.fetch_add(1, Ordering::Relaxed),
+            false => self.mismatch_count.fetch_add(1, Ordering::Relaxed),
+        };
+    }
+
+    pub fn summarize(&self, elapsed: Duration) -> RunSummary {
+        let mut latencies = self.latencies.lock().unwrap().clone();
+        latencies.sort();
+
+        let mut failures: Vec<(String, u64)> = self.failures.lock().unwrap().clone().into_iter().collect();
+        failures.sort_by_key(|(_, count)| Reverse(*count));
+
+        RunSummary {
+            failed_count: failures.iter().map(|(_, count)| count).sum(),
+            failures,
+            object_count: self.object_count.load(Ordering::Relaxed),
+            request_count: latencies.len() as u64,
+            total_bytes: self.total_bytes.load(Ordering::Relaxed),
+            retry_count: self.retry_count.load(Ordering::Relaxed),
+            verified_count: self.verified_count.load(Ordering::Relaxed),
+            mismatch_count: self.mismatch_count.load(Ordering::Relaxed),
+            elapsed,
+            settings: vec![],
+            min: latencies.first().copied().unwrap_or_default(),
+            mean: mean(&latencies),
+            p50: percentile(&latencies, 50.0),
+            p90: percentile(&latencies, 90.0),
+            p95: percentile(&latencies, 95.0),
+            p99: percentile(&latencies, 99.0),
+            max: latencies.last().copied().unwrap_or_default(),
+        }
+    }
+}
+
+fn mean(latencies: &[Duration]) -> Duration {
+    match latencies.len() {
+        0 => Duration::ZERO,
+        count => latencies.iter().sum::<Duration>() / count as u32,
+    }
+}
+
+// Nearest-rank percentile over an already sorted list of samples
+fn percentile(sorted_latencies: &[Duration], percentile: f64) -> Duration {
+    if sorted_latencies.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = (percentile / 100.0 * sorted_latencies.len() as f64).ceil() as usize;
+    sorted_latencies[rank.clamp(1, sorted_latencies.len()) - 1]
+}
+
+// Aggregate results of a run, ready to be displayed
+pub struct RunSummary {
+    pub object_count: u64,
+    pub request_count: u64,
+    pub failed_count: u64,
+    // Distinct failure reasons with the number of requests that failed for each, most frequent first
+    pub failures: Vec<(String, u64)>,
+    pub total_bytes: u64,
+    pub retry_count: u64,
+    // Objects stored successfully whose checksum did or didn't match when read back. Mismatches are
+    // correctness failures, kept apart from failed requests.
+    pub verified_count: u64,
+    pub mismatch_count: u64,
+    pub elapsed: Duration,
+    // Extra labelled values shown with the results, such as run parameters or operation-specific counts
+    pub settings: Vec<(String, String)>,
+    pub min: Duration,
+    pub mean: Duration,
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+impl RunSummary {
+    pub fn with_setting(mut self, name: &str, value: impl ToString) -> RunSummary {
+        self.settings.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn objects_per_second(&self) -> f64 {
+        self.object_count as f64 / self.elapsed.as_secs_f64()
+    }
+
+    pub fn megabytes_per_second(&self) -> f64 {
+        self.total_bytes as f64 / (1024.0 * 1024.0) / self.elapsed.as_secs_f64()
+    }
+
+    // Share of objects that succeeded, out of those that either succeeded or failed for good
+    pub fn success_rate(&self) -> f64 {
+        match self.object_count + self.failed_count {
+            0 => 100.0,
+            attempted => self.object_count as f64 * 100.0 / attempted as f64,
+        }
+    }
+
+    // Labelled values shown with the results, led by whether the bucket's requests were accelerated
+    fn settings_for(&self, bucket_name: &str) -> Vec<(String, String)> {
+        let accelerated = ACCELERATION.lock().unwrap().get(bucket_name).copied();
+        accelerated.map(|accelerated| ("Accelerated".to_string(), if accelerated { "yes" } else { "no" }.to_string()))
+            .into_iter()
+            .chain(self.settings.iter().cloned())
+            .collect()
+    }
+
+    // Whether any objects were read back and checked, so the verification counts are worth showing
+    fn was_verified(&self) -> bool {
+        self.verified_count + self.mismatch_count > 0
+    }
+
+    // Prints the summary for people, or a RunReport in JSON mode
+    pub fn print(&self, operation: &str, bucket_name: &str) {
+        crate::session::record_summary(self.object_count, self.failed_count);
+        let report = self.report(operation, bucket_name);
+        crate::report::write(&report);
+        if crate::output::is_json() {
+            println!("{0}", serde_json::to_string(&report).unwrap());
+            return;
+        }
+
+        println!("{0}", format!("{0} summary", operation).bold());
+        for (name, value) in self.settings_for(bucket_name) {
+            println!("  {0:<14} {1}", name, value);
+        }
+        println!("  {0:<14} {1}", "Succeeded", self.object_count.to_string().green());
+        println!("  {0:<14} {1}", "Failed", self.failed_count.to_string().red());
+        let success_rate = format!("{0:.2}%", self.success_rate());
+        println!("  {0:<14} {1}", "Success rate", match self.failed_count {
+            0 => success_rate.green(),
+            _ => success_rate.yellow(),
+        });
+        println!("  {0:<14} {1}", "Total bytes", self.total_bytes.to_string().green());
+        println!("  {0:<14} {1}", "Requests", self.request_count.to_string().green());
+        println!("  {0:<14} {1}", "Retries", self.retry_count.to_string().yellow());
+        if self.was_verified() {
+            println!("  {0:<14} {1}", "Verified", self.verified_count.to_string().green());
+            println!("  {0:<14} {1}", "Mismatched", self.mismatch_count.to_string().red());
+        }
+        println!("  {0:<14} {1}", "Elapsed", format!("{0:.2}s", self.elapsed.as_secs_f64()).green());
+        println!("  {0:<14} {1}", "Objects/sec", format!("{0:.2}", self.objects_per_second()).green());
+        println!("  {0:<14} {1}", "MB/sec", format!("{0:.2}", self.megabytes_per_second()).green());
+        println!("  {0:<14} min {1}  avg {2}  p50 {3}  p90 {4}  p95 {5}  p99 {6}  max {7}", "Latency",
+            format_latency(self.min).blue(),
+            format_latency(self.mean).blue(),
+            format_latency(self.p50).blue(),
+            format_latency(self.p90).blue(),
+            format_latency(self.p95).blue(),
+            format_latency(self.p99).blue(),
+            format_latency(self.max).blue());
+
+        if !self.failures.is_empty() {
+            println!("  Failure reasons");
+            for (reason, count) in &self.failures {
+                println!("  {0:>8}  {1}", count.to_string().red(), reason);
+            }
+        }
+    }
+
+    pub fn report(&self, operation: &str, bucket_name: &str) -> RunReport {
+        RunReport {
+            operation: operation.to_string(),
+            bucket: bucket_name.to_string(),
+            region: BUCKET_REGIONS.lock().unwrap().get(bucket_name).cloned(),
+            succeeded: self.object_count,
+            failed: self.failed_count,
+            success_rate_percent: self.success_rate(),
+            requests: self.request_count,
+            retries: self.retry_count,
+            total_bytes: self.total_bytes,
+            elapsed_seconds: self.elapsed.as_secs_f64(),
+            objects_per_second: self.objects_per_second(),
+            megabytes_per_second: self.megabytes_per_second(),
+            latency_ms: LatencyReport {
+                min: self.min.as_secs_f64() * 1000.0,
+                mean: self.mean.as_secs_f64() * 1000.0,
+                p50: self.p50.as_secs_f64() * 1000.0,
+                p90: self.p90.as_secs_f64() * 1000.0,
+                p95: self.p95.as_secs_f64() * 1000.0,
+                p99: self.p99.as_secs_f64() * 1000.0,
+                max: self.max.as_secs_f64() * 1000.0,
+            },
+            verification: self.was_verified().then_some(VerificationReport {
+                verified: self.verified_count,
+                mismatched: self.mismatch_count,
+            }),
+            settings: self.settings_for(bucket_name).into_iter()
+                .map(|(name, value)| (name.to_lowercase().replace(' ', "_"), value))
+                .collect(),
+            failures: self.failures.iter()
+                .map(|(reason, count)| FailureReport { reason: reason.clone(), count: *count })
+                .collect(),
+        }
+    }
+}
+
+// Machine-readable form of a RunSummary, emitted with --output json
+#[derive(Serialize)]
+pub struct RunReport {
+    pub operation: String,
+    pub bucket: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    pub succeeded: u64,
+    pub failed: u64,
+    pub success_rate_percent: f64,
+    pub requests: u64,
+    pub retries: u64,
+    pub total_bytes: u64,
+    pub elapsed_seconds: f64,
+    pub objects_per_second: f64,
+    pub megabytes_per_second: f64,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub settings: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verification: Option<VerificationReport>,
+    pub latency_ms: LatencyReport,
+    pub failures: Vec<FailureReport>,
+}
+
+#[derive(Serialize)]
+pub struct LatencyReport {
+    pub min: f64,
+    pub mean: f64,
+    pub p50: f64,
+    pub p90: f64,
+    pub p95: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+#[derive(Serialize)]
+pub struct VerificationReport {
+    pub verified: u64,
+    pub mismatched: u64,
+}
+
+#[derive(Serialize)]
+pub struct FailureReport {
+    pub reason: String,
+    pub count: u64,
+}
+
+fn format_latency(latency: Duration) -> String {
+    format!("{0:.1}ms", latency.as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn millis(count: u64) -> Vec<Duration> {
+        (1..=count).map(Duration::from_millis).collect()
+    }
+
+    #[test]
+    fn percentile_of_no_samples_is_zero() {
+        assert_eq!(percentile(&[], 50.0), Duration::ZERO);
+    }
+
+    #[test]
+    fn percentile_of_one_sample_is_that_sample() {
+        let latencies = millis(1);
+        assert_eq!(percentile(&latencies, 50.0), Duration::from_millis(1));
+        assert_eq!(percentile(&latencies, 99.0), Duration::from_millis(1));
+    }
+
+    #[test]
+    fn percentile_of_two_samples_uses_the_nearest_rank() {
+        let latencies = millis(2);
+        assert_eq!(percentile(&latencies, 50.0), Duration::from_millis(1));
+        assert_eq!(percentile(&latencies, 99.0), Duration::from_millis(2));
+    }
+
+    #[test]
+    fn percentile_of_a_hundred_samples_is_the_matching_rank() {
+        let latencies = millis(100);
+        assert_eq!(percentile(&latencies, 50.0), Duration::from_millis(50));
+        assert_eq!(percentile(&latencies, 99.0), Duration::from_millis(99));
+        assert_eq!(percentile(&latencies, 100.0), Duration::from_millis(100));
+    }
+
+    #[test]
+    fn acceleration_is_only_reported_for_the_bucket_it_was_recorded_for() {
+        set_acceleration("s3-stress-stats-test-accelerated", true);
+        let summary = RunStats::default().summarize(Duration::from_secs(1)).with_setting("Workers", 4);
+        let accelerated = summary.report("Test", "s3-stress-stats-test-accelerated");
+        assert_eq!(accelerated.settings.get("accelerated").map(String::as_str), Some("yes"));
+        assert_eq!(accelerated.settings.get("workers").map(String::as_str), Some("4"));
+        let other = summary.report("Test", "s3-stress-stats-test-other");
+        assert_eq!(other.settings.get("accelerated"), None);
+    }
+}
@@ -0,0 +1,265 @@
+use anyhow::Context;
+use aws_sdk_s3::{primitives::ByteStream, types::{CompletedMultipartUpload, CompletedPart}, Client};
+use aws_smithy_types::byte_stream::Length;
+use colorize::AnsiColor;
+use futures::{future::join_all, stream, StreamExt};
+use std::{path::{Path, PathBuf}, sync::{atomic::{AtomicU64, Ordering}, Arc}, time::Instant};
+use tokio_util::sync::CancellationToken;
+
+use crate::cli::UploadDirectoryArgs;
+use crate::interrupt;
+use crate::multipart;
+use crate::output::status;
+use crate::stats::RunStats;
+use crate::{new_progress_bar, parse_size, prompt_worker_count, retry, track_progress, validate_size, MAX_SINGLE_PUT_SIZE};
+
+// Number of parts of a single file uploaded at the same time
+const PART_CONCURRENCY: usize = 4;
+
+// A file found under the upload directory, with the key it's stored under
+struct LocalFile {
+    path: PathBuf,
+    key: String,
+    size: u64,
+}
+
+// Settings and shared counters for every worker taking part in a directory upload
+struct UploadDirectoryContext {
+    bucket_name: String,
+    files: Vec<LocalFile>,
+    multipart_threshold: u64,
+    // Position of the next file to upload, shared so workers pick up whichever file is next
+    next_index: AtomicU64,
+    cancel: CancellationToken,
+    completed_count: Arc<AtomicU64>,
+    run_stats: RunStats,
+}
+
+#[tracing::instrument(skip_all, fields(bucket = %bucket_name))]
+pub async fn operation_upload_directory(s3_client: Client, bucket_name: String, args: UploadDirectoryArgs) -> anyhow::Result<()> {
+    let directory = match args.directory {
+        Some(directory) => directory,
+        None => inquire::Text::new("Which local directory should I upload?")
+            .with_default(".")
+            .prompt()?.into(),
+    };
+    if !directory.is_dir() {
+        anyhow::bail!("{0} is not a directory", directory.display());
+    }
+
+    let prefix = match args.prefix {
+        Some(prefix) => prefix,
+        None => inquire::Text::new("Key prefix for the uploaded files (leave blank for none)").prompt()?,
+    };
+
+    let files = collect_files(&directory, &prefix)?;
+    if files.is_empty() {
+        status!("{0}", "No files found to upload".blue());
+        return Ok(());
+    }
+    let directory_size: u64 = files.iter().map(|file| file.size).sum();
+    status!("Found {0} files totalling {1} bytes", files.len(), directory_size);
+
+    let worker_count = match args.concurrency {
+        Some(concurrency) => concurrency,
+        None => prompt_worker_count()?,
+    };
+
+    // Only worth asking about when some file is big enough to be split into parts
+    let largest_file = files.iter().map(|file| file.size).max().unwrap_or_default();
+    let multipart_threshold = match args.multipart_threshold {
+        Some(threshold) => threshold,
+        None if largest_file <= multipart::MIN_PART_SIZE => MAX_SINGLE_PUT_SIZE,
+        None => {
+            let threshold = inquire::Text::new("Use multipart uploads for files larger than")
+                .with_default("100MB")
+                .with_validator(validate_size)
+                .prompt()?;
+            parse_size(&threshold).context("Invalid multipart threshold")?
+        }
+    };
+    // A single PUT can't carry more than 5GB, so larger files always go through multipart
+    let multipart_threshold = multipart_threshold.clamp(multipart::MIN_PART_SIZE, MAX_SINGLE_PUT_SIZE);
+
+    let operation = interrupt::start_operation();
+    let file_count = files.len() as u64;
+    let context = Arc::new(UploadDirectoryContext {
+        bucket_name,
+        files,
+        multipart_threshold,
+        next_index: AtomicU64::new(0),
+        cancel: operation.token(),
+        completed_count: Arc::new(AtomicU64::new(0)),
+        run_stats: RunStats::default(),
+    });
+
+    let progress = new_progress_bar(file_count);
+    let progress_task = tokio::spawn(track_progress(progress.clone(), context.completed_count.clone(), vec![context.run_stats.byte_counter()]));
+
+    let start_time = Instant::now();
+
+    let mut join_handle_list = vec![];
+    for _ in 0..worker_count.min(file_count as u32) {
+        join_handle_list.push(tokio::spawn(upload_worker(s3_client.clone(), context.clone())));
+    }
+    join_all(join_handle_list).await;
+
+    progress_task.abort();
+    progress.finish_and_clear();
+    interrupt::report_if_interrupted(&context.cancel);
+    context.run_stats.summarize(start_time.elapsed())
+        .with_setting("Directory", directory.display())
+        .with_setting("Files", file_count)
+        .print("Upload directory", &context.bucket_name);
+    Ok(())
+}
+
+// Walks the directory tree, keying each file by its path relative to the directory. Keys always use
+// forward slashes so the layout is the same whichever platform uploaded it. Symbolic links are skipped,
+// since following them could loop or pull in files from outside the directory.
+fn collect_files(directory: &Path, prefix: &str) -> anyhow::Result<Vec<LocalFile>> {
+    let mut files = vec![];
+    let mut pending_directories = vec![directory.to_path_buf()];
+
+    while let Some(current) = pending_directories.pop() {
+        let entries = std::fs::read_dir(&current)
+            .with_context(|| format!("Unable to read directory {0}", current.display()))?;
+        for entry in entries {
+            let entry = entry.with_context(|| format!("Unable to read directory {0}", current.display()))?;
+            let file_type = entry.file_type()?;
+            let path = entry.path();
+            if file_type.is_dir() {
+                pending_directories.push(path);
+            }
+            else if file_type.is_file() {
+                let relative_path = path.strip_prefix(directory).unwrap_or(&path);
+                let relative_key: Vec<String> = relative_path.components()
+                    .map(|component| component.as_os_str().to_string_lossy().into_owned())
+                    .collect();
+                files.push(LocalFile {
+                    key: format!("{0}{1}", prefix, relative_key.join("/")),
+                    size: entry.metadata()?.len(),
+                    path,
+                });
+            }
+        }
+    }
+    files.sort_by(|a, b| a.key.cmp(&b.key));
+    Ok(files)
+}
+
+async fn upload_worker(s3_client: Client, context: Arc<UploadDirectoryContext>) {
+    loop {
+        if context.cancel.is_cancelled() {
+            break;
+        }
+        let index = context.next_index.fetch_add(1, Ordering::Relaxed) as usize;
+        let Some(file) = context.files.get(index) else {
+            break;
+        };
+
+        let request_start = Instant::now();
+        let upload_result = match file.size > context.multipart_threshold {
+            true => upload_file_multipart(&s3_client, &context, file).await,
+            false => upload_file(&s3_client, &context, file).await,
+        };
+
+        match upload_result {
+            Ok(()) => context.run_stats.record(request_start.elapsed(), file.size),
+            Err(reason) => context.run_stats.record_failure(reason),
+        }
+        context.completed_count.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+// Streams the file from disk rather than reading it into memory first
+async fn upload_file(s3_client: &Client, context: &UploadDirectoryContext, file: &LocalFile) -> Result<(), String> {
+    let body = ByteStream::from_path(&file.path).await
+        .map_err(|err| format!("Unable to read {0}: {1}", file.path.display(), err))?;
+    s3_client.put_object()
+        .bucket(&context.bucket_name)
+        .key(&file.key)
+        .body(body)
+        .send().await
+        .map(|_| ())
+        .map_err(|err| retry::error_reason(&err))
+}
+
+// Uploads a large file in parts, each streamed from its own byte range of the file. If any part fails,
+// the upload is aborted so the parts already stored don't linger and incur storage charges.
+async fn upload_file_multipart(s3_client: &Client, context: &UploadDirectoryContext, file: &LocalFile) -> Result<(), String> {
+    let create_output = s3_client.create_multipart_upload()
+        .bucket(&context.bucket_name)
+        .key(&file.key)
+        .send().await
+        .map_err(|err| retry::error_reason(&err))?;
+    let upload_id = create_output.upload_id.unwrap_or_default();
+
+    let part_size = multipart::part_size(file.size, multipart::DEFAULT_PART_SIZE);
+    let part_count = file.size.div_ceil(part_size);
+
+    let part_results: Vec<Result<CompletedPart, String>> = stream::iter(1..=part_count)
+        .map(|part_number| {
+            let upload_id = &upload_id;
+            async move {
+                if context.cancel.is_cancelled() {
+                    return Err("Interrupted".to_string());
+                }
+                // Only the final part can be smaller than the others
+                let offset = (part_number - 1) * part_size;
+                let length = part_size.min(file.size - offset);
+                let body = ByteStream::read_from()
+                    .path(&file.path)
+                    .offset(offset)
+                    .length(Length::Exact(length))
+                    .build().await
+                    .map_err(|err| format!("Unable to read {0}: {1}", file.path.display(), err))?;
+
+                let upload_output = s3_client.upload_part()
+                    .bucket(&context.bucket_name)
+                    .key(&file.key)
+                    .upload_id(upload_id)
+                    .part_number(part_number as i32)
+                    .body(body)
+                    .send().await
+                    .map_err(|err| retry::error_reason(&err))?;
+
+                Ok(CompletedPart::builder()
+                    .part_number(part_number as i32)
+                    .set_e_tag(upload_output.e_tag)
+                    .build())
+            }
+        })
+        .buffer_unordered(PART_CONCURRENCY)
+        .collect().await;
+
+    let mut completed_parts = match part_results.into_iter().collect::<Result<Vec<CompletedPart>, String>>() {
+        Ok(parts) => parts,
+        Err(reason) => {
+            abort_upload(s3_client, context, &file.key, &upload_id).await;
+            return Err(reason);
+        }
+    };
+    completed_parts.sort_by_key(|part| part.part_number);
+
+    let complete_result = s3_client.complete_multipart_upload()
+        .bucket(&context.bucket_name)
+        .key(&file.key)
+        .upload_id(&upload_id)
+        .multipart_upload(CompletedMultipartUpload::builder().set_parts(Some(completed_parts)).build())
+        .send().await;
+
+    if let Err(err) = complete_result {
+        abort_upload(s3_client, context, &file.key, &upload_id).await;
+        return Err(retry::error_reason(&err));
+    }
+    Ok(())
+}
+
+async fn abort_upload(s3_client: &Client, context: &UploadDirectoryContext, key: &str, upload_id: &str) {
+    let _abort_result = s3_client.abort_multipart_upload()
+        .bucket(&context.bucket_name)
+        .key(key)
+        .upload_id(upload_id)
+        .send().await;
+}